@@ -0,0 +1,58 @@
+//! Numeric sort of 2M integers in 0..1000: the integer path, which picks
+//! counting sort for so small a range, against the LSD radix sort on the
+//! same `(value, index)` pairs.
+//!
+//! Run with `cargo bench --bench counting_sort`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use gnu_sort::radix_sort::RadixSort;
+use gnu_sort::zero_copy::{parse_lines, Line};
+
+const LINES: u64 = 2_000_000;
+const RANGE: u64 = 1000;
+const ROUNDS: usize = 3;
+
+/// Fastest of `ROUNDS` sorts of a fresh copy of `lines`
+fn best_of(lines: &[Line], sort: impl Fn(&mut [Line])) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let mut lines = lines.to_vec();
+            let start = Instant::now();
+            sort(&mut lines);
+            black_box(&lines);
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    let mut data = Vec::new();
+    for n in 0..LINES {
+        let value = (n.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32) % RANGE;
+        data.extend_from_slice(format!("{value}\n").as_bytes());
+    }
+    let lines = parse_lines(&data);
+    let sorter = RadixSort::new(false);
+
+    // Both keep equal values in input order, so they agree line for line
+    let mut counted = lines.clone();
+    sorter.sort_numeric_lines(&mut counted);
+    let mut radix = lines.clone();
+    assert!(sorter.stable_sort_integer_lines(&mut radix, false));
+    assert!(counted
+        .iter()
+        .zip(&radix)
+        .all(|(a, b)| std::ptr::eq(unsafe { a.as_bytes() }, unsafe { b.as_bytes() })));
+
+    let counting = best_of(&lines, |lines| sorter.sort_numeric_lines(lines));
+    let lsd = best_of(&lines, |lines| {
+        sorter.stable_sort_integer_lines(lines, false);
+    });
+
+    println!("{} integers in 0..{RANGE}", lines.len());
+    println!("LSD radix:     {lsd:>10.2?}");
+    println!("counting sort: {counting:>10.2?}");
+}
@@ -0,0 +1,56 @@
+//! External sort of a 64MB file split into 1MB chunks: the final k-way merge
+//! alone, against a first pass merging groups of chunks on every rayon
+//! thread.
+//!
+//! Run with `cargo bench --bench external_merge`.
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use gnu_sort::external_sort::ExternalSort;
+
+const INPUT_SIZE: usize = 64 * 1024 * 1024;
+const CHUNK_MB: usize = 1;
+const ROUNDS: usize = 3;
+
+/// Fastest of `ROUNDS` sorts with `threads` merge threads
+fn best_of(input: &std::path::Path, output: &std::path::Path, threads: usize) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let sorter = ExternalSort::new(CHUNK_MB, true, false, None)
+                .expect("temporary directory")
+                .with_merge_threads(threads);
+            let start = Instant::now();
+            sorter
+                .sort_file(input, output, false, false)
+                .expect("external sort");
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    let dir = tempfile::tempdir().expect("temporary directory");
+    let input = dir.path().join("input.txt");
+    let output = dir.path().join("output.txt");
+
+    let mut data = Vec::with_capacity(INPUT_SIZE + 64);
+    let mut n = 0u64;
+    while data.len() < INPUT_SIZE {
+        let value = n.wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        writeln!(data, "{value:016x} line {n}").unwrap();
+        n += 1;
+    }
+    std::fs::write(&input, &data).expect("write input");
+
+    let threads = rayon::current_num_threads();
+    let sequential = best_of(&input, &output, 1);
+    let expected = std::fs::read(&output).expect("read output");
+    let parallel = best_of(&input, &output, threads);
+    assert!(std::fs::read(&output).expect("read output") == expected);
+
+    println!("{n} lines in {} MB", data.len() / (1024 * 1024));
+    println!("single merge:              {sequential:>10.2?}");
+    println!("grouped merge ({threads:>2} threads): {parallel:>10.2?}");
+}
@@ -0,0 +1,84 @@
+//! Keyed sort of 2M lines drawn from 500 distinct ones: `compare_with_keys`,
+//! which returns early for byte-identical lines, against the same keys
+//! extracted and compared for every pair.
+//!
+//! Run with `cargo bench --bench keyed_duplicates`.
+
+use std::cmp::Ordering;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use gnu_sort::config::{SortConfig, SortKey};
+use gnu_sort::zero_copy::{parse_lines, Line};
+
+const LINES: u64 = 2_000_000;
+const DISTINCT: u64 = 500;
+const ROUNDS: usize = 3;
+
+/// `compare_with_keys` for plain text keys without the byte-equality check
+fn compare_every_key(a: &Line, b: &Line, keys: &[SortKey], config: &SortConfig) -> Ordering {
+    for key in keys {
+        let a_key = a.extract_key_with_config(key, None, config);
+        let b_key = b.extract_key_with_config(key, None, config);
+        let cmp = a_key.unwrap_or_default().cmp(b_key.unwrap_or_default());
+        if cmp != Ordering::Equal {
+            return cmp;
+        }
+    }
+    unsafe { a.as_bytes().cmp(b.as_bytes()) }
+}
+
+/// Fastest of `ROUNDS` sorts of a fresh copy of `lines`
+fn best_of(lines: &[Line], compare: impl Fn(&Line, &Line) -> Ordering) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let mut lines = lines.to_vec();
+            let start = Instant::now();
+            lines.sort_by(&compare);
+            black_box(&lines);
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    // Log-style records, most of them repeats of a few hundred lines
+    let mut data = Vec::new();
+    for n in 0..LINES {
+        let id = n.wrapping_mul(0x9e37_79b9_7f4a_7c15) % DISTINCT;
+        data.extend_from_slice(
+            format!(
+                "host{:02} service{:03} status={} request {id}\n",
+                id % 7,
+                id % 97,
+                id % 5
+            )
+            .as_bytes(),
+        );
+    }
+    let lines = parse_lines(&data);
+
+    let config = SortConfig::default();
+    let keys = ["2,2", "1,1", "3,3"].map(|spec| SortKey::parse(spec).expect("sort key"));
+
+    // Both orders agree before either is timed
+    let mut shortcut = lines.clone();
+    shortcut.sort_by(|a, b| a.compare_with_keys(b, &keys, None, &config));
+    let mut every_key = lines.clone();
+    every_key.sort_by(|a, b| compare_every_key(a, b, &keys, &config));
+    assert!(shortcut
+        .iter()
+        .zip(&every_key)
+        .all(|(a, b)| unsafe { a.as_bytes() == b.as_bytes() }));
+
+    let early = best_of(&lines, |a, b| a.compare_with_keys(b, &keys, None, &config));
+    let full = best_of(&lines, |a, b| compare_every_key(a, b, &keys, &config));
+
+    println!(
+        "{} lines, {DISTINCT} distinct, keys -k2,2 -k1,1 -k3,3",
+        lines.len()
+    );
+    println!("every key:    {full:>10.2?}");
+    println!("early return: {early:>10.2?}");
+}
@@ -0,0 +1,81 @@
+//! Byte-order sort of 1M random strings: the MSD radix sort that large
+//! plain `sort` inputs take, against the comparison sort it replaces.
+//!
+//! Run with `cargo bench --bench msd_radix`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use gnu_sort::radix_sort::RadixSort;
+use gnu_sort::zero_copy::{parse_lines, Line};
+
+const LINES: u64 = 1_000_000;
+const ROUNDS: usize = 3;
+
+/// Fastest of `ROUNDS` sorts of a fresh copy of `lines`
+fn best_of(lines: &[Line], sort: impl Fn(&mut [Line])) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let mut lines = lines.to_vec();
+            let start = Instant::now();
+            sort(&mut lines);
+            black_box(&lines);
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// Plain byte-wise order, as the comparison sort uses it
+fn compare_bytes(a: &Line, b: &Line) -> std::cmp::Ordering {
+    unsafe { a.as_bytes().cmp(b.as_bytes()) }
+}
+
+fn main() {
+    // Random lowercase strings of 4 to 31 bytes
+    let mut data = Vec::new();
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let mut next = || {
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        state
+    };
+    for _ in 0..LINES {
+        let len = 4 + next() % 28;
+        for _ in 0..len {
+            data.push(b'a' + (next() % 26) as u8);
+        }
+        data.push(b'\n');
+    }
+    let lines = parse_lines(&data);
+
+    // Both orders agree before either is timed
+    for parallel in [false, true] {
+        let mut radix = lines.clone();
+        RadixSort::new(parallel).sort_lexicographic_lines(&mut radix);
+        let mut compared = lines.clone();
+        compared.sort_unstable_by(compare_bytes);
+        assert!(radix
+            .iter()
+            .zip(&compared)
+            .all(|(a, b)| unsafe { a.as_bytes() == b.as_bytes() }));
+    }
+
+    let comparison = best_of(&lines, |lines| lines.sort_unstable_by(compare_bytes));
+    let sequential = best_of(&lines, |lines| {
+        RadixSort::new(false).sort_lexicographic_lines(lines)
+    });
+    let parallel = best_of(&lines, |lines| {
+        RadixSort::new(true).sort_lexicographic_lines(lines)
+    });
+
+    println!(
+        "{} random strings, {} MB",
+        lines.len(),
+        data.len() / (1024 * 1024)
+    );
+    println!("comparison sort:     {comparison:>10.2?}");
+    println!("MSD radix:           {sequential:>10.2?}");
+    println!("MSD radix, parallel: {parallel:>10.2?}");
+}
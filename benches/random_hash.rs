@@ -0,0 +1,89 @@
+//! Hashing 2M lines the way `-R` groups them: std's SipHash against the
+//! in-tree `FastHasher` that the `fast-hash` feature switches to.
+//!
+//! Run with `cargo bench --bench random_hash`.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use gnu_sort::hash_sort::FastHasher;
+use gnu_sort::zero_copy::parse_lines;
+
+const LINES: u64 = 2_000_000;
+const ROUNDS: usize = 3;
+
+/// Hash of `line` under `seed`, as the random sort computes it
+fn hash_line<H: Hasher + Default>(seed: u64, line: &[u8]) -> u64 {
+    let mut hasher = H::default();
+    seed.hash(&mut hasher);
+    line.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Fastest of `ROUNDS` passes hashing every line
+fn best_of<H: Hasher + Default>(lines: &[&[u8]]) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            for line in lines {
+                black_box(hash_line::<H>(7, line));
+            }
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+fn main() {
+    // Log-style records, every one of them distinct
+    let mut data = Vec::new();
+    for n in 0..LINES {
+        data.extend_from_slice(
+            format!("host{:02} service{:03} request {n}\n", n % 7, n % 97).as_bytes(),
+        );
+    }
+    let parsed = parse_lines(&data);
+    let lines: Vec<&[u8]> = parsed
+        .iter()
+        .map(|line| unsafe { line.as_bytes() })
+        .collect();
+
+    // Distinct lines must keep distinct hashes for grouping to stay exact
+    for (name, distinct) in [
+        (
+            "sip",
+            lines
+                .iter()
+                .map(|line| hash_line::<DefaultHasher>(7, line))
+                .collect::<HashSet<_>>()
+                .len(),
+        ),
+        (
+            "fast",
+            lines
+                .iter()
+                .map(|line| hash_line::<FastHasher>(7, line))
+                .collect::<HashSet<_>>()
+                .len(),
+        ),
+    ] {
+        assert_eq!(distinct, lines.len(), "{name} hash collided");
+    }
+
+    let sip = best_of::<DefaultHasher>(&lines);
+    let fast = best_of::<FastHasher>(&lines);
+    let megabytes = data.len() as f64 / (1024.0 * 1024.0);
+
+    println!("{} lines, {megabytes:.1}MB", lines.len());
+    println!(
+        "SipHash:    {sip:>10.2?} ({:.0}MB/s)",
+        megabytes / sip.as_secs_f64()
+    );
+    println!(
+        "FastHasher: {fast:>10.2?} ({:.0}MB/s)",
+        megabytes / fast.as_secs_f64()
+    );
+}
@@ -0,0 +1,66 @@
+//! `-u` over 10M lines drawn from 10 distinct ones: the whole `sort -u`
+//! path, which hashes the distinct lines out before sorting, against
+//! sorting every line and then dropping adjacent duplicates.
+//!
+//! Run with `cargo bench --bench unique_low_cardinality`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use gnu_sort::config::SortConfig;
+use gnu_sort::zero_copy::{parse_lines, Line};
+
+const LINES: usize = 10_000_000;
+const ROUNDS: usize = 3;
+
+/// Fastest of `ROUNDS` runs of `run`
+fn best_of(run: impl Fn() -> Vec<u8>) -> Duration {
+    (0..ROUNDS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(run());
+            start.elapsed()
+        })
+        .min()
+        .unwrap_or_default()
+}
+
+/// Sort every line bytewise, then keep the first of each run
+fn sort_then_dedup(data: &[u8]) -> Vec<u8> {
+    let mut lines: Vec<Line> = parse_lines(data);
+    lines.sort_unstable_by(|a, b| unsafe { a.as_bytes().cmp(b.as_bytes()) });
+    lines.dedup_by(|a, b| unsafe { a.as_bytes() == b.as_bytes() });
+    let mut output = Vec::new();
+    for line in &lines {
+        output.extend_from_slice(unsafe { line.as_bytes() });
+        output.push(b'\n');
+    }
+    output
+}
+
+fn main() {
+    // Ten status words, each repeated a million times in shuffled order
+    let values = [
+        "ok", "error", "timeout", "retry", "redirect", "denied", "missing", "busy", "moved",
+        "unknown",
+    ];
+    let mut data = Vec::new();
+    for n in 0..LINES as u64 {
+        let index = n.wrapping_mul(0x9e37_79b9_7f4a_7c15) >> 32;
+        data.extend_from_slice(values[index as usize % values.len()].as_bytes());
+        data.push(b'\n');
+    }
+
+    let config = SortConfig::default().with_unique(true);
+    let unique = || gnu_sort::sort_bytes(&config, &data).expect("sort -u");
+
+    // Both give the same distinct lines before either is timed
+    assert_eq!(unique(), sort_then_dedup(&data));
+
+    let hashed = best_of(unique);
+    let sorted = best_of(|| sort_then_dedup(&data));
+
+    println!("{LINES} lines, {} distinct", values.len());
+    println!("sort then dedup: {sorted:>10.2?}");
+    println!("sort -u:         {hashed:>10.2?}");
+}
@@ -163,6 +163,11 @@ generate_data() {
         if [ ! -f "test_dups_${suffix}.txt" ]; then
             seq 1 $size | awk '{print $1 % 100}' > "test_dups_${suffix}.txt"
         fi
+
+        # Low-cardinality data (10 distinct lines)
+        if [ ! -f "test_lowcard_${suffix}.txt" ]; then
+            seq 1 $size | awk '{print "value_" (($1 * 7) % 10)}' > "test_lowcard_${suffix}.txt"
+        fi
     else
         # Use fixed seed for reproducibility
         RANDOM=42
@@ -193,6 +198,11 @@ generate_data() {
         if [ ! -f "test_dups_${suffix}.txt" ]; then
             for ((i=1; i<=size; i++)); do echo "$((i % 100))"; done > "test_dups_${suffix}.txt"
         fi
+
+        # Low-cardinality data (10 distinct lines)
+        if [ ! -f "test_lowcard_${suffix}.txt" ]; then
+            for ((i=1; i<=size; i++)); do echo "value_$((i * 7 % 10))"; done > "test_lowcard_${suffix}.txt"
+        fi
     fi
 }
 
@@ -421,6 +431,7 @@ run_test_suite() {
     test_sort_all "Basic string" "test_strings_${suffix}.txt" "" "$label"
     test_sort_all "Reverse numeric" "test_nums_${suffix}.txt" "-rn" "$label"
     test_sort_all "Unique sort" "test_dups_${suffix}.txt" "-u" "$label"
+    test_sort_all "Unique low cardinality" "test_lowcard_${suffix}.txt" "-u" "$label"
     test_sort_all "Numeric unique" "test_dups_${suffix}.txt" "-nu" "$label"
     test_sort_all "Ignore case" "test_strings_${suffix}.txt" "-f" "$label"
     test_sort_all "Random sort" "test_dups_${suffix}.txt" "-R" "$label"
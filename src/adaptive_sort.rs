@@ -5,6 +5,9 @@ use std::sync::Arc;
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
 
+/// Largest value range counting sort will allocate a count table for
+const COUNTING_SORT_MAX_RANGE: u64 = 1_000_000;
+
 /// Adaptive sorting algorithm that selects optimal strategy based on data patterns
 pub struct AdaptiveSort {
     #[allow(dead_code)]
@@ -177,30 +180,57 @@ impl AdaptiveSort {
         }
     }
 
+    /// Choose between counting and radix sort for `len` integers spanning
+    /// `min..=max`: counting sort wins while the range is no larger than 64K
+    /// values or the input itself, up to the counting sort's own limit
+    pub fn select_integer_algorithm(len: usize, min: i64, max: i64) -> SortAlgorithm {
+        const SMALL_RANGE: u64 = 1 << 16;
+
+        let range = max.abs_diff(min).saturating_add(1);
+        if range <= SMALL_RANGE.max(len as u64).min(COUNTING_SORT_MAX_RANGE) {
+            SortAlgorithm::CountingSort
+        } else {
+            SortAlgorithm::RadixSort
+        }
+    }
+
     ///  Counting sort for small integer ranges (O(n+k) complexity)
-    pub fn counting_sort(data: &mut [i32], min: i32, max: i32) {
-        let range = (max - min + 1) as usize;
-        if range > 1_000_000 {
-            // Fall back to standard sort for large ranges
-            data.sort_unstable();
+    ///
+    /// Sorts `(value, index)` pairs by value. Pairs with equal values keep
+    /// their relative order, so the line index they carry stays a stable
+    /// tie-breaker. All values must lie within `min..=max`.
+    pub fn counting_sort(values: &mut [(i64, usize)], min: i64, max: i64) {
+        let range = (max as i128 - min as i128 + 1) as u128;
+        if range > COUNTING_SORT_MAX_RANGE as u128 {
+            // Fall back to standard (stable) sort for large ranges
+            values.sort_by_key(|(value, _)| *value);
             return;
         }
 
-        let mut counts = vec![0; range];
+        let slot = |value: i64| (value as i128 - min as i128) as usize;
+        let mut counts = vec![0usize; range as usize];
 
         // Count occurrences
-        for &value in data.iter() {
-            counts[(value - min) as usize] += 1;
+        for &(value, _) in values.iter() {
+            counts[slot(value)] += 1;
         }
 
-        // Reconstruct sorted array
-        let mut idx = 0;
-        for (i, &count) in counts.iter().enumerate() {
-            for _ in 0..count {
-                data[idx] = min + i as i32;
-                idx += 1;
-            }
+        // Turn counts into starting positions
+        let mut total = 0;
+        for count in counts.iter_mut() {
+            let start = total;
+            total += *count;
+            *count = start;
         }
+
+        // Scatter in input order, which keeps equal values stable
+        let mut sorted = vec![(0i64, 0usize); values.len()];
+        for &(value, idx) in values.iter() {
+            let position = &mut counts[slot(value)];
+            sorted[*position] = (value, idx);
+            *position += 1;
+        }
+        values.copy_from_slice(&sorted);
     }
 
     ///  String interning for repeated values (reduce memory and comparisons)
@@ -364,7 +394,7 @@ pub enum DataType {
     Mixed,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum SortAlgorithm {
     QuickSort,
     MergeSort,
@@ -449,9 +479,44 @@ mod tests {
 
     #[test]
     fn test_counting_sort() {
-        let mut data = vec![5, 2, 8, 1, 9, 3, 7, 4, 6];
+        let mut data: Vec<(i64, usize)> = [5, 2, 8, 1, 9, 3, 7, 4, 6]
+            .iter()
+            .enumerate()
+            .map(|(idx, &value)| (value, idx))
+            .collect();
         AdaptiveSort::counting_sort(&mut data, 1, 9);
-        assert_eq!(data, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+        let values: Vec<i64> = data.iter().map(|(value, _)| *value).collect();
+        assert_eq!(values, vec![1, 2, 3, 4, 5, 6, 7, 8, 9]);
+
+        // Equal values keep their original order
+        let mut data = vec![(2, 0), (-1, 1), (2, 2), (-1, 3), (0, 4)];
+        AdaptiveSort::counting_sort(&mut data, -1, 2);
+        assert_eq!(data, vec![(-1, 1), (-1, 3), (0, 4), (2, 0), (2, 2)]);
+    }
+
+    #[test]
+    fn test_integer_algorithm_selection() {
+        assert_eq!(
+            AdaptiveSort::select_integer_algorithm(50_000, 0, 999),
+            SortAlgorithm::CountingSort
+        );
+        assert_eq!(
+            AdaptiveSort::select_integer_algorithm(50_000, -1_000_000, 1_000_000),
+            SortAlgorithm::RadixSort
+        );
+        assert_eq!(
+            AdaptiveSort::select_integer_algorithm(10, i64::MIN, i64::MAX),
+            SortAlgorithm::RadixSort
+        );
+        // A large input justifies a proportionally large count table
+        assert_eq!(
+            AdaptiveSort::select_integer_algorithm(2_000_000, 0, 900_000),
+            SortAlgorithm::CountingSort
+        );
+        assert_eq!(
+            AdaptiveSort::select_integer_algorithm(2_000_000, 0, 1_500_000),
+            SortAlgorithm::RadixSort
+        );
     }
 
     #[test]
@@ -0,0 +1,1120 @@
+//! Command-line parsing shared by the `sort` binary and library users
+//!
+//! Everything needed to turn a GNU-style argument vector into a
+//! [`SortConfig`]: the clap command definition, the legacy `+N -M` rewrite
+//! and the mapping from parsed matches to configuration.
+
+use clap::{Arg, Command};
+
+use crate::config::{SortConfig, SortConfigBuilder, SortKey, SortMode};
+use crate::error::{SortError, SortResult};
+
+/// Environment variables that change how a command line is read.
+///
+/// `POSIXLY_CORRECT` is not among them: blank-separated fields always follow
+/// the POSIX rule, a run of blanks then a run of non-blanks, which is also
+/// GNU's default, so there is no other field splitting for it to select.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CliEnvironment {
+    /// Default options from `SORT_OPTIONS`, split on whitespace and placed
+    /// before the command-line arguments
+    pub sort_options: Option<String>,
+    /// Whether the character type locale (`LC_ALL`, `LC_CTYPE`, `LANG`) is
+    /// UTF-8; `-k` character positions then count characters, not bytes
+    pub utf8_locale: bool,
+}
+
+impl CliEnvironment {
+    /// Read the settings from the process environment
+    pub fn from_env() -> Self {
+        Self {
+            sort_options: std::env::var("SORT_OPTIONS").ok(),
+            utf8_locale: ["LC_ALL", "LC_CTYPE", "LANG"]
+                .iter()
+                .filter_map(|name| std::env::var(name).ok())
+                .find(|value| !value.is_empty())
+                .is_some_and(|locale| is_utf8_locale(&locale)),
+        }
+    }
+}
+
+/// Whether a locale name such as `en_US.UTF-8` selects UTF-8
+fn is_utf8_locale(locale: &str) -> bool {
+    let codeset = locale.split('.').nth(1).unwrap_or("");
+    let codeset = codeset.split('@').next().unwrap_or("");
+    codeset.eq_ignore_ascii_case("utf-8") || codeset.eq_ignore_ascii_case("utf8")
+}
+
+/// Parse a full argument vector (program name first) into a configuration
+/// and the list of input files, honoring `SORT_OPTIONS`.
+///
+/// Files named by `--files0-from` are returned when no operands are given.
+pub fn parse_args<S: AsRef<str>>(args: &[S]) -> SortResult<(SortConfig, Vec<String>)> {
+    parse_args_with_env(args, &CliEnvironment::from_env())
+}
+
+/// Like [`parse_args`], with the environment given explicitly
+pub fn parse_args_with_env<S: AsRef<str>>(
+    args: &[S],
+    env: &CliEnvironment,
+) -> SortResult<(SortConfig, Vec<String>)> {
+    let mut args: Vec<String> = args.iter().map(|arg| arg.as_ref().to_string()).collect();
+    if args.is_empty() {
+        args.push("sort".to_string());
+    }
+    if let Some(options) = &env.sort_options {
+        args.splice(1..1, options.split_whitespace().map(str::to_string));
+    }
+    let converted_args = convert_legacy_syntax(&args);
+
+    let matches = build_cli().try_get_matches_from(converted_args)?;
+    if matches.get_flag("version") {
+        return Err(SortError::Version(version_text()));
+    }
+    let mut config = parse_config_from_matches(&matches)?;
+    config.utf8_positions = env.utf8_locale && !config.byte_order;
+
+    let mut input_files: Vec<String> = matches
+        .get_many::<String>("files")
+        .unwrap_or_default()
+        .cloned()
+        .collect();
+    if input_files.is_empty() {
+        input_files = config.input_files.clone();
+    }
+
+    Ok((config, input_files))
+}
+
+/// `--version` output, laid out like GNU sort's so that scripts reading the
+/// first line (`sort (PACKAGE) VERSION`) keep working
+pub fn version_text() -> String {
+    let author = env!("CARGO_PKG_AUTHORS")
+        .split(':')
+        .map(|author| author.split(" <").next().unwrap_or(author))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        "sort ({package}) {version}\n\
+         Copyright (c) 2025 rust-sort contributors.\n\
+         License {license}.\n\
+         This is a Rust implementation of GNU sort; you are free to change and redistribute it.\n\
+         There is NO WARRANTY, to the extent permitted by law.\n\
+         \n\
+         Written by {author}.\n",
+        package = env!("CARGO_PKG_NAME"),
+        version = env!("CARGO_PKG_VERSION"),
+        license = env!("CARGO_PKG_LICENSE"),
+    )
+}
+
+/// Build the clap command describing every option GNU sort accepts
+pub fn build_cli() -> Command {
+    Command::new("sort")
+        .author("GNU sort compatible implementation in Rust")
+        .override_usage("sort [OPTION]... [FILE]...")
+        .about("Sort lines of text files")
+        .long_about("Sort lines of text files according to various criteria. \n\nThis implementation is compatible with GNU sort and supports all major features including field sorting, numeric comparisons, and parallel processing.")
+        .disable_help_flag(true)  // We use -h for human-numeric-sort
+        .disable_version_flag(true)  // We use -V for version-sort
+
+        // Input files
+        .arg(Arg::new("files")
+            .help("Input files to sort (use '-' or omit for stdin)")
+            .num_args(0..)
+            .value_name("FILE"))
+
+        // Sort modes (mutually exclusive)
+        .arg(Arg::new("numeric-sort")
+            .short('n')
+            .long("numeric-sort")
+            .help("Compare according to string numerical value")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("general-numeric-sort")
+            .short('g')
+            .long("general-numeric-sort")
+            .help("Compare according to general numerical value")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("human-numeric-sort")
+            .short('h')
+            .long("human-numeric-sort")
+            .help("Compare human readable numbers (e.g., 2K 1G)")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("month-sort")
+            .short('M')
+            .long("month-sort")
+            .help("Compare by month names")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("random-sort")
+            .short('R')
+            .long("random-sort")
+            .help("Shuffle, but group identical keys")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("random-source")
+            .long("random-source")
+            .help("Get random bytes from FILE, for a repeatable -R")
+            .value_name("FILE")
+            .require_equals(true))
+        .arg(Arg::new("version-sort")
+            .short('V')
+            .long("version-sort")
+            .help("Natural sort of version numbers")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("sort")
+            .long("sort")
+            .help("Sort according to WORD")
+            .long_help("Sort according to WORD: general-numeric -g, human-numeric -h, month -M, numeric -n, random -R, version -V")
+            .value_name("WORD")
+            .value_parser(["general-numeric", "human-numeric", "month", "numeric", "random", "version"]))
+
+        // Sort modifiers
+        .arg(Arg::new("reverse")
+            .short('r')
+            .long("reverse")
+            .help("Reverse the result of comparisons")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("unique")
+            .short('u')
+            .long("unique")
+            .help("Output only the first of an equal run")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("stable")
+            .short('s')
+            .long("stable")
+            .help("Stabilize sort by disabling last-resort comparison")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("empty-last")
+            .long("empty-last")
+            .help("Output empty and all-blank lines after all others")
+            .action(clap::ArgAction::SetTrue))
+
+        // Text processing options
+        .arg(Arg::new("ignore-case")
+            .short('f')
+            .long("ignore-case")
+            .help("Fold lower case to upper case characters")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("dictionary-order")
+            .short('d')
+            .long("dictionary-order")
+            .help("Consider only blanks and alphanumeric characters")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("ignore-leading-blanks")
+            .short('b')
+            .long("ignore-leading-blanks")
+            .help("Ignore leading blanks")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("ignore-nonprinting")
+            .short('i')
+            .long("ignore-nonprinting")
+            .help("Consider only printable characters")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("ignore-chars")
+            .long("ignore-chars")
+            .help("Leave the characters in STR out of comparisons")
+            .value_name("STR")
+            .require_equals(true))
+
+        // Field and key options
+        .arg(Arg::new("field-separator")
+            .short('t')
+            .long("field-separator")
+            .help("Use SEP instead of non-blank to blank transition; SEP may be an escape such as \\t")
+            .value_name("SEP"))
+        .arg(Arg::new("field-separator-string")
+            .long("field-separator-string")
+            .help("Split fields on the literal string STR (may be several bytes)")
+            .value_name("STR")
+            .conflicts_with("field-separator"))
+        .arg(Arg::new("key")
+            .short('k')
+            .long("key")
+            .help("Sort via a key; KEYDEF gives location and type")
+            .long_help("Sort via a key; KEYDEF gives location and type.\n\nKEYDEF is F[.C][OPTS][,F[.C][OPTS]] for start and stop position, where F is a field number and C a character position in the field; both are origin 1, and the stop position defaults to the line's end.\n\nIf neither -t nor -b is in effect, characters in a field are counted from the beginning of the whitespace separating the preceding field; otherwise they are counted from the beginning of the field.\n\nOPTS is one or more single-letter ordering options [bdfgiMnRrVz], which override global ordering options for that key. As an extension, L orders by the length of the key instead of its text. If no key is given, use the entire line as the key.\n\nExamples:\n  1    - sort by first field\n  2,4  - sort by fields 2 through 4\n  1.3,1.5 - sort by characters 3-5 of field 1\n  2nr  - sort by field 2 numerically in reverse")
+            .value_name("KEYDEF")
+            .action(clap::ArgAction::Append))
+
+        // I/O options
+        .arg(Arg::new("output")
+            .short('o')
+            .long("output")
+            .help("Write result to FILE instead of standard output")
+            .value_name("FILE")
+            .overrides_with("output"))
+        .arg(Arg::new("zero-terminated")
+            .short('z')
+            .long("zero-terminated")
+            .help("Line delimiter is NUL, not newline")
+            .action(clap::ArgAction::SetTrue))
+
+        // Operation modes
+        .arg(Arg::new("check")
+            .short('c')
+            .long("check")
+            .help("Check for sorted input; do not sort")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("check-silent")
+            .short('C')
+            .long("check=silent")
+            .help("Like -c, but do not report first bad line")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("check-count")
+            .long("check-count")
+            .help("Check for sorted input and report how many lines are out of order")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("merge")
+            .short('m')
+            .long("merge")
+            .help("Merge already sorted files; do not sort")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("verify")
+            .long("verify")
+            .help("With -m, fail if an input turns out not to be sorted")
+            .requires("merge")
+            .action(clap::ArgAction::SetTrue))
+
+        // Performance options
+        .arg(Arg::new("buffer-size")
+            .short('S')
+            .long("buffer-size")
+            .help("Use SIZE for main memory buffer")
+            .long_help("Use SIZE for main memory buffer. SIZE may be followed by the following multiplicative suffixes: % 1% of memory, b 1, K 1024 (default), and so on for M, G, T, P, E, Z, Y.")
+            .value_name("SIZE")
+            .overrides_with("buffer-size"))
+        .arg(Arg::new("parallel")
+            .long("parallel")
+            .help("Change the number of sorts run concurrently to N")
+            .value_name("N"))
+        .arg(Arg::new("temporary-directory")
+            .short('T')
+            .long("temporary-directory")
+            .help("Use DIR for temporaries, not $TMPDIR or /tmp")
+            .value_name("DIR")
+            .overrides_with("temporary-directory"))
+
+        // Additional options
+        .arg(Arg::new("compress-program")
+            .long("compress-program")
+            .help("Compress temporaries with PROG; decompress them with PROG -d")
+            .value_name("PROG"))
+        .arg(Arg::new("debug")
+            .long("debug")
+            .help("Annotate the part of the line used to sort, and warn about questionable usage to stderr")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("accounting")
+            .long("accounting")
+            .help("With -n or -g, read parenthesized numbers such as (100) as negative")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("byte-order")
+            .long("byte-order")
+            .help("Compare bytes, ignoring the locale environment; same as --locale=C")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("locale")
+            .long("locale")
+            .help("Collate by LOCALE instead of the environment; only C and POSIX are supported")
+            .value_name("LOCALE")
+            .value_parser(["C", "POSIX"]))
+        .arg(Arg::new("strict-fields")
+            .long("strict-fields")
+            .help("Fail if a line lacks a field referenced by a key, instead of treating it as empty")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("max-line-length")
+            .long("max-line-length")
+            .help("Fail on lines longer than N bytes, or see --long-lines")
+            .value_name("N"))
+        .arg(Arg::new("long-lines")
+            .long("long-lines")
+            .help("With --max-line-length: error, or truncate longer lines when comparing")
+            .value_name("ACTION")
+            .value_parser(["error", "truncate"]))
+        .arg(Arg::new("show-key")
+            .long("show-key")
+            .help("Write each line's sort key before it (prefix, the default) or in its place (replace)")
+            .value_name("MODE")
+            .num_args(0..=1)
+            .require_equals(true)
+            .default_missing_value("prefix")
+            .value_parser(["prefix", "replace"]))
+        .arg(Arg::new("key-delimiter")
+            .long("key-delimiter")
+            .help("With --show-key, separate the key from the line by STR instead of a tab")
+            .value_name("STR"))
+        .arg(Arg::new("output-compress")
+            .long("output-compress")
+            .help("Compress the output: auto (gzip for an -o name ending in .gz, the default), none or gzip")
+            .value_name("MODE")
+            .require_equals(true)
+            .value_parser(["auto", "none", "gzip"]))
+        .arg(Arg::new("algorithm")
+            .long("algorithm")
+            .help("Sort in memory with mergesort, quicksort or radix instead of picking one from the input (auto, the default)")
+            .value_name("NAME")
+            .require_equals(true)
+            .value_parser(["auto", "mergesort", "quicksort", "radix"]))
+        .arg(Arg::new("safe")
+            .long("safe")
+            .help("Warn if an input file changes while it is being sorted")
+            .action(clap::ArgAction::SetTrue))
+        .arg(Arg::new("trailing-newline")
+            .long("trailing-newline")
+            .help("Terminate the final output line: always, never, or preserve (as in the input)")
+            .value_name("WHEN")
+            .value_parser(["always", "never", "preserve"]))
+        .arg(Arg::new("files0-from")
+            .long("files0-from")
+            .help("Read input from the files specified by NUL-terminated names in file F")
+            .value_name("F"))
+
+        // Add explicit help and version options since we disabled the automatic ones
+        .arg(Arg::new("help")
+            .long("help")
+            .help("Display this help and exit")
+            .action(clap::ArgAction::Help))
+        .arg(Arg::new("version")
+            .long("version")
+            .help("Output version information and exit")
+            .action(clap::ArgAction::SetTrue))
+}
+
+/// Convert legacy +N -M syntax to modern -k syntax
+pub fn convert_legacy_syntax(args: &[String]) -> Vec<String> {
+    let mut converted = Vec::new();
+    converted.push(args[0].clone()); // Program name
+
+    let mut i = 1;
+    while i < args.len() {
+        let arg = &args[i];
+
+        if arg.starts_with('+') && arg.len() > 1 {
+            // Legacy start position +N
+            if let Ok(start_field) = arg[1..].parse::<usize>() {
+                // Look for corresponding -M
+                if i + 1 < args.len() && args[i + 1].starts_with('-') && args[i + 1].len() > 1 {
+                    if let Ok(end_field) = args[i + 1][1..].parse::<usize>() {
+                        // Convert +N -M to -k (N+1),(M)
+                        converted.push("-k".to_string());
+                        converted.push(format!("{},{}", start_field + 1, end_field));
+                        i += 2; // Skip both +N and -M
+                        continue;
+                    }
+                }
+                // Just +N without -M, convert to -k (N+1)
+                converted.push("-k".to_string());
+                converted.push(format!("{}", start_field + 1));
+                i += 1;
+                continue;
+            }
+        }
+
+        // Regular argument, copy as-is
+        converted.push(arg.clone());
+        i += 1;
+    }
+
+    converted
+}
+
+/// Parse configuration from command line matches
+pub fn parse_config_from_matches(matches: &clap::ArgMatches) -> SortResult<SortConfig> {
+    let mut builder = SortConfigBuilder::new();
+
+    // Determine sort mode (mutually exclusive)
+    let sort_mode = if matches.get_flag("numeric-sort") {
+        SortMode::Numeric
+    } else if matches.get_flag("general-numeric-sort") {
+        SortMode::GeneralNumeric
+    } else if matches.get_flag("human-numeric-sort") {
+        SortMode::HumanNumeric
+    } else if matches.get_flag("month-sort") {
+        SortMode::Month
+    } else if matches.get_flag("random-sort") {
+        SortMode::Random
+    } else if matches.get_flag("version-sort") {
+        SortMode::Version
+    } else if let Some(sort_word) = matches.get_one::<String>("sort") {
+        match sort_word.as_str() {
+            "general-numeric" => SortMode::GeneralNumeric,
+            "human-numeric" => SortMode::HumanNumeric,
+            "month" => SortMode::Month,
+            "numeric" => SortMode::Numeric,
+            "random" => SortMode::Random,
+            "version" => SortMode::Version,
+            _ => {
+                return Err(SortError::parse_error(&format!(
+                    "unknown sort type: {sort_word}"
+                )))
+            }
+        }
+    } else {
+        SortMode::Lexicographic
+    };
+
+    builder = builder.mode(sort_mode);
+
+    // Apply boolean flags
+    if matches.get_flag("reverse") {
+        builder = builder.reverse();
+    }
+    if matches.get_flag("unique") {
+        builder = builder.unique();
+    }
+    if matches.get_flag("stable") {
+        builder = builder.stable();
+    }
+    if matches.get_flag("check")
+        || matches.get_flag("check-silent")
+        || matches.get_flag("check-count")
+    {
+        builder = builder.check();
+    }
+    if matches.get_flag("merge") {
+        builder = builder.merge();
+    }
+    if matches.get_flag("zero-terminated") {
+        builder = builder.zero_terminated();
+    }
+
+    let mut config = builder.build()?;
+
+    // Set additional options not handled by builder
+    config.ignore_case = matches.get_flag("ignore-case");
+    config.empty_last = matches.get_flag("empty-last");
+    config.dictionary_order = matches.get_flag("dictionary-order");
+    config.ignore_leading_blanks = matches.get_flag("ignore-leading-blanks");
+    config.ignore_nonprinting = matches.get_flag("ignore-nonprinting");
+    if let Some(chars) = matches.get_one::<String>("ignore-chars") {
+        config = config.with_ignore_chars(Some(chars.clone()));
+    }
+    config.debug = matches.get_flag("debug");
+    config.safe = matches.get_flag("safe");
+    config.accounting = matches.get_flag("accounting");
+    config.strict_fields = matches.get_flag("strict-fields");
+    config.byte_order =
+        matches.get_flag("byte-order") || matches.get_one::<String>("locale").is_some();
+    config.check_count = matches.get_flag("check-count");
+    config.verify = matches.get_flag("verify");
+
+    // Set field separator
+    if let Some(sep_str) = matches.get_one::<String>("field-separator") {
+        config.field_separator = Some(parse_field_separator(sep_str)?);
+    }
+    if let Some(sep_str) = matches.get_one::<String>("field-separator-string") {
+        config.field_separator_string = Some(sep_str.clone());
+    }
+
+    // Set output file
+    if let Some(output) = matches.get_one::<String>("output") {
+        config.output_file = Some(output.clone());
+    }
+
+    // Set buffer size
+    if let Some(buffer_str) = matches.get_one::<String>("buffer-size") {
+        config.set_buffer_size_from_string(buffer_str)?;
+    }
+
+    // Set parallel threads
+    if let Some(parallel_str) = matches.get_one::<String>("parallel") {
+        let threads: usize = parallel_str.parse().map_err(|_| {
+            SortError::parse_error(&format!("invalid thread count: {parallel_str}"))
+        })?;
+        config.parallel_threads = Some(threads);
+    }
+
+    // Set trailing newline policy
+    if let Some(policy) = matches.get_one::<String>("trailing-newline") {
+        config.trailing_newline = policy.parse()?;
+    }
+
+    // Set maximum line length
+    if let Some(length) = matches.get_one::<String>("max-line-length") {
+        let length: usize = length.parse().map_err(|_| {
+            SortError::parse_error(&format!("invalid maximum line length: {length}"))
+        })?;
+        config.max_line_length = Some(length);
+    }
+    if let Some(action) = matches.get_one::<String>("long-lines") {
+        config.long_lines = action.parse()?;
+    }
+
+    // Set key display
+    if let Some(mode) = matches.get_one::<String>("show-key") {
+        config.show_key = Some(mode.parse()?);
+    }
+    if let Some(delimiter) = matches.get_one::<String>("key-delimiter") {
+        config.key_delimiter = delimiter.clone();
+    }
+
+    // Set output compression
+    if let Some(mode) = matches.get_one::<String>("output-compress") {
+        config.output_compression = mode.parse()?;
+    }
+
+    // Set sort algorithm
+    if let Some(algorithm) = matches.get_one::<String>("algorithm") {
+        config.algorithm = algorithm.parse()?;
+    }
+
+    // Set temporary directory
+    if let Some(program) = matches.get_one::<String>("compress-program") {
+        config.compress_program = Some(program.clone());
+        config.compress_temp = true;
+    }
+
+    if let Some(temp_dir) = matches.get_one::<String>("temporary-directory") {
+        config.temp_dir = Some(temp_dir.clone());
+    }
+
+    // Parse sort keys from -k options
+    if let Some(key_defs) = matches.get_many::<String>("key") {
+        for keydef in key_defs {
+            let key = SortKey::parse(keydef)?;
+            config.keys.push(key);
+        }
+    }
+
+    if let Some(source) = matches.get_one::<String>("random-source") {
+        config.random_seed = Some(random_seed_from_file(source)?);
+    }
+
+    // Handle files0-from option
+    if let Some(files0_file) = matches.get_one::<String>("files0-from") {
+        config.input_files = read_files_from_null_separated_file(files0_file)?;
+
+        // Sorting would truncate a listed input before it has been read
+        if let Some(output) = config.output_file.as_deref() {
+            if config
+                .input_files
+                .iter()
+                .any(|input| same_file(input, output))
+            {
+                return Err(SortError::conflicting_options(&format!(
+                    "-o {output} is also an input listed by --files0-from {files0_file}"
+                )));
+            }
+        }
+    }
+
+    // Validate the final configuration
+    config.validate()?;
+
+    Ok(config)
+}
+
+/// Read filenames from a null-separated file
+fn read_files_from_null_separated_file(filename: &str) -> SortResult<Vec<String>> {
+    use std::fs::File;
+    use std::io::Read;
+
+    let mut file = File::open(filename).map_err(|_| SortError::file_not_found(filename))?;
+
+    let mut contents = Vec::new();
+    file.read_to_end(&mut contents)?;
+
+    let files: Vec<String> = contents
+        .split(|&b| b == 0)
+        .filter(|chunk| !chunk.is_empty())
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect();
+
+    Ok(files)
+}
+
+/// Bytes of a `--random-source` file that make up the seed; a source such
+/// as `/dev/urandom` never ends
+const RANDOM_SOURCE_BYTES: u64 = 64 * 1024;
+
+/// Seed for `-R` from the start of a `--random-source` file, so that the
+/// same file always gives the same shuffle. The bytes go through the
+/// in-tree [`FastHasher`], whose output does not change between Rust
+/// releases as std's `DefaultHasher` may.
+///
+/// [`FastHasher`]: crate::hash_sort::FastHasher
+fn random_seed_from_file(filename: &str) -> SortResult<u64> {
+    use std::hash::Hasher;
+    use std::io::Read;
+
+    let file = std::fs::File::open(filename).map_err(|_| SortError::file_not_found(filename))?;
+    let mut contents = Vec::new();
+    file.take(RANDOM_SOURCE_BYTES).read_to_end(&mut contents)?;
+
+    let mut hasher = crate::hash_sort::FastHasher::default();
+    hasher.write(&contents);
+    Ok(hasher.finish())
+}
+
+/// The `-t` separator: one character, which may take several bytes in
+/// UTF-8, or one of the escapes `\t`, `\n`, `\0` and `\\`
+fn parse_field_separator(sep: &str) -> SortResult<char> {
+    match sep {
+        "\\t" => return Ok('\t'),
+        "\\n" => return Ok('\n'),
+        "\\0" => return Ok('\0'),
+        "\\\\" => return Ok('\\'),
+        _ => {}
+    }
+
+    let mut chars = sep.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        (None, _) => Err(SortError::invalid_field_separator("empty tab")),
+        (Some(_), Some(_)) => Err(SortError::invalid_field_separator(&format!(
+            "multi-character tab '{sep}'"
+        ))),
+    }
+}
+
+/// Whether two paths name the same file; paths that do not resolve are
+/// compared as written
+fn same_file(a: &str, b: &str) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => std::path::Path::new(a) == std::path::Path::new(b),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_basic_config() {
+        let app = build_cli();
+        let matches = app
+            .try_get_matches_from(["sort", "-n", "-r"])
+            .expect("Failed to parse test arguments");
+
+        let config = parse_config_from_matches(&matches).expect("Failed to parse test config");
+
+        assert_eq!(config.mode, SortMode::Numeric);
+        assert!(config.reverse);
+    }
+
+    #[test]
+    fn test_parse_complex_config() {
+        let app = build_cli();
+        let matches = app
+            .try_get_matches_from([
+                "sort",
+                "-k",
+                "2,4",
+                "-t",
+                ":",
+                "-u",
+                "-o",
+                "output.txt",
+                "input.txt",
+            ])
+            .expect("Failed to parse test arguments");
+
+        let config = parse_config_from_matches(&matches).expect("Failed to parse test config");
+
+        assert!(config.unique);
+        assert_eq!(config.field_separator, Some(':'));
+        assert_eq!(config.output_file, Some("output.txt".to_string()));
+        assert!(!config.keys.is_empty());
+    }
+
+    #[test]
+    fn test_parse_field_separator_string() {
+        let matches = build_cli()
+            .try_get_matches_from(["sort", "--field-separator-string=||", "-k2,2"])
+            .expect("Failed to parse test arguments");
+        let config = parse_config_from_matches(&matches).expect("Failed to parse test config");
+        assert_eq!(config.field_separator_string.as_deref(), Some("||"));
+
+        let matches = build_cli()
+            .try_get_matches_from(["sort", "--field-separator-string="])
+            .expect("Failed to parse test arguments");
+        assert!(parse_config_from_matches(&matches).is_err());
+
+        assert!(build_cli()
+            .try_get_matches_from(["sort", "-t", ":", "--field-separator-string=||"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_field_separator_escapes() {
+        let separator = |sep: &str| {
+            SortConfig::from_args(&["sort", "-t", sep]).map(|(config, _)| config.field_separator)
+        };
+        assert_eq!(separator("\\t").unwrap(), Some('\t'));
+        assert_eq!(separator("\t").unwrap(), Some('\t'));
+        assert_eq!(separator("\\\\").unwrap(), Some('\\'));
+        assert_eq!(separator("\u{e9}").unwrap(), Some('\u{e9}'));
+
+        let err = separator("").unwrap_err().to_string();
+        assert!(err.contains("empty tab"), "{err}");
+        let err = separator("ab").unwrap_err().to_string();
+        assert!(err.contains("multi-character tab 'ab'"), "{err}");
+    }
+
+    #[test]
+    fn test_conflicting_options() {
+        let app = build_cli();
+        let matches = app
+            .try_get_matches_from(["sort", "-c", "-m"])
+            .expect("Failed to parse test arguments");
+
+        let result = parse_config_from_matches(&matches);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_from_args_returns_operands() {
+        let (config, files) = SortConfig::from_args(&[
+            "sort",
+            "-nr",
+            "-k",
+            "2",
+            "--parallel",
+            "2",
+            "a.txt",
+            "-",
+            "b.txt",
+        ])
+        .expect("Failed to parse test arguments");
+
+        assert_eq!(config.mode, SortMode::Numeric);
+        assert!(config.reverse);
+        assert_eq!(config.keys.len(), 1);
+        assert_eq!(config.keys[0].start_field, 2);
+        assert_eq!(config.parallel_threads, Some(2));
+        assert_eq!(files, ["a.txt", "-", "b.txt"]);
+
+        let (config, files) = SortConfig::from_args(&["sort"]).expect("empty command line");
+        assert_eq!(config.mode, SortMode::Lexicographic);
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn test_repeated_options_last_wins() {
+        let (config, _) = SortConfig::from_args(&[
+            "sort", "-o", "a", "-S", "1M", "-T", "/x", "-o", "b", "-S", "2M", "-T", "/y",
+        ])
+        .expect("repeated -o, -S and -T are accepted");
+        assert_eq!(config.output_file.as_deref(), Some("b"));
+        assert_eq!(config.buffer_size, Some(2 << 20));
+        assert_eq!(config.temp_dir.as_deref(), Some("/y"));
+    }
+
+    #[test]
+    fn test_from_args_legacy_keys() {
+        let args = vec!["sort".to_string(), "+1".into(), "-3".into(), "in".into()];
+        let (config, files) = SortConfig::from_args(&args).expect("legacy key syntax");
+
+        assert_eq!(config.keys.len(), 1);
+        assert_eq!(config.keys[0].start_field, 2);
+        assert_eq!(config.keys[0].end_field, Some(3));
+        assert_eq!(files, ["in"]);
+    }
+
+    #[test]
+    fn test_version_first_line_matches_gnu_layout() {
+        let err = SortConfig::from_args(&["sort", "--version"]).unwrap_err();
+        assert_eq!(err.exit_code(), crate::EXIT_SUCCESS);
+        let text = err.to_string();
+        assert_eq!(
+            text.lines().next().unwrap(),
+            format!("sort (gnu-sort) {}", env!("CARGO_PKG_VERSION"))
+        );
+        assert!(text.contains("\n\nWritten by "));
+    }
+
+    #[test]
+    fn test_from_args_files0_from() {
+        let dir = tempfile::tempdir().unwrap();
+        let list = dir.path().join("list");
+        std::fs::write(&list, b"one\0two\0").unwrap();
+        let arg = format!("--files0-from={}", list.display());
+
+        let (_, files) = SortConfig::from_args(&["sort", arg.as_str()]).unwrap();
+        assert_eq!(files, ["one", "two"]);
+    }
+
+    #[test]
+    fn test_output_listed_in_files0_from_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("input");
+        std::fs::write(&input, b"b\na\n").unwrap();
+        let list = dir.path().join("list");
+        std::fs::write(&list, format!("other\0{}\0", input.display())).unwrap();
+        let files0 = format!("--files0-from={}", list.display());
+
+        // Same file under another spelling
+        let output = dir.path().join(".").join("input");
+        let output = output.to_str().unwrap();
+        let err = SortConfig::from_args(&["sort", files0.as_str(), "-o", output]).unwrap_err();
+        assert!(matches!(err, SortError::ConflictingOptions { .. }));
+        assert!(err.to_string().contains("also an input"));
+
+        let elsewhere = dir.path().join("out");
+        let elsewhere = elsewhere.to_str().unwrap();
+        assert!(SortConfig::from_args(&["sort", files0.as_str(), "-o", elsewhere]).is_ok());
+    }
+
+    #[test]
+    fn test_from_args_errors() {
+        assert!(matches!(
+            SortConfig::from_args(&["sort", "--no-such-option"]),
+            Err(SortError::Usage(_))
+        ));
+        assert!(matches!(
+            SortConfig::from_args(&["sort", "--help"]),
+            Err(SortError::Usage(e)) if e.kind() == clap::error::ErrorKind::DisplayHelp
+        ));
+        assert!(SortConfig::from_args(&["sort", "-k", "x"]).is_err());
+        assert!(SortConfig::from_args(&["sort", "-t", "ab"]).is_err());
+        assert!(SortConfig::from_args(&["sort", "-c", "-m"]).is_err());
+    }
+
+    #[test]
+    fn test_sort_options_are_prepended() {
+        let env = CliEnvironment {
+            sort_options: Some(" -r  -t : ".to_string()),
+            ..Default::default()
+        };
+        let (config, files) = parse_args_with_env(&["sort", "-k2", "in"], &env).unwrap();
+        assert!(config.reverse);
+        assert_eq!(config.field_separator, Some(':'));
+        assert_eq!(config.keys.len(), 1);
+        assert_eq!(files, ["in"]);
+
+        // Blank SORT_OPTIONS adds nothing
+        let env = CliEnvironment {
+            sort_options: Some("  ".to_string()),
+            ..Default::default()
+        };
+        let (config, files) = parse_args_with_env(&["sort", "-n"], &env).unwrap();
+        assert_eq!(config.mode, SortMode::Numeric);
+        assert!(!config.reverse);
+        assert!(files.is_empty());
+    }
+
+    #[test]
+    fn test_utf8_locale_counts_characters() {
+        for (locale, utf8) in [
+            ("en_US.UTF-8", true),
+            ("de_DE.utf8@euro", true),
+            ("C.UTF-8", true),
+            ("C", false),
+            ("POSIX", false),
+            ("en_US.ISO-8859-1", false),
+        ] {
+            assert_eq!(is_utf8_locale(locale), utf8, "{locale}");
+        }
+
+        let env = CliEnvironment {
+            utf8_locale: true,
+            ..Default::default()
+        };
+        let (config, _) = parse_args_with_env(&["sort", "-k1.3,1.4"], &env).unwrap();
+        assert!(config.utf8_positions);
+        let (config, _) = parse_args_with_env(&["sort", "--locale=C", "-k1.3"], &env).unwrap();
+        assert!(!config.utf8_positions);
+        let (config, _) = parse_args_with_env(&["sort", "-k1.3"], &Default::default()).unwrap();
+        assert!(!config.utf8_positions);
+    }
+
+    #[test]
+    fn test_debug_chain_names_the_position_unit() {
+        let args = ["sort", "--debug", "-k2.3,2.4", "-k1.2"];
+        let chain_with = |utf8_locale: bool| {
+            let env = CliEnvironment {
+                utf8_locale,
+                ..Default::default()
+            };
+            parse_args_with_env(&args, &env)
+                .unwrap()
+                .0
+                .comparator_chain()
+        };
+
+        assert_eq!(
+            chain_with(true)[..2],
+            [
+                "key 1 (-k2.3,2.4): field 2 character 3 to field 2 character 4, lexicographic, ascending",
+                "key 2 (-k1.2): field 1 character 2 to end of line, lexicographic, ascending",
+            ]
+        );
+        assert_eq!(
+            chain_with(false)[..2],
+            [
+                "key 1 (-k2.3,2.4): field 2 byte 3 to field 2 byte 4, lexicographic, ascending",
+                "key 2 (-k1.2): field 1 byte 2 to end of line, lexicographic, ascending",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_byte_order_flags() {
+        for flag in ["--byte-order", "--locale=C", "--locale=POSIX"] {
+            let (config, _) = SortConfig::from_args(&["sort", flag]).unwrap();
+            assert!(config.byte_order, "{flag}");
+        }
+        let (config, _) = SortConfig::from_args(&["sort"]).unwrap();
+        assert!(!config.byte_order);
+        assert!(SortConfig::from_args(&["sort", "--locale=en_US.UTF-8"]).is_err());
+    }
+
+    #[test]
+    fn test_first_field_keeps_leading_blanks() {
+        let input = b"  b 1\n a 2\n";
+        let (config, _) = SortConfig::from_args(&["sort", "-k1,1"]).unwrap();
+        // Field 1 counts the leading blanks, and ' ' sorts before 'a'
+        assert_eq!(crate::sort_bytes(&config, input).unwrap(), b"  b 1\n a 2\n");
+
+        // `b` skips them, and `-u` keeps " b" apart from "b"
+        let (config, _) = SortConfig::from_args(&["sort", "-k1b,1"]).unwrap();
+        assert_eq!(crate::sort_bytes(&config, input).unwrap(), b" a 2\n  b 1\n");
+        let (config, _) = SortConfig::from_args(&["sort", "-u", "-k1,1"]).unwrap();
+        assert_eq!(
+            crate::sort_bytes(&config, b"b\n b\nb \n").unwrap(),
+            b" b\nb\n"
+        );
+    }
+
+    #[test]
+    fn test_keys_from_command_line() {
+        let (config, _) = SortConfig::from_args(&["sort", "-k2", "-k4"]).unwrap();
+        let fields: Vec<_> = config.keys.iter().map(|key| key.start_field).collect();
+        assert_eq!(fields, [2, 4]);
+
+        assert!(SortConfig::from_args(&["sort", "-k0"]).is_err());
+        assert!(SortConfig::from_args(&["sort", "-k2", "-k1.0"]).is_err());
+        // but an end character of 0 stands for the end of the field
+        let (config, _) = SortConfig::from_args(&["sort", "-k2,3.0"]).unwrap();
+        assert_eq!(
+            (config.keys[0].end_field, config.keys[0].end_char),
+            (Some(3), None)
+        );
+
+        // Keys without options of their own take the global ones
+        let input = b"b 2\na 10\nc 1\n";
+        let (config, _) = SortConfig::from_args(&["sort", "-n", "-k2"]).unwrap();
+        assert_eq!(
+            crate::sort_bytes(&config, input).unwrap(),
+            b"c 1\nb 2\na 10\n"
+        );
+        let (config, _) = SortConfig::from_args(&["sort", "-n", "-k2", "-k1,1r"]).unwrap();
+        assert_eq!(
+            crate::sort_bytes(&config, input).unwrap(),
+            b"c 1\nb 2\na 10\n"
+        );
+        let (config, _) = SortConfig::from_args(&["sort", "-nr", "-k1,1"]).unwrap();
+        assert_eq!(
+            crate::sort_bytes(&config, input).unwrap(),
+            b"c 1\nb 2\na 10\n"
+        );
+    }
+
+    #[test]
+    fn test_blank_separated_keys() {
+        // Fields without -t start with their leading blanks, as in GNU sort
+        let input = b"b  x 3\na y  10\n  c x 10\nd y 2\n e  x 3\n";
+        for (keys, expected) in [
+            (
+                &["-k2,2", "-k3,3n"][..],
+                &b" e  x 3\nb  x 3\n  c x 10\nd y 2\na y  10\n"[..],
+            ),
+            (
+                &["-k2b,2", "-k3,3nr"],
+                b"  c x 10\n e  x 3\nb  x 3\na y  10\nd y 2\n",
+            ),
+            (
+                &["-k2,2", "-k1,1"],
+                b" e  x 3\nb  x 3\n  c x 10\na y  10\nd y 2\n",
+            ),
+            (
+                &["-k2,3.0", "-k1,1"],
+                b" e  x 3\nb  x 3\n  c x 10\na y  10\nd y 2\n",
+            ),
+        ] {
+            let args: Vec<&str> = std::iter::once("sort")
+                .chain(keys.iter().copied())
+                .collect();
+            let (config, _) = SortConfig::from_args(&args).unwrap();
+            assert_eq!(
+                crate::sort_bytes(&config, input).unwrap(),
+                expected,
+                "{keys:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_multibyte_field_separator() {
+        for separator in ["§", "→"] {
+            let (config, _) = SortConfig::from_args(&["sort", "-t", separator, "-k2,2"]).unwrap();
+            assert_eq!(config.field_separator, separator.chars().next());
+
+            // Split on the whole encoding: field 2 of the second line is
+            // "a", not "a" plus the separator's lead byte
+            let input = format!("1{separator}ab\n2{separator}a{separator}c\n");
+            let expected = format!("2{separator}a{separator}c\n1{separator}ab\n");
+            assert_eq!(
+                crate::sort_bytes(&config, input.as_bytes()).unwrap(),
+                expected.as_bytes()
+            );
+        }
+        assert!(SortConfig::from_args(&["sort", "-t", "§§"]).is_err());
+    }
+
+    #[test]
+    fn test_show_key_prefixes_the_key() {
+        let input = b"x:3:a\ny:1:b\nz:2:c\n";
+        let show = |args: &[&str]| {
+            let (config, _) = SortConfig::from_args(args).unwrap();
+            crate::sort_bytes(&config, input).unwrap()
+        };
+
+        assert_eq!(
+            show(&["sort", "--show-key", "-t:", "-k2,2"]),
+            b"1\ty:1:b\n2\tz:2:c\n3\tx:3:a\n"
+        );
+        assert_eq!(
+            show(&["sort", "--show-key", "--key-delimiter= | ", "-t:", "-k2,2"]),
+            b"1 | y:1:b\n2 | z:2:c\n3 | x:3:a\n"
+        );
+        // Several keys are joined by the delimiter; `replace` drops the line
+        assert_eq!(
+            show(&["sort", "--show-key=replace", "-t:", "-k2,2", "-k3,3"]),
+            b"1\tb\n2\tc\n3\ta\n"
+        );
+        assert!(SortConfig::from_args(&["sort", "--show-key=suffix"]).is_err());
+    }
+
+    #[test]
+    fn test_random_source_repeats_the_shuffle() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        std::fs::write(&source, b"some random bytes").unwrap();
+        let arg = format!("--random-source={}", source.display());
+
+        let input: Vec<u8> = (0..200)
+            .flat_map(|i| format!("{}\n", i % 50).into_bytes())
+            .collect();
+        let shuffle = || {
+            let (config, _) = SortConfig::from_args(&["sort", "-R", &arg]).unwrap();
+            assert!(config.random_seed.is_some());
+            crate::sort_bytes(&config, &input).unwrap()
+        };
+        let first = shuffle();
+        assert_eq!(first, shuffle());
+
+        let mut sorted_input: Vec<&[u8]> = input.split(|&b| b == b'\n').collect();
+        let mut sorted_output: Vec<&[u8]> = first.split(|&b| b == b'\n').collect();
+        sorted_input.sort();
+        sorted_output.sort();
+        assert_eq!(sorted_input, sorted_output);
+
+        // The seed depends on the file bytes alone, whatever the toolchain
+        assert_eq!(
+            random_seed_from_file(&source.to_string_lossy()).unwrap(),
+            0x9dc4_2bf9_cbf7_15ae
+        );
+
+        let missing = dir.path().join("missing");
+        let arg = format!("--random-source={}", missing.display());
+        assert!(SortConfig::from_args(&["sort", "-R", &arg]).is_err());
+    }
+}
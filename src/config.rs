@@ -2,6 +2,10 @@
 
 use crate::error::{SortError, SortResult};
 use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Random seed shared by every comparison in this process when none is configured
+static PROCESS_RANDOM_SEED: OnceLock<u64> = OnceLock::new();
 
 /// Sort key specification for field-based sorting
 #[derive(Debug, Clone)]
@@ -16,6 +20,10 @@ pub struct SortKey {
     pub end_char: Option<usize>,
     /// Sort options specific to this key
     pub options: SortKeyOptions,
+    /// Skip leading blanks of the start field before applying `start_char` (`b` on the start)
+    pub start_skip_blanks: bool,
+    /// Skip leading blanks of the end field before applying `end_char` (`b` on the end)
+    pub end_skip_blanks: bool,
 }
 
 /// Options specific to a sort key
@@ -28,9 +36,53 @@ pub struct SortKeyOptions {
     pub ignore_case: bool,
     pub dictionary_order: bool,
     pub ignore_leading_blanks: bool,
+    /// `i`: leave control characters out of text comparisons of the key
+    pub ignore_nonprinting: bool,
     pub human_numeric: bool,
     pub version: bool,
     pub random: bool,
+    /// `L` (an extension): order by the length of the key, in characters
+    /// under a UTF-8 locale, else bytes, instead of by its text
+    pub length: bool,
+}
+
+impl SortKeyOptions {
+    /// True when the key specifies no ordering options of its own, in which
+    /// case GNU sort applies the global options to it
+    pub fn is_empty(&self) -> bool {
+        !(self.numeric
+            || self.general_numeric
+            || self.month
+            || self.reverse
+            || self.ignore_case
+            || self.dictionary_order
+            || self.ignore_leading_blanks
+            || self.ignore_nonprinting
+            || self.human_numeric
+            || self.version
+            || self.random
+            || self.length)
+    }
+
+    /// The comparison this key performs, in the precedence
+    /// `compare_with_keys` applies when several type letters are given
+    pub fn mode(&self) -> SortMode {
+        if self.random {
+            SortMode::Random
+        } else if self.general_numeric {
+            SortMode::GeneralNumeric
+        } else if self.numeric {
+            SortMode::Numeric
+        } else if self.month {
+            SortMode::Month
+        } else if self.version {
+            SortMode::Version
+        } else if self.human_numeric {
+            SortMode::HumanNumeric
+        } else {
+            SortMode::Lexicographic
+        }
+    }
 }
 
 impl SortKey {
@@ -46,15 +98,22 @@ impl SortKey {
 
         // Parse start position and options
         let (start_field, start_char, start_opts) = Self::parse_field_spec(parts[0])?;
+        if start_char == Some(0) {
+            return Err(SortError::parse_error("character positions start at 1"));
+        }
 
-        // Parse end position if present
+        // Parse end position if present; `.0` ends the key with its field
         let (end_field, end_char, end_opts) = if parts.len() == 2 {
             let (field, char_pos, opts) = Self::parse_field_spec(parts[1])?;
-            (Some(field), char_pos, opts)
+            (Some(field), char_pos.filter(|&pos| pos != 0), opts)
         } else {
             (None, None, SortKeyOptions::default())
         };
 
+        // `b` applies to the position it is attached to, so record it per side
+        let start_skip_blanks = start_opts.ignore_leading_blanks;
+        let end_skip_blanks = end_opts.ignore_leading_blanks;
+
         // Merge options (start options take precedence)
         let mut options = start_opts;
         // Apply end options only if they're set and start options aren't
@@ -79,6 +138,9 @@ impl SortKey {
         if !options.ignore_leading_blanks {
             options.ignore_leading_blanks = end_opts.ignore_leading_blanks;
         }
+        if !options.ignore_nonprinting {
+            options.ignore_nonprinting = end_opts.ignore_nonprinting;
+        }
         if !options.human_numeric {
             options.human_numeric = end_opts.human_numeric;
         }
@@ -88,6 +150,9 @@ impl SortKey {
         if !options.random {
             options.random = end_opts.random;
         }
+        if !options.length {
+            options.length = end_opts.length;
+        }
 
         Ok(Self {
             start_field,
@@ -95,6 +160,8 @@ impl SortKey {
             end_field,
             end_char,
             options,
+            start_skip_blanks,
+            end_skip_blanks,
         })
     }
 
@@ -151,9 +218,6 @@ impl SortKey {
                 let pos = char_str.parse::<usize>().map_err(|_| {
                     SortError::parse_error(&format!("invalid character position: {char_str}"))
                 })?;
-                if pos == 0 {
-                    return Err(SortError::parse_error("character positions start at 1"));
-                }
                 Some(pos)
             }
         } else {
@@ -173,7 +237,8 @@ impl SortKey {
                 'h' => options.human_numeric = true,
                 'V' => options.version = true,
                 'R' => options.random = true,
-                'i' => {} // ignore non-printing - not fully implemented
+                'i' => options.ignore_nonprinting = true,
+                'L' => options.length = true,
                 'z' => {} // zero-terminated - handled globally
                 _ => {
                     return Err(SortError::parse_error(&format!("invalid key option: {ch}")));
@@ -185,6 +250,49 @@ impl SortKey {
     }
 }
 
+/// Formats the key as a `-k` argument, e.g. `2.3b,4n`: `b` stays on the
+/// side it applies to and the ordering letters go last
+impl std::fmt::Display for SortKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.start_field)?;
+        if let Some(start_char) = self.start_char {
+            write!(f, ".{start_char}")?;
+        }
+        if self.start_skip_blanks {
+            write!(f, "b")?;
+        }
+        if let Some(end_field) = self.end_field {
+            write!(f, ",{end_field}")?;
+            if let Some(end_char) = self.end_char {
+                write!(f, ".{end_char}")?;
+            }
+            if self.end_skip_blanks {
+                write!(f, "b")?;
+            }
+        }
+
+        let options = &self.options;
+        for (set, letter) in [
+            (options.dictionary_order, 'd'),
+            (options.ignore_case, 'f'),
+            (options.general_numeric, 'g'),
+            (options.ignore_nonprinting, 'i'),
+            (options.length, 'L'),
+            (options.month, 'M'),
+            (options.human_numeric, 'h'),
+            (options.numeric, 'n'),
+            (options.random, 'R'),
+            (options.reverse, 'r'),
+            (options.version, 'V'),
+        ] {
+            if set {
+                write!(f, "{letter}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
 /// Main configuration structure for sort operations
 #[derive(Debug, Clone)]
 pub struct SortConfig {
@@ -196,10 +304,19 @@ pub struct SortConfig {
     pub unique: bool,
     /// Use stable sort algorithm
     pub stable: bool,
+    /// Write empty and all-blank lines after the rest of the sorted output
+    /// (`--empty-last`)
+    pub empty_last: bool,
     /// Check if input is already sorted
     pub check: bool,
+    /// Under check mode, count every adjacent out-of-order pair instead of
+    /// stopping at the first (`--check-count`)
+    pub check_count: bool,
     /// Merge already sorted files
     pub merge: bool,
+    /// Under merge mode, fail on the first input line that sorts before the
+    /// one above it (`--verify`)
+    pub verify: bool,
     /// Use zero bytes as line terminators instead of newlines
     pub zero_terminated: bool,
     /// Ignore case differences
@@ -210,8 +327,14 @@ pub struct SortConfig {
     pub ignore_leading_blanks: bool,
     /// Ignore non-printing characters
     pub ignore_nonprinting: bool,
+    /// Characters left out of every comparison, on top of `-d` and `-i`
+    /// (`--ignore-chars`); output lines keep them
+    pub ignore_chars: Option<String>,
     /// Field separator character
     pub field_separator: Option<char>,
+    /// Multi-byte field separator (`--field-separator-string`); takes the
+    /// place of `field_separator` when set
+    pub field_separator_string: Option<String>,
     /// Sort keys (field specifications)
     pub keys: Vec<SortKey>,
     /// Output file path
@@ -224,10 +347,47 @@ pub struct SortConfig {
     pub input_files: Vec<String>,
     /// Debug mode (for troubleshooting)
     pub debug: bool,
+    /// Re-check input files after sorting and warn if they changed
+    pub safe: bool,
     /// Compress temporary files
     pub compress_temp: bool,
+    /// Program that compresses temporary files (`--compress-program`)
+    pub compress_program: Option<String>,
     /// Temporary directory for external sorting
     pub temp_dir: Option<String>,
+    /// Whether the final output line gets a terminator
+    pub trailing_newline: TrailingNewline,
+    /// Seed for random ordering (`-R` and the per-key `R` option)
+    pub random_seed: Option<u64>,
+    /// Read parenthesized numbers such as `(100)` as negative under `-n`/`-g`
+    pub accounting: bool,
+    /// Fail when a line lacks a field that a key references, instead of
+    /// comparing it as empty
+    pub strict_fields: bool,
+    /// Compare bytewise whatever the locale environment says (`--byte-order`,
+    /// `--locale=C`)
+    pub byte_order: bool,
+    /// Count `-k` character positions in UTF-8 characters instead of bytes
+    /// (set under a UTF-8 locale)
+    pub utf8_positions: bool,
+    /// Comparisons performed by `compare_with_keys`, shared by clones of
+    /// this configuration
+    #[cfg(feature = "comparison-count")]
+    pub comparisons: std::sync::Arc<std::sync::atomic::AtomicU64>,
+    /// Longest line accepted, in bytes (`--max-line-length`)
+    pub max_line_length: Option<usize>,
+    /// What happens to lines longer than `max_line_length` (`--long-lines`)
+    pub long_lines: LongLines,
+    /// Write each output line's sort key before it, or in its place
+    /// (`--show-key`)
+    pub show_key: Option<ShowKey>,
+    /// Text between the key and the line, and between keys, under
+    /// `--show-key` (`--key-delimiter`)
+    pub key_delimiter: String,
+    /// Compression of the sorted output (`--output-compress`)
+    pub output_compression: OutputCompression,
+    /// In-memory sort algorithm (`--algorithm`)
+    pub algorithm: SortAlgorithm,
 }
 
 /// Sort mode enumeration
@@ -256,6 +416,79 @@ pub enum SortOrder {
     Descending,
 }
 
+/// What `--max-line-length` does with a longer line (`--long-lines`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LongLines {
+    /// Fail, naming the line
+    #[default]
+    Error,
+    /// Compare only the first `max_line_length` bytes; the output keeps
+    /// the whole line
+    Truncate,
+}
+
+/// What `--show-key` writes for each output line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShowKey {
+    /// The key, the delimiter, then the line
+    #[default]
+    Prefix,
+    /// The key alone
+    Replace,
+}
+
+/// Compression of the sorted output (`--output-compress`); writing gzip
+/// needs the `gzip` feature
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputCompression {
+    /// Gzip when the `-o` file name ends in `.gz`, otherwise none
+    #[default]
+    Auto,
+    /// Write the output as is, whatever its name
+    None,
+    /// Gzip the output, including to stdout
+    Gzip,
+}
+
+impl OutputCompression {
+    /// Whether output to `output_file` (stdout when `None`) is gzipped
+    pub fn applies_to(self, output_file: Option<&str>) -> bool {
+        match self {
+            OutputCompression::Auto => output_file.is_some_and(|file| file.ends_with(".gz")),
+            OutputCompression::None => false,
+            OutputCompression::Gzip => true,
+        }
+    }
+}
+
+/// In-memory sort algorithm (`--algorithm`); anything but `Auto` skips the
+/// input pattern detection, for reproducible timings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortAlgorithm {
+    /// Pick by size, mode and the patterns sampled from the input
+    #[default]
+    Auto,
+    /// Stable merge sort
+    Mergesort,
+    /// Three-way quicksort
+    Quicksort,
+    /// Radix sort where the comparison allows it (`-n` or plain byte order
+    /// of whole lines), whatever the input size; otherwise as `Auto`
+    Radix,
+}
+
+/// Policy for terminating the final output line (`--trailing-newline`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingNewline {
+    /// Always terminate the final line
+    Always,
+    /// Never terminate the final line
+    Never,
+    /// Terminate the final line only if the input ended with a terminator
+    #[default]
+    Preserve,
+}
+
 impl Default for SortConfig {
     fn default() -> Self {
         Self {
@@ -263,32 +496,107 @@ impl Default for SortConfig {
             reverse: false,
             unique: false,
             stable: false,
+            empty_last: false,
             check: false,
+            check_count: false,
             merge: false,
+            verify: false,
             zero_terminated: false,
             ignore_case: false,
             dictionary_order: false,
             ignore_leading_blanks: false,
             ignore_nonprinting: false,
+            ignore_chars: None,
             field_separator: None,
+            field_separator_string: None,
             keys: Vec::new(),
             output_file: None,
             buffer_size: None,
             parallel_threads: None,
             input_files: Vec::new(),
             debug: false,
+            safe: false,
             compress_temp: false,
+            compress_program: None,
             temp_dir: None,
+            trailing_newline: TrailingNewline::Preserve,
+            random_seed: None,
+            accounting: false,
+            strict_fields: false,
+            byte_order: false,
+            utf8_positions: false,
+            #[cfg(feature = "comparison-count")]
+            comparisons: Default::default(),
+            max_line_length: None,
+            long_lines: LongLines::Error,
+            show_key: None,
+            key_delimiter: "\t".to_string(),
+            output_compression: OutputCompression::Auto,
+            algorithm: SortAlgorithm::Auto,
         }
     }
 }
 
+/// Smallest memory budget given to sorts that may spill to disk; a
+/// smaller `-S` would split the input into a flood of tiny chunks, so it
+/// is raised to this, much as GNU sort has a floor of its own
+pub const MIN_EXTERNAL_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// Largest buffer size accepted on any platform
+const MAX_BUFFER_SIZE: u64 = 8 * 1024 * 1024 * 1024; // 8GB
+
+/// Largest buffer size this platform can address: 8GB, or less where
+/// `usize` is narrower (4GB - 1 on 32-bit targets)
+pub fn max_buffer_size() -> u64 {
+    MAX_BUFFER_SIZE.min(usize::MAX as u64)
+}
+
+/// Check a requested buffer size against `limit` and convert it to `usize`
+fn checked_buffer_size(size: u64, limit: u64) -> SortResult<usize> {
+    if size > limit {
+        return Err(SortError::invalid_buffer_size(&format!(
+            "buffer size {size} too large (maximum {limit} bytes on this platform)"
+        )));
+    }
+    usize::try_from(size).map_err(|_| SortError::invalid_buffer_size(&size.to_string()))
+}
+
+/// Total physical memory in bytes, the base for `-S N%`; where it cannot
+/// be read a conservative 1GB is assumed
+fn total_memory_bytes() -> u64 {
+    #[cfg(target_os = "linux")]
+    {
+        if let Ok(meminfo) = std::fs::read_to_string("/proc/meminfo") {
+            let total_kb = meminfo
+                .lines()
+                .find(|line| line.starts_with("MemTotal:"))
+                .and_then(|line| line.split_whitespace().nth(1))
+                .and_then(|kb| kb.parse::<u64>().ok());
+            if let Some(kb) = total_kb {
+                return kb * 1024;
+            }
+        }
+    }
+    1024 * 1024 * 1024
+}
+
 impl SortConfig {
     /// Create a new configuration with default values
     pub fn new() -> Self {
         Self::default()
     }
 
+    /// Parse a GNU sort command line into a configuration and file operands.
+    ///
+    /// `args` includes the program name, as `std::env::args()` does. Legacy
+    /// `+N -M` keys are accepted, and `SORT_OPTIONS` is honored (see
+    /// [`crate::cli::CliEnvironment`]). `--help`, `--version` and malformed options
+    /// come back as [`SortError::Usage`]; call `exit()` on the wrapped clap
+    /// error to print them the way the `sort` binary does.
+    pub fn from_args(args: &[impl AsRef<str>]) -> SortResult<(SortConfig, Vec<String>)> {
+        crate::cli::parse_args(args)
+    }
+
     /// Set the sort mode
     pub fn with_mode(mut self, mode: SortMode) -> Self {
         self.mode = mode;
@@ -313,36 +621,91 @@ impl SortConfig {
         self
     }
 
+    /// Move empty and all-blank lines to the end of the output
+    pub fn with_empty_last(mut self, empty_last: bool) -> Self {
+        self.empty_last = empty_last;
+        self
+    }
+
     /// Enable check mode
     pub fn with_check(mut self, check: bool) -> Self {
         self.check = check;
         self
     }
 
+    /// Enable check mode that counts all out-of-order pairs
+    pub fn with_check_count(mut self, check_count: bool) -> Self {
+        self.check_count = check_count;
+        self.check |= check_count;
+        self
+    }
+
     /// Enable merge mode
     pub fn with_merge(mut self, merge: bool) -> Self {
         self.merge = merge;
         self
     }
 
+    /// Check merge inputs for disorder as they are read
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
     /// Enable zero-terminated lines
     pub fn with_zero_terminated(mut self, zero_terminated: bool) -> Self {
         self.zero_terminated = zero_terminated;
         self
     }
 
+    /// Set characters to leave out of comparisons
+    pub fn with_ignore_chars(mut self, chars: Option<String>) -> Self {
+        self.ignore_chars = chars.filter(|chars| !chars.is_empty());
+        self
+    }
+
     /// Set field separator
     pub fn with_field_separator(mut self, separator: Option<char>) -> Self {
         self.field_separator = separator;
         self
     }
 
+    /// Set multi-byte field separator
+    pub fn with_field_separator_string(mut self, separator: Option<String>) -> Self {
+        self.field_separator_string = separator;
+        self
+    }
+
     /// Add a sort key
     pub fn add_key(mut self, key: SortKey) -> Self {
         self.keys.push(key);
         self
     }
 
+    /// Give each key without ordering options of its own the global ones,
+    /// as GNU sort does: `sort -n -k2` compares field 2 numerically
+    pub fn inherit_key_options(&mut self) {
+        let global = SortKeyOptions {
+            numeric: self.mode == SortMode::Numeric,
+            general_numeric: self.mode == SortMode::GeneralNumeric,
+            month: self.mode == SortMode::Month,
+            reverse: self.reverse,
+            ignore_case: self.ignore_case,
+            dictionary_order: self.dictionary_order,
+            ignore_leading_blanks: self.ignore_leading_blanks,
+            ignore_nonprinting: self.ignore_nonprinting,
+            human_numeric: self.mode == SortMode::HumanNumeric,
+            version: self.mode == SortMode::Version,
+            random: self.mode == SortMode::Random,
+            length: false,
+        };
+        for key in self.keys.iter_mut().filter(|key| key.options.is_empty()) {
+            key.options = global.clone();
+            key.start_skip_blanks = global.ignore_leading_blanks;
+            key.end_skip_blanks = global.ignore_leading_blanks;
+        }
+    }
+
     /// Set output file
     pub fn with_output_file(mut self, output_file: Option<String>) -> Self {
         self.output_file = output_file;
@@ -373,13 +736,147 @@ impl SortConfig {
         self
     }
 
-    /// Parse buffer size from string (simplified)
+    /// Enable safe mode (warn about inputs modified during the sort)
+    pub fn with_safe(mut self, safe: bool) -> Self {
+        self.safe = safe;
+        self
+    }
+
+    /// Set the trailing newline policy
+    pub fn with_trailing_newline(mut self, policy: TrailingNewline) -> Self {
+        self.trailing_newline = policy;
+        self
+    }
+
+    /// Set the directory for temporary files
+    pub fn with_temp_dir(mut self, temp_dir: Option<String>) -> Self {
+        self.temp_dir = temp_dir;
+        self
+    }
+
+    /// Compress temporary files with `program`, or leave them uncompressed
+    pub fn with_compress_program(mut self, program: Option<String>) -> Self {
+        self.compress_temp = program.is_some();
+        self.compress_program = program;
+        self
+    }
+
+    /// Set the random seed
+    pub fn with_random_seed(mut self, seed: Option<u64>) -> Self {
+        self.random_seed = seed;
+        self
+    }
+
+    /// Read parenthesized numbers as negative
+    pub fn with_accounting(mut self, accounting: bool) -> Self {
+        self.accounting = accounting;
+        self
+    }
+
+    /// Set the compression of the sorted output
+    pub fn with_output_compression(mut self, compression: OutputCompression) -> Self {
+        self.output_compression = compression;
+        self
+    }
+
+    /// Set the in-memory sort algorithm
+    pub fn with_algorithm(mut self, algorithm: SortAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// Reject lines that lack a field referenced by a key
+    pub fn with_strict_fields(mut self, strict_fields: bool) -> Self {
+        self.strict_fields = strict_fields;
+        self
+    }
+
+    /// Force byte comparison regardless of `LC_ALL`/`LC_COLLATE`/`LANG`
+    pub fn with_byte_order(mut self, byte_order: bool) -> Self {
+        self.byte_order = byte_order;
+        self
+    }
+
+    /// Address `-k` character positions by UTF-8 character
+    pub fn with_utf8_positions(mut self, utf8_positions: bool) -> Self {
+        self.utf8_positions = utf8_positions;
+        self
+    }
+
+    /// Reject lines longer than `max_line_length` bytes, or with `None` accept
+    /// lines of any length
+    pub fn with_max_line_length(mut self, max_line_length: Option<usize>) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    /// Set what happens to lines longer than the maximum line length
+    pub fn with_long_lines(mut self, long_lines: LongLines) -> Self {
+        self.long_lines = long_lines;
+        self
+    }
+
+    /// Write each line's sort key before it or in its place, or with `None`
+    /// write the lines alone
+    pub fn with_show_key(mut self, show_key: Option<ShowKey>) -> Self {
+        self.show_key = show_key;
+        self
+    }
+
+    /// Set the text written between a shown key and its line
+    pub fn with_key_delimiter(mut self, delimiter: impl Into<String>) -> Self {
+        self.key_delimiter = delimiter.into();
+        self
+    }
+
+    /// Whether output lines get more than their own text: keys under
+    /// `--show-key`, underlines under `--debug`
+    pub fn annotates_output(&self) -> bool {
+        self.show_key.is_some() || self.debug
+    }
+
+    /// Bytes of each line that comparisons look at, when `--long-lines=truncate`
+    /// cuts longer lines short
+    pub fn comparison_limit(&self) -> Option<usize> {
+        self.max_line_length
+            .filter(|_| self.long_lines == LongLines::Truncate)
+    }
+
+    /// Comparisons performed so far by sorts using this configuration
+    #[cfg(feature = "comparison-count")]
+    pub fn comparison_count(&self) -> u64 {
+        self.comparisons.load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Parse a `-S` size: a number followed by an optional multiplier,
+    /// `b` for bytes, `K` (the default), `M`, `G`, `T` or `P` for powers of
+    /// 1024, or `%` for that share of total system memory
     pub fn set_buffer_size_from_string(&mut self, size_str: &str) -> SortResult<()> {
-        // Simple parsing for now - just parse as number
-        let size = size_str
-            .parse::<usize>()
-            .map_err(|_| SortError::internal("Invalid buffer size"))?;
-        self.buffer_size = Some(size);
+        let invalid = || SortError::invalid_buffer_size(size_str);
+        let digits = size_str
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(size_str.len());
+        let (number, suffix) = size_str.split_at(digits);
+        let number = number.parse::<u64>().map_err(|_| invalid())?;
+        let size = if suffix == "%" {
+            if number > 100 {
+                return Err(invalid());
+            }
+            // A share of a large machine may pass the cap; use the cap then
+            (total_memory_bytes() / 100 * number).min(max_buffer_size())
+        } else {
+            let exponent = match suffix {
+                "b" => 0,
+                "" | "K" | "k" => 1,
+                "M" | "m" => 2,
+                "G" | "g" => 3,
+                "T" | "t" => 4,
+                "P" | "p" => 5,
+                _ => return Err(invalid()),
+            };
+            number.saturating_mul(1u64 << (10 * exponent))
+        };
+        self.buffer_size = Some(checked_buffer_size(size, max_buffer_size())?);
         Ok(())
     }
 
@@ -410,6 +907,11 @@ impl SortConfig {
                 ));
             }
         }
+        if self.field_separator_string.as_deref() == Some("") {
+            return Err(SortError::invalid_field_separator(
+                "empty field separator string",
+            ));
+        }
 
         // Check for reasonable buffer size
         if let Some(buffer_size) = self.buffer_size {
@@ -418,13 +920,7 @@ impl SortConfig {
                     "buffer size too small (minimum 1KB)",
                 ));
             }
-            // Use u64 to avoid overflow on 32-bit systems
-            const MAX_BUFFER_SIZE: u64 = 8 * 1024 * 1024 * 1024; // 8GB
-            if buffer_size as u64 > MAX_BUFFER_SIZE {
-                return Err(SortError::invalid_buffer_size(
-                    "buffer size too large (maximum 8GB)",
-                ));
-            }
+            checked_buffer_size(buffer_size as u64, max_buffer_size())?;
         }
 
         // Validate thread count
@@ -491,6 +987,26 @@ impl SortConfig {
         self.buffer_size.unwrap_or(1024 * 1024) // 1MB default
     }
 
+    /// Whether text comparisons collate with the locale environment's
+    /// `LC_COLLATE`: not under `--byte-order` or `--locale=C`
+    pub fn collates(&self) -> bool {
+        !self.byte_order && crate::locale::LocaleConfig::is_enabled()
+    }
+
+    /// Memory budget from `-S` for sorts that may spill to disk, raised to
+    /// [`MIN_EXTERNAL_BUFFER_SIZE`]; `None` without `-S`
+    pub fn external_buffer_size(&self) -> Option<usize> {
+        self.buffer_size
+            .map(|size| size.max(MIN_EXTERNAL_BUFFER_SIZE))
+    }
+
+    /// Get effective random seed; without a configured seed one is drawn
+    /// once per process so all comparisons in a run agree
+    pub fn effective_random_seed(&self) -> u64 {
+        self.random_seed
+            .unwrap_or_else(|| *PROCESS_RANDOM_SEED.get_or_init(rand::random))
+    }
+
     /// Get effective thread count
     pub fn effective_thread_count(&self) -> usize {
         self.parallel_threads.unwrap_or_else(num_cpus::get)
@@ -504,6 +1020,78 @@ impl SortConfig {
         config
     }
 
+    /// Describe, one line per step, how two lines are compared: each key with
+    /// its field range, comparison and direction, then the last resort.
+    /// Character positions within a field are given as the bytes or UTF-8
+    /// characters they count under the locale.
+    pub fn comparator_chain(&self) -> Vec<String> {
+        let direction = |reverse: bool| if reverse { "descending" } else { "ascending" };
+        let unit = if self.utf8_positions {
+            "character"
+        } else {
+            "byte"
+        };
+        let mut chain = Vec::new();
+
+        if self.keys.is_empty() {
+            chain.push(format!(
+                "key: whole line, {}, {}",
+                self.mode,
+                direction(self.reverse)
+            ));
+        }
+        for (index, key) in self.keys.iter().enumerate() {
+            let position = |field: usize, char_pos: Option<usize>| match char_pos {
+                Some(c) => format!("field {field} {unit} {c}"),
+                None => format!("field {field}"),
+            };
+            let end = match (key.end_field, key.end_char) {
+                (Some(field), Some(c)) => position(field, Some(c)),
+                (Some(field), None) => format!("end of field {field}"),
+                (None, _) => "end of line".to_string(),
+            };
+
+            let kind = if key.options.length {
+                "length".to_string()
+            } else {
+                key.options.mode().to_string()
+            };
+            let mut description = format!(
+                "key {} (-k{key}): {} to {end}, {kind}",
+                index + 1,
+                position(key.start_field, key.start_char),
+            );
+            if key.start_skip_blanks || key.end_skip_blanks {
+                description.push_str(", skipping leading blanks");
+            }
+            if key.options.dictionary_order {
+                description.push_str(", dictionary order");
+            }
+            if key.options.ignore_case {
+                description.push_str(", ignoring case");
+            }
+            if key.options.ignore_nonprinting {
+                description.push_str(", ignoring non-printing");
+            }
+            // Keys without options of their own inherit the global -r
+            let reverse = key.options.reverse || (key.options.is_empty() && self.reverse);
+            description.push_str(&format!(", {}", direction(reverse)));
+            chain.push(description);
+        }
+
+        chain.push(if self.stable {
+            "last resort: none, equal lines keep their input order (-s)".to_string()
+        } else if self.unique {
+            "last resort: none, the first of equal lines is kept (-u)".to_string()
+        } else {
+            format!(
+                "last resort: whole line, bytewise, {}",
+                direction(self.reverse)
+            )
+        });
+        chain
+    }
+
     /// Create a configuration for check operations
     pub fn for_check(&self) -> Self {
         let mut config = self.clone();
@@ -546,6 +1134,111 @@ impl std::fmt::Display for SortMode {
     }
 }
 
+impl FromStr for TrailingNewline {
+    type Err = SortError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(TrailingNewline::Always),
+            "never" => Ok(TrailingNewline::Never),
+            "preserve" => Ok(TrailingNewline::Preserve),
+            _ => Err(SortError::parse_error(&format!(
+                "invalid trailing newline policy: {s}"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for TrailingNewline {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TrailingNewline::Always => "always",
+            TrailingNewline::Never => "never",
+            TrailingNewline::Preserve => "preserve",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl FromStr for LongLines {
+    type Err = SortError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(LongLines::Error),
+            "truncate" => Ok(LongLines::Truncate),
+            _ => Err(SortError::parse_error(&format!(
+                "invalid long line policy: {s}"
+            ))),
+        }
+    }
+}
+
+impl FromStr for OutputCompression {
+    type Err = SortError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(OutputCompression::Auto),
+            "none" => Ok(OutputCompression::None),
+            "gzip" => Ok(OutputCompression::Gzip),
+            _ => Err(SortError::parse_error(&format!(
+                "invalid output compression: {s}"
+            ))),
+        }
+    }
+}
+
+impl FromStr for SortAlgorithm {
+    type Err = SortError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(SortAlgorithm::Auto),
+            "mergesort" => Ok(SortAlgorithm::Mergesort),
+            "quicksort" => Ok(SortAlgorithm::Quicksort),
+            "radix" => Ok(SortAlgorithm::Radix),
+            _ => Err(SortError::parse_error(&format!(
+                "invalid sort algorithm: {s}"
+            ))),
+        }
+    }
+}
+
+impl FromStr for ShowKey {
+    type Err = SortError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "prefix" => Ok(ShowKey::Prefix),
+            "replace" => Ok(ShowKey::Replace),
+            _ => Err(SortError::parse_error(&format!(
+                "invalid show-key mode: {s}"
+            ))),
+        }
+    }
+}
+
+impl std::fmt::Display for ShowKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            ShowKey::Prefix => "prefix",
+            ShowKey::Replace => "replace",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl std::fmt::Display for LongLines {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            LongLines::Error => "error",
+            LongLines::Truncate => "truncate",
+        };
+        write!(f, "{name}")
+    }
+}
+
 /// Builder pattern for creating configurations
 pub struct SortConfigBuilder {
     config: SortConfig,
@@ -607,6 +1300,12 @@ impl SortConfigBuilder {
         self
     }
 
+    /// Set multi-byte field separator
+    pub fn field_separator_string(mut self, separator: &str) -> Self {
+        self.config.field_separator_string = Some(separator.to_string());
+        self
+    }
+
     /// Add a sort key
     pub fn key(mut self, key: SortKey) -> Self {
         self.config.keys.push(key);
@@ -625,6 +1324,12 @@ impl SortConfigBuilder {
         self
     }
 
+    /// Set the trailing newline policy
+    pub fn trailing_newline(mut self, policy: TrailingNewline) -> Self {
+        self.config.trailing_newline = policy;
+        self
+    }
+
     /// Build the final configuration
     pub fn build(self) -> SortResult<SortConfig> {
         self.config.validate()?;
@@ -734,6 +1439,76 @@ mod tests {
         assert!("invalid".parse::<SortMode>().is_err());
     }
 
+    #[test]
+    fn test_sort_key_display_round_trips() {
+        for spec in ["2,2n", "1,1r", "1.3,1.3", "2b,3.1b", "3dfr", "1.2b,4.5n"] {
+            let key = SortKey::parse(spec).unwrap();
+            assert_eq!(key.to_string(), spec);
+        }
+        assert_eq!(SortKey::parse("2n,3b").unwrap().to_string(), "2,3bn");
+    }
+
+    #[test]
+    fn test_comparator_chain() {
+        let config = SortConfig::default()
+            .add_key(SortKey::parse("2,2n").unwrap())
+            .add_key(SortKey::parse("1,1r").unwrap());
+        assert_eq!(
+            config.comparator_chain(),
+            [
+                "key 1 (-k2,2n): field 2 to end of field 2, numeric, ascending",
+                "key 2 (-k1,1r): field 1 to end of field 1, lexicographic, descending",
+                "last resort: whole line, bytewise, ascending",
+            ]
+        );
+
+        let config = SortConfig::default().with_reverse(true).with_stable(true);
+        assert_eq!(
+            config.comparator_chain(),
+            [
+                "key: whole line, lexicographic, descending",
+                "last resort: none, equal lines keep their input order (-s)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sort_key_blanks_per_position() {
+        let start = SortKey::parse("2b,3").expect("Failed to parse key");
+        assert!(start.start_skip_blanks);
+        assert!(!start.end_skip_blanks);
+
+        let end = SortKey::parse("2,3b").expect("Failed to parse key");
+        assert!(!end.start_skip_blanks);
+        assert!(end.end_skip_blanks);
+
+        // Either way the key has options of its own
+        assert!(!start.options.is_empty());
+        assert!(!end.options.is_empty());
+    }
+
+    #[test]
+    fn test_trailing_newline_from_str() {
+        for policy in [
+            TrailingNewline::Always,
+            TrailingNewline::Never,
+            TrailingNewline::Preserve,
+        ] {
+            assert_eq!(
+                policy
+                    .to_string()
+                    .parse::<TrailingNewline>()
+                    .expect("Failed to parse trailing newline policy"),
+                policy
+            );
+        }
+        assert!("sometimes".parse::<TrailingNewline>().is_err());
+        assert_eq!(
+            SortConfig::default().trailing_newline,
+            TrailingNewline::Preserve
+        );
+    }
+
     #[test]
     fn test_validate_conflicting_options() {
         let config = SortConfig {
@@ -752,6 +1527,61 @@ mod tests {
 
         let config = SortConfig::default().with_buffer_size(Some(2048));
         assert_eq!(config.effective_buffer_size(), 2048);
+        // Too small to spill to disk in sensible chunks
+        assert_eq!(
+            config.external_buffer_size(),
+            Some(MIN_EXTERNAL_BUFFER_SIZE)
+        );
+
+        let config = SortConfig::default().with_buffer_size(Some(64 << 20));
+        assert_eq!(config.external_buffer_size(), Some(64 << 20));
+        assert_eq!(SortConfig::default().external_buffer_size(), None);
+    }
+
+    #[test]
+    fn test_buffer_size_platform_limit() {
+        let mut config = SortConfig::default();
+        config.set_buffer_size_from_string("4096b").unwrap();
+        assert_eq!(config.buffer_size, Some(4096));
+        assert!(config.set_buffer_size_from_string("8589934593b").is_err());
+
+        // A 32-bit target can address less than the 8GB cap
+        let limit_32 = u32::MAX as u64;
+        assert_eq!(
+            checked_buffer_size(limit_32, limit_32).unwrap() as u64,
+            limit_32
+        );
+        assert!(matches!(
+            checked_buffer_size(limit_32 + 1, limit_32),
+            Err(SortError::InvalidBufferSize { .. })
+        ));
+        assert!(max_buffer_size() <= usize::MAX as u64);
+    }
+
+    #[test]
+    fn test_buffer_size_suffixes() {
+        let mut config = SortConfig::default();
+        config.set_buffer_size_from_string("100K").unwrap();
+        assert_eq!(config.buffer_size, Some(100 * 1024));
+        config.set_buffer_size_from_string("2M").unwrap();
+        assert_eq!(config.buffer_size, Some(2 * 1024 * 1024));
+        // Without a suffix the number counts kilobytes, as in GNU sort
+        config.set_buffer_size_from_string("64").unwrap();
+        assert_eq!(config.buffer_size, Some(64 * 1024));
+
+        config.set_buffer_size_from_string("10%").unwrap();
+        let expected = (total_memory_bytes() / 100 * 10).min(max_buffer_size());
+        assert_eq!(config.buffer_size, Some(expected as usize));
+
+        for bad in ["10X", "M", "1.5M", "101%", ""] {
+            assert!(
+                matches!(
+                    config.set_buffer_size_from_string(bad),
+                    Err(SortError::InvalidBufferSize { .. })
+                ),
+                "{bad:?} should be rejected"
+            );
+        }
     }
 
     #[test]
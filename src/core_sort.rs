@@ -1,30 +1,398 @@
 use crate::adaptive_sort::{AdaptiveSort, DataPattern, DataType};
 use crate::args::SortArgs;
-use crate::config::SortConfig;
+use crate::config::{SortAlgorithm, SortConfig, TrailingNewline};
 use crate::external_sort::ExternalSort;
 use crate::hash_sort::HashSort;
 use crate::radix_sort::RadixSort;
-use crate::zero_copy::{Line, MappedFile, ZeroCopyReader};
-use crossbeam_channel::{bounded, Receiver, Sender};
+use crate::zero_copy::{
+    check_input_lines, parse_lines_with_terminator, Line, LineChunk, MappedFile, ZeroCopyReader,
+};
 use rand::rngs::StdRng;
-use rand::{Rng, SeedableRng};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{self, BufWriter, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering as AtomicOrdering};
 use std::sync::Arc;
 use std::thread;
 
+/// Default amount of stdin held in memory before spilling to the external sorter
+pub(crate) const STDIN_MEMORY_BUDGET: usize = 100 * 1024 * 1024; // 100MB
+
+/// Leading lines sampled for the `--debug` numeric warnings
+const DEBUG_SAMPLE_LINES: usize = 1000;
+
+/// Stdin contents after a bounded read
+enum StdinInput {
+    /// Input fit within the memory budget
+    InMemory(Vec<u8>),
+    /// Input exceeded the budget and was streamed to a temporary file
+    Spilled(crate::temp::Temporary<tempfile::NamedTempFile>),
+}
+
+/// Writer that holds back a trailing line terminator until `finish`, so the
+/// final output line can be written with or without it; under `--show-key`
+/// it also puts each line's key in front of it, and under `--debug` it
+/// underlines what each line is compared by. Under `--empty-last` it holds
+/// back blank lines and writes them after all the others.
+struct FinalTerminatorWriter<W: Write> {
+    inner: W,
+    terminator: u8,
+    pending: bool,
+    keep_final: bool,
+    /// Configuration of the annotations, when output is written line by line
+    annotate: Option<SortConfig>,
+    /// Start of a line whose terminator has not been written yet
+    partial: Vec<u8>,
+    /// Blank lines held back for the end, when `--empty-last` asks for it
+    blank_lines: Option<Vec<Vec<u8>>>,
+}
+
+impl<W: Write> FinalTerminatorWriter<W> {
+    fn new(inner: W, terminator: u8, keep_final: bool) -> Self {
+        Self {
+            inner,
+            terminator,
+            pending: false,
+            keep_final,
+            annotate: None,
+            partial: Vec::new(),
+            blank_lines: None,
+        }
+    }
+
+    /// Annotate each line as `config` asks, with `--show-key` or `--debug`
+    fn with_annotations(mut self, config: &SortConfig) -> Self {
+        self.annotate = config.annotates_output().then(|| config.clone());
+        self.blank_lines = config.empty_last.then(Vec::new);
+        self
+    }
+
+    /// Write the held-back blank lines and, if the policy keeps it, the
+    /// final terminator, then flush
+    fn finish(mut self) -> io::Result<W> {
+        if !self.partial.is_empty() {
+            let mut line = std::mem::take(&mut self.partial);
+            if self
+                .blank_lines
+                .as_ref()
+                .is_some_and(|blank| !blank.is_empty())
+            {
+                // Blank lines follow, so this is no longer the final line
+                line.push(self.terminator);
+            }
+            self.write_line(&line)?;
+        }
+        for line in self.blank_lines.take().unwrap_or_default() {
+            self.write_annotated(&line)?;
+        }
+        if self.pending && self.keep_final {
+            self.inner.write_all(&[self.terminator])?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl FinalTerminatorWriter<OutputSink> {
+    /// Finish the output and commit it to its destination
+    fn commit(self) -> io::Result<()> {
+        self.finish()?.commit()
+    }
+}
+
+impl<W: Write> FinalTerminatorWriter<W> {
+    /// Write one line, or hold it back for the end if it is blank under
+    /// `--empty-last`
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if let Some(blank_lines) = &mut self.blank_lines {
+            let body = line.strip_suffix(&[self.terminator]).unwrap_or(line);
+            if body.iter().all(|&b| b == b' ' || b == b'\t') {
+                blank_lines.push(line.to_vec());
+                return Ok(());
+            }
+        }
+        self.write_annotated(line)
+    }
+
+    /// Write `buf`, holding back its terminator if it ends with one
+    fn write_through(&mut self, buf: &[u8]) -> io::Result<()> {
+        let Some((&last, body)) = buf.split_last() else {
+            return Ok(());
+        };
+
+        if self.pending {
+            self.inner.write_all(&[self.terminator])?;
+        }
+
+        if last == self.terminator {
+            self.inner.write_all(body)?;
+            self.pending = true;
+        } else {
+            self.inner.write_all(buf)?;
+            self.pending = false;
+        }
+        Ok(())
+    }
+
+    /// Write one line, with or without its terminator, annotated.
+    ///
+    /// `--show-key` writes the `-k` keys (or the whole line without keys)
+    /// joined by the key delimiter, then the delimiter and the line unless
+    /// the key replaces it. `--debug` follows the line with its underlines.
+    fn write_annotated(&mut self, line: &[u8]) -> io::Result<()> {
+        let Some(config) = &self.annotate else {
+            return self.write_through(line);
+        };
+
+        let (body, terminator) = match line.split_last() {
+            Some((&last, body)) if last == self.terminator => (body, &line[body.len()..]),
+            _ => (line, &[][..]),
+        };
+        let parsed = Line::new(body);
+        let mut out = Vec::with_capacity(2 * line.len());
+        if let Some(show_key) = config.show_key {
+            let delimiter = config.key_delimiter.as_bytes();
+            if config.keys.is_empty() {
+                out.extend_from_slice(body);
+            }
+            for (index, key) in config.keys.iter().enumerate() {
+                if index > 0 {
+                    out.extend_from_slice(delimiter);
+                }
+                let field = parsed.extract_key_with_config(key, config.field_separator, config);
+                out.extend_from_slice(field.unwrap_or_default());
+            }
+            if show_key != crate::config::ShowKey::Replace {
+                out.extend_from_slice(delimiter);
+                out.extend_from_slice(body);
+            }
+        } else if config.debug {
+            // As in GNU sort, tabs show as `>` so that underlines line up
+            out.extend(body.iter().map(|&b| if b == b'\t' { b'>' } else { b }));
+        } else {
+            out.extend_from_slice(body);
+        }
+        if config.debug {
+            for underline in parsed.debug_underlines(config) {
+                out.push(self.terminator);
+                out.extend_from_slice(underline.as_bytes());
+            }
+        }
+        out.extend_from_slice(terminator);
+        self.write_through(&out)
+    }
+}
+
+impl<W: Write> Write for FinalTerminatorWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.annotate.is_none() && self.blank_lines.is_none() {
+            self.write_through(buf)?;
+            return Ok(buf.len());
+        }
+
+        // Annotations and blank lines go line by line, so gather each line
+        // whole first
+        let mut rest = buf;
+        while let Some(end) = rest.iter().position(|&b| b == self.terminator) {
+            self.partial.extend_from_slice(&rest[..=end]);
+            rest = &rest[end + 1..];
+            let line = std::mem::take(&mut self.partial);
+            self.write_line(&line)?;
+        }
+        self.partial.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Destination of the sorted output.
+///
+/// A regular `-o` file is written to a temporary file in the same directory
+/// and renamed over the target by `commit`, so a failed sort leaves an
+/// existing output untouched and the output may safely be one of the inputs.
+enum OutputSink {
+    Stdout(BufWriter<io::Stdout>),
+    /// Targets that cannot be replaced by a rename, such as `/dev/null`
+    Direct(BufWriter<File>),
+    Staged {
+        writer: BufWriter<tempfile::NamedTempFile>,
+        target: PathBuf,
+        /// Permissions of the file being replaced, restored on commit
+        permissions: Option<std::fs::Permissions>,
+    },
+    /// Gzip in front of another sink
+    #[cfg(feature = "gzip")]
+    Gzip(Box<flate2::write::GzEncoder<OutputSink>>),
+}
+
+impl OutputSink {
+    /// Open `output`, or stdout when no output file is given
+    fn open(output: Option<&str>) -> io::Result<Self> {
+        let Some(output) = output else {
+            return Ok(Self::Stdout(BufWriter::new(io::stdout())));
+        };
+
+        let mut target = PathBuf::from(output);
+        let permissions = match std::fs::metadata(&target) {
+            Ok(metadata) if !metadata.is_file() => {
+                return Ok(Self::Direct(BufWriter::new(File::create(&target)?)));
+            }
+            Ok(metadata) => {
+                // Replace the file a symlink points to, not the link itself
+                target = std::fs::canonicalize(&target)?;
+                Some(metadata.permissions())
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e),
+        };
+
+        let dir = match target.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut builder = tempfile::Builder::new();
+        builder.prefix(".sort");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            // Same mode as `File::create` would give, subject to the umask
+            builder.permissions(std::fs::Permissions::from_mode(0o666));
+        }
+        let temp = builder.tempfile_in(dir)?;
+
+        Ok(Self::Staged {
+            writer: BufWriter::new(temp),
+            target,
+            permissions,
+        })
+    }
+
+    /// Gzip everything written to this sink
+    fn gzip(self) -> io::Result<Self> {
+        #[cfg(feature = "gzip")]
+        {
+            let encoder = flate2::write::GzEncoder::new(self, flate2::Compression::default());
+            Ok(Self::Gzip(Box::new(encoder)))
+        }
+        #[cfg(not(feature = "gzip"))]
+        {
+            drop(self);
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "gzip output needs sort built with the `gzip` feature",
+            ))
+        }
+    }
+
+    /// Path of the staging file, for writers that produce the output by path
+    fn staging_path(&self) -> Option<&Path> {
+        match self {
+            Self::Staged { writer, .. } => Some(writer.get_ref().path()),
+            _ => None,
+        }
+    }
+
+    /// Flush the output and move a staged file into place; dropping the sink
+    /// without committing discards the staged file
+    fn commit(self) -> io::Result<()> {
+        match self {
+            Self::Stdout(mut writer) => writer.flush(),
+            Self::Direct(mut writer) => writer.flush(),
+            Self::Staged {
+                writer,
+                target,
+                permissions,
+            } => {
+                let temp = writer.into_inner().map_err(|e| e.into_error())?;
+                if let Some(permissions) = permissions {
+                    std::fs::set_permissions(temp.path(), permissions)?;
+                }
+                temp.persist(&target)?;
+                Ok(())
+            }
+            #[cfg(feature = "gzip")]
+            Self::Gzip(encoder) => encoder.finish()?.commit(),
+        }
+    }
+}
+
+impl Write for OutputSink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout(writer) => writer.write(buf),
+            Self::Direct(writer) => writer.write(buf),
+            Self::Staged { writer, .. } => writer.write(buf),
+            #[cfg(feature = "gzip")]
+            Self::Gzip(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout(writer) => writer.flush(),
+            Self::Direct(writer) => writer.flush(),
+            Self::Staged { writer, .. } => writer.flush(),
+            #[cfg(feature = "gzip")]
+            Self::Gzip(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Size and modification time of an input file, recorded when the sort starts
+struct InputSnapshot {
+    path: PathBuf,
+    len: u64,
+    modified: Option<std::time::SystemTime>,
+}
+
+impl InputSnapshot {
+    fn capture(path: &Path) -> io::Result<Self> {
+        let metadata = std::fs::metadata(path)?;
+        Ok(Self {
+            path: path.to_path_buf(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+        })
+    }
+
+    /// Whether the file's size or modification time differs from the snapshot
+    fn changed(&self) -> bool {
+        match std::fs::metadata(&self.path) {
+            Ok(metadata) => metadata.len() != self.len || metadata.modified().ok() != self.modified,
+            Err(_) => true,
+        }
+    }
+}
+
 /// Core sort implementation using zero-copy architecture
 pub struct CoreSort {
     args: SortArgs,
     config: SortConfig,
+    /// Whether the last input ended with a line terminator (`--trailing-newline=preserve`)
+    input_terminated: AtomicBool,
 }
 
 impl CoreSort {
-    pub fn new(args: SortArgs, config: SortConfig) -> Self {
-        Self { args, config }
+    pub fn new(args: SortArgs, mut config: SortConfig) -> Self {
+        config.inherit_key_options();
+        let mut sorter = Self {
+            args,
+            config,
+            input_terminated: AtomicBool::new(true),
+        };
+        // `-u` keeps the first of each run of equal lines in input order,
+        // which takes a stable sort unless equal lines are identical
+        if sorter.args.unique && !sorter.is_plain_byte_order() {
+            sorter.args.stable = true;
+        }
+        sorter
     }
 
     /// Compare two lines using cached data - optimized for hot path
@@ -36,7 +404,7 @@ impl CoreSort {
         cache: &ComparisonCache,
     ) -> Ordering {
         // Fast path for common case - direct line comparison
-        if !self.args.numeric_sort && !self.config.ignore_case && !self.args.random_sort {
+        if !self.numeric_fast_paths() && !self.case_fold_fast_path() && !self.args.random_sort {
             return a.line.compare_with_keys(
                 &b.line,
                 &self.config.keys,
@@ -46,7 +414,7 @@ impl CoreSort {
         }
 
         // If numeric sort, use cached numeric values
-        if self.args.numeric_sort {
+        if self.numeric_fast_paths() {
             if let (Some(a_num), Some(b_num)) = (
                 cache
                     .entries
@@ -67,16 +435,18 @@ impl CoreSort {
                     a_num.partial_cmp(&b_num).unwrap_or(Ordering::Equal)
                 };
 
-                return if self.args.reverse {
+                // Equal numbers such as `007` and `7` fall back to the whole line
+                let cmp = if self.args.reverse {
                     cmp.reverse()
                 } else {
                     cmp
                 };
+                return cmp.then_with(|| a.line.last_resort_compare(&b.line, &self.config));
             }
         }
 
         // If case-insensitive, use cached folded bytes
-        if self.config.ignore_case {
+        if self.case_fold_fast_path() {
             if let (Some(a_folded), Some(b_folded)) = (
                 cache
                     .entries
@@ -87,12 +457,14 @@ impl CoreSort {
                     .get(b.original_index)
                     .and_then(|e| e.folded_bytes.as_ref()),
             ) {
+                // Lines differing only in case fall back to the whole line
                 let cmp = a_folded.cmp(b_folded);
-                return if self.args.reverse {
+                let cmp = if self.args.reverse {
                     cmp.reverse()
                 } else {
                     cmp
                 };
+                return cmp.then_with(|| a.line.last_resort_compare(&b.line, &self.config));
             }
         }
 
@@ -126,24 +498,144 @@ impl CoreSort {
         )
     }
 
-    /// Fast comparison for direct Line sorting with index tracking
+    /// Fast comparison for direct Line sorting with index tracking.
+    /// Global reverse is applied inside `compare_with_keys`.
     #[inline]
     fn compare_lines_direct(&self, a_line: &Line, b_line: &Line) -> Ordering {
-        let cmp = a_line.compare_with_keys(
+        a_line.compare_with_keys(
             b_line,
             &self.config.keys,
             self.config.field_separator,
             &self.config,
-        );
+        )
+    }
 
-        if self.args.reverse {
-            cmp.reverse()
+    /// Whether sorting `len` lines should run in parallel: the input must be
+    /// large enough to benefit and `--parallel` must allow more than one thread
+    fn use_parallel(&self, len: usize) -> bool {
+        const PARALLEL_THRESHOLD: usize = 8192;
+        len >= PARALLEL_THRESHOLD && self.config.effective_thread_count() > 1
+    }
+
+    /// Whether `-n` may use the integer radix sort and cached numeric values
+    /// of whole lines; keys, `--accounting` and `--ignore-chars` need the
+    /// full comparator
+    fn numeric_fast_paths(&self) -> bool {
+        self.args.numeric_sort
+            && !self.config.accounting
+            && self.config.keys.is_empty()
+            && self.config.ignore_chars.is_none()
+    }
+
+    /// Whether `-f` may compare cached case-folded whole lines: no keys, no
+    /// other option that changes the comparison and no locale collation
+    fn case_fold_fast_path(&self) -> bool {
+        self.config.ignore_case
+            && self.config.mode == crate::config::SortMode::Lexicographic
+            && self.config.keys.is_empty()
+            && !self.config.dictionary_order
+            && !self.config.ignore_leading_blanks
+            && !self.config.ignore_nonprinting
+            && self.config.ignore_chars.is_none()
+            && !self.config.collates()
+    }
+
+    /// Whether lines are ordered by their raw bytes alone: whole-line
+    /// lexicographic comparison with no modifiers and no locale collation
+    fn is_plain_byte_order(&self) -> bool {
+        self.config.mode == crate::config::SortMode::Lexicographic
+            && self.config.keys.is_empty()
+            && !self.config.ignore_case
+            && !self.config.dictionary_order
+            && !self.config.ignore_leading_blanks
+            && !self.config.ignore_nonprinting
+            && self.config.ignore_chars.is_none()
+            && !self.config.collates()
+    }
+
+    /// Equality used by `-u` to decide whether two adjacent lines are
+    /// duplicates: they compare equal on everything they are sorted by, so
+    /// under `-n` `1` and `01` are duplicates
+    fn lines_equal_for_unique(&self, a: &Line, b: &Line) -> bool {
+        if self.is_plain_byte_order() {
+            unsafe { a.as_bytes() == b.as_bytes() }
         } else {
-            cmp
+            self.compare_lines_direct(a, b) == Ordering::Equal
+        }
+    }
+
+    /// Drop lines equal to their predecessor under `-u`, keeping the first of
+    /// each run; large inputs are split into partitions deduplicated in parallel
+    fn dedup_sorted_lines(&self, mut lines: Vec<Line>) -> Vec<Line> {
+        if !self.use_parallel(lines.len()) {
+            lines.dedup_by(|a, b| self.lines_equal_for_unique(a, b));
+            return lines;
         }
+        let partitions = rayon::current_num_threads() * 4;
+        let partition_len = (lines.len() + partitions - 1) / partitions;
+        self.dedup_partitioned(&lines, partition_len)
+    }
+
+    /// Parallel half of [`Self::dedup_sorted_lines`]. Every line is compared
+    /// with the one before it, including across partition edges, so a run
+    /// spanning several partitions collapses exactly as in the sequential pass
+    fn dedup_partitioned(&self, lines: &[Line], partition_len: usize) -> Vec<Line> {
+        use rayon::prelude::*;
+
+        let kept: Vec<Vec<Line>> = lines
+            .par_chunks(partition_len.max(1))
+            .enumerate()
+            .map(|(index, partition)| {
+                let start = index * partition_len.max(1);
+                partition
+                    .iter()
+                    .enumerate()
+                    .filter(|&(offset, line)| {
+                        let position = start + offset;
+                        position == 0 || !self.lines_equal_for_unique(line, &lines[position - 1])
+                    })
+                    .map(|(_, line)| *line)
+                    .collect()
+            })
+            .collect();
+        kept.concat()
     }
 
     pub fn sort(&self) -> io::Result<()> {
+        let result = self.worker_pool()?.install(|| self.run());
+        #[cfg(feature = "comparison-count")]
+        if self.config.debug {
+            eprintln!("Comparisons: {}", self.config.comparison_count());
+        }
+        result
+    }
+
+    /// Thread pool sized by `--parallel` whose threads are named
+    /// `sort-worker-N`, so they can be told apart in profiles. Every thread
+    /// the sort runs on comes from here.
+    fn worker_pool(&self) -> io::Result<rayon::ThreadPool> {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(self.config.effective_thread_count())
+            .thread_name(|index| format!("sort-worker-{index}"))
+            .spawn_handler(|worker| {
+                #[cfg(test)]
+                tests::THREADS_SPAWNED.with(|count| count.set(count.get() + 1));
+                let mut builder = thread::Builder::new();
+                if let Some(name) = worker.name() {
+                    builder = builder.name(name.to_string());
+                }
+                if let Some(stack_size) = worker.stack_size() {
+                    builder = builder.stack_size(stack_size);
+                }
+                builder.spawn(|| worker.run())?;
+                Ok(())
+            })
+            .build()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))
+    }
+
+    /// Run the sort; parallel work lands on the current rayon pool
+    fn run(&self) -> io::Result<()> {
         // Initialize locale configuration at startup
         let _locale_config = crate::locale::LocaleConfig::get();
 
@@ -163,6 +655,9 @@ impl CoreSort {
             // Sort method info
             eprintln!("Byte sort is used");
             eprintln!("sort_method=mergesort");
+            for step in self.config.comparator_chain() {
+                eprintln!("{step}");
+            }
         }
 
         let input_files = &self.args.files;
@@ -185,7 +680,22 @@ impl CoreSort {
             return self.check_sorted(input_files);
         }
 
-        if input_files.is_empty() || (input_files.len() == 1 && input_files[0] == "-") {
+        if let Some(last) = input_files.last().filter(|path| *path != "-") {
+            self.record_input_terminator(Path::new(last));
+        }
+
+        // Mapped inputs must not change underneath us; record them so a
+        // modification during the sort can at least be reported
+        let snapshots = if self.config.debug || self.config.safe {
+            Self::snapshot_inputs(input_files, self.args.output.as_deref())
+        } else {
+            Vec::new()
+        };
+
+        let result = if self.config.merge {
+            // Inputs are sorted already; merge them without sorting again
+            self.merge_inputs(input_files)
+        } else if input_files.is_empty() || (input_files.len() == 1 && input_files[0] == "-") {
             // Read from stdin
             self.sort_stdin()
         } else if input_files.len() == 1 {
@@ -194,11 +704,104 @@ impl CoreSort {
         } else {
             // Multiple files - use multi-threaded approach
             self.sort_multiple_files(input_files)
+        };
+
+        for warning in Self::changed_input_warnings(&snapshots) {
+            eprintln!("sort: warning: {warning}");
+        }
+
+        result
+    }
+
+    /// Record size and mtime of every regular input file; an input that is
+    /// also the output file is expected to change and is skipped
+    fn snapshot_inputs(input_files: &[String], output: Option<&str>) -> Vec<InputSnapshot> {
+        input_files
+            .iter()
+            .filter(|path| *path != "-" && Some(path.as_str()) != output)
+            .filter_map(|path| InputSnapshot::capture(Path::new(path)).ok())
+            .collect()
+    }
+
+    /// Warnings for inputs whose size or mtime changed since they were recorded
+    fn changed_input_warnings(snapshots: &[InputSnapshot]) -> Vec<String> {
+        snapshots
+            .iter()
+            .filter(|snapshot| snapshot.changed())
+            .map(|snapshot| {
+                format!(
+                    "{}: file changed during the sort; results may be inconsistent",
+                    snapshot.path.display()
+                )
+            })
+            .collect()
+    }
+
+    /// `--debug` warnings for numeric comparisons that look misapplied: a
+    /// numeric sort or key over sampled input that is mostly not numbers,
+    /// and numeric keys spanning several fields
+    fn numeric_warnings(&self, lines: &[Line]) -> Vec<String> {
+        use crate::config::SortMode;
+
+        let sample = &lines[..lines.len().min(DEBUG_SAMPLE_LINES)];
+        let mut warnings = Vec::new();
+
+        if self.config.keys.is_empty() {
+            let flag = match self.config.mode {
+                SortMode::Numeric => "-n",
+                SortMode::GeneralNumeric => "-g",
+                SortMode::HumanNumeric => "-h",
+                _ => return warnings,
+            };
+            let text = sample
+                .iter()
+                .filter(|line| crate::numeric::numeric_prefix_len(unsafe { line.as_bytes() }) == 0)
+                .count();
+            if text * 2 > sample.len() {
+                warnings.push(format!(
+                    "{flag} is used, but {text} of {} sampled lines do not start with a number",
+                    sample.len()
+                ));
+            }
+            return warnings;
+        }
+
+        for (index, key) in self.config.keys.iter().enumerate() {
+            let options = &key.options;
+            if !(options.numeric || options.general_numeric || options.human_numeric) {
+                continue;
+            }
+            let number = index + 1;
+
+            if key.end_field.map_or(true, |end| end > key.start_field) {
+                warnings.push(format!("key {number} is numeric and spans multiple fields"));
+            }
+
+            let text = sample
+                .iter()
+                .filter(|line| {
+                    !line
+                        .extract_key_with_config(key, self.config.field_separator, &self.config)
+                        .is_some_and(|field| crate::numeric::numeric_prefix_len(field) > 0)
+                })
+                .count();
+            if text * 2 > sample.len() {
+                warnings.push(format!(
+                    "key {number} is numeric, but {text} of {} sampled fields are not numbers",
+                    sample.len()
+                ));
+            }
         }
+
+        warnings
     }
 
     /// Check if files are sorted according to current settings
     fn check_sorted(&self, input_files: &[String]) -> io::Result<()> {
+        if self.config.check_count {
+            return self.report_disorder_counts(input_files);
+        }
+
         if input_files.is_empty() || (input_files.len() == 1 && input_files[0] == "-") {
             // Check stdin
             return self.check_stdin_sorted();
@@ -210,8 +813,8 @@ impl CoreSort {
                 Ok(()) => {}
                 Err(line_num) => {
                     // File is not sorted - return error with correct line number
-                    eprintln!("sort: {file}:{line_num}: disorder");
-                    std::process::exit(1);
+                    let error = crate::error::SortError::not_sorted(file, line_num);
+                    return Err(io::Error::new(io::ErrorKind::InvalidData, error));
                 }
             }
         }
@@ -219,30 +822,104 @@ impl CoreSort {
         Ok(())
     }
 
+    /// `--check-count`: report how many adjacent line pairs are out of order
+    /// in each input, failing with [`SortError::DisorderCounted`] if there
+    /// are any; stdin past the memory budget is counted from a temporary file
+    ///
+    /// [`SortError::DisorderCounted`]: crate::error::SortError::DisorderCounted
+    fn report_disorder_counts(&self, input_files: &[String]) -> io::Result<()> {
+        let stdin_only = [String::from("-")];
+        let inputs = if input_files.is_empty() {
+            &stdin_only[..]
+        } else {
+            input_files
+        };
+
+        let mut total = 0;
+        for file in inputs {
+            let count = if file == "-" {
+                let stdin = std::io::stdin();
+                self.count_reader_disorders(stdin.lock(), self.stdin_memory_budget())?
+            } else {
+                let mapped_file =
+                    MappedFile::with_terminator(Path::new(file), self.line_terminator())?;
+                self.count_disorders(mapped_file.lines())
+            };
+
+            if count > 0 {
+                eprintln!("sort: {file}: {count} out-of-order lines");
+                total += count;
+            }
+        }
+
+        if total > 0 {
+            let error = crate::error::SortError::DisorderCounted { count: total };
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+        Ok(())
+    }
+
+    /// [`Self::count_disorders`] for a stream, holding at most `budget` bytes
+    /// of it in memory
+    fn count_reader_disorders<R: Read>(&self, reader: R, budget: usize) -> io::Result<usize> {
+        let terminator = self.line_terminator();
+        Ok(match self.read_bounded(reader, budget)? {
+            StdinInput::InMemory(buffer) => {
+                self.count_disorders(&parse_lines_with_terminator(&buffer, terminator))
+            }
+            StdinInput::Spilled(temp_file) => self.count_disorders(
+                MappedFile::with_terminator(temp_file.path(), terminator)?.lines(),
+            ),
+        })
+    }
+
+    /// Number of adjacent pairs whose second line sorts before the first
+    fn count_disorders(&self, lines: &[Line]) -> usize {
+        lines
+            .windows(2)
+            .filter(|pair| !self.is_lines_in_order(&pair[0], &pair[1]))
+            .count()
+    }
+
     /// Check if stdin is sorted
     fn check_stdin_sorted(&self) -> io::Result<()> {
-        use std::io::BufRead;
         let stdin = std::io::stdin();
-        let reader = stdin.lock();
+        if let Err(line_num) = self.check_reader_sorted(stdin.lock())? {
+            let error = crate::error::SortError::not_sorted("-", line_num);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+        }
+        Ok(())
+    }
 
-        let mut prev_line: Option<String> = None;
+    /// Check if a stream is sorted, reading one line at a time, and return
+    /// the line number of the first disorder if there is one
+    fn check_reader_sorted<R: std::io::BufRead>(
+        &self,
+        mut reader: R,
+    ) -> io::Result<Result<(), usize>> {
+        let mut prev = Vec::new();
+        let mut line = Vec::new();
         let mut line_num = 0;
+        let terminator = self.line_terminator();
 
-        for line_result in reader.lines() {
+        loop {
+            line.clear();
+            if reader.read_until(terminator, &mut line)? == 0 {
+                return Ok(Ok(()));
+            }
             line_num += 1;
-            let line = line_result?;
-
-            if let Some(ref prev) = prev_line {
-                if !self.is_in_order(prev, &line) {
-                    eprintln!("sort: -:{line_num}: disorder");
-                    std::process::exit(1);
+            if line.last() == Some(&terminator) {
+                line.pop();
+                if terminator == b'\n' && line.ends_with(b"\r") {
+                    line.pop();
                 }
             }
 
-            prev_line = Some(line);
+            if line_num > 1 && !self.is_in_order(&prev, &line) {
+                return Ok(Err(line_num));
+            }
+            std::mem::swap(&mut prev, &mut line);
         }
-
-        Ok(())
     }
 
     /// Check if a file is sorted (old method for compatibility)
@@ -256,27 +933,83 @@ impl CoreSort {
 
     /// Check if a file is sorted and return line number of disorder if found
     fn check_file_sorted_with_line(&self, path: &Path) -> io::Result<Result<(), usize>> {
-        let mapped_file = MappedFile::new(path)?;
+        let mapped_file = MappedFile::with_terminator(path, self.line_terminator())?;
         let lines = mapped_file.lines();
 
-        for i in 1..lines.len() {
-            let prev = &lines[i - 1];
-            let curr = &lines[i];
-
-            if !self.is_lines_in_order(prev, curr) {
-                // Return 1-based line number (i+1 because i is the index of current line)
-                return Ok(Err(i + 1));
+        if self.config.debug {
+            if let Some(disagreement) =
+                self.check_agrees_with_sort(lines, |a, b| self.is_lines_in_order(a, b))?
+            {
+                eprintln!("sort: warning: {}: {disagreement}", path.display());
             }
         }
 
-        Ok(Ok(()))
+        // Return 1-based line number (i+1 because i is the index of current line)
+        Ok(self.first_disorder(lines).map_or(Ok(()), |i| Err(i + 1)))
+    }
+
+    /// Index of the first line that sorts before the line above it; large
+    /// inputs are split into partitions checked in parallel
+    fn first_disorder(&self, lines: &[Line]) -> Option<usize> {
+        if !self.use_parallel(lines.len()) {
+            return (1..lines.len()).find(|&i| !self.is_lines_in_order(&lines[i - 1], &lines[i]));
+        }
+        let partitions = rayon::current_num_threads() * 4;
+        let partition_len = (lines.len() + partitions - 1) / partitions;
+        self.first_disorder_partitioned(lines, partition_len)
+    }
+
+    /// Parallel half of [`Self::first_disorder`]. Each partition's first line
+    /// is checked against the last line of the partition before it, and the
+    /// earliest disorder of any partition wins
+    fn first_disorder_partitioned(&self, lines: &[Line], partition_len: usize) -> Option<usize> {
+        use rayon::prelude::*;
+
+        let partition_len = partition_len.max(1);
+        lines
+            .par_chunks(partition_len)
+            .enumerate()
+            .find_map_first(|(index, partition)| {
+                let start = index * partition_len;
+                (start.max(1)..start + partition.len())
+                    .find(|&i| !self.is_lines_in_order(&lines[i - 1], &lines[i]))
+            })
+    }
+
+    /// `--debug` self-test for `-c`: sort `lines` in full and confirm that
+    /// the sort leaves them as they are exactly when `in_order` accepts
+    /// every adjacent pair. Returns how the two disagree, if they do.
+    fn check_agrees_with_sort(
+        &self,
+        lines: &[Line],
+        in_order: impl Fn(&Line, &Line) -> bool,
+    ) -> io::Result<Option<String>> {
+        let disorder = lines
+            .windows(2)
+            .position(|pair| !in_order(&pair[0], &pair[1]));
+
+        // A quiet copy of this sort, so the cross-check adds no debug output
+        let sorter = CoreSort::new(self.args.clone(), self.config.clone().with_debug(false));
+        let mut sorted = Vec::new();
+        sorter.sort_lines_in_memory(lines, &mut sorted)?;
+        let mut input = Vec::with_capacity(sorted.len());
+        sorter.write_output_direct(lines, &mut input)?;
+
+        Ok(match (disorder, sorted == input) {
+            (None, false) => Some("-c finds the input sorted, but sorting changes it".to_string()),
+            (Some(index), true) => Some(format!(
+                "-c finds disorder at line {}, but sorting leaves the input as is",
+                index + 2
+            )),
+            _ => None,
+        })
     }
 
-    /// Check if two strings are in order according to current sort settings
-    fn is_in_order(&self, a: &str, b: &str) -> bool {
-        let line_a = Line::new(a.as_bytes());
-        let line_b = Line::new(b.as_bytes());
-        self.is_lines_in_order(&line_a, &line_b)
+    /// Check if two byte strings are in order according to current sort
+    /// settings. The `Line`s viewing them live only for this call, so the
+    /// buffers may be reused as soon as it returns.
+    fn is_in_order(&self, a: &[u8], b: &[u8]) -> bool {
+        self.is_lines_in_order(&Line::new(a), &Line::new(b))
     }
 
     /// Check if two Lines are in order
@@ -293,85 +1026,260 @@ impl CoreSort {
     /// Sort data from stdin using streaming approach
     fn sort_stdin(&self) -> io::Result<()> {
         let stdin = std::io::stdin();
-        let file = stdin.lock();
+        self.sort_reader(stdin.lock())
+    }
 
-        // For stdin, we need to read into memory first
-        let mut buffer = Vec::new();
-        // Use u64 and convert to avoid overflow on 32-bit systems
-        const MAX_STDIN_SIZE: u64 = 2 * 1024 * 1024 * 1024; // 2GB limit for stdin
-        file.take(MAX_STDIN_SIZE).read_to_end(&mut buffer)?;
+    /// Sort a stream, keeping at most the memory budget in memory and handing
+    /// anything larger to the external sorter
+    fn sort_reader<R: Read>(&self, reader: R) -> io::Result<()> {
+        match self.read_bounded(reader, self.stdin_memory_budget())? {
+            StdinInput::InMemory(buffer) => {
+                self.record_buffer_terminator(&buffer);
+                let mut output = self.open_output()?;
+                self.sort_buffer_to(&buffer, &mut output)?;
+                output.commit()
+            }
+            StdinInput::Spilled(temp_file) => {
+                self.record_input_terminator(temp_file.path());
+                self.sort_large_file_external(temp_file.path())
+            }
+        }
+    }
 
-        // Create temporary file and sort it
-        let temp_file = tempfile::NamedTempFile::new()?;
-        std::fs::write(temp_file.path(), &buffer)?;
+    /// Sort a complete in-memory buffer, writing the sorted lines to `output`;
+    /// the final terminator follows the trailing newline policy
+    pub fn sort_buffer(&self, input: &[u8], output: &mut dyn Write) -> io::Result<()> {
+        self.record_buffer_terminator(input);
+        let mut output = self.output_writer(output);
+        self.sort_buffer_to(input, &mut output)?;
+        output.finish()?;
+        Ok(())
+    }
 
-        self.sort_single_file(temp_file.path())
+    /// Parse `input` into lines borrowing from it and sort them into `output`
+    fn sort_buffer_to(&self, input: &[u8], output: &mut dyn Write) -> io::Result<()> {
+        let lines = parse_lines_with_terminator(input, self.line_terminator());
+        self.sort_lines_in_memory(&lines, output)
     }
 
-    /// Sort a single file using optimal strategy based on size
-    fn sort_single_file(&self, path: &Path) -> io::Result<()> {
-        // Validate file exists and is readable
-        if !path.exists() {
-            return Err(io::Error::new(
-                io::ErrorKind::NotFound,
-                format!("File not found: {}", path.display()),
-            ));
-        }
+    /// Remember whether an in-memory input ends with a line terminator
+    fn record_buffer_terminator(&self, input: &[u8]) {
+        let terminator = self.line_terminator();
+        self.input_terminated.store(
+            input.last().map_or(true, |&b| b == terminator),
+            AtomicOrdering::Relaxed,
+        );
+    }
 
-        // Check file size to determine strategy
-        let metadata = std::fs::metadata(path)?;
-        const MAX_FILE_SIZE: u64 = 100u64 * 1024 * 1024 * 1024; // 100GB limit
-        if metadata.len() > MAX_FILE_SIZE {
-            return Err(io::Error::new(
-                io::ErrorKind::InvalidInput,
-                format!(
-                    "File too large: {} bytes (max: {} bytes)",
-                    metadata.len(),
-                    MAX_FILE_SIZE
-                ),
-            ));
+    /// Byte that ends each input and output line: NUL under `-z`, else newline
+    fn line_terminator(&self) -> u8 {
+        if self.config.zero_terminated {
+            b'\0'
+        } else {
+            b'\n'
         }
+    }
 
-        let file_size = metadata.len() as usize;
-        const LARGE_FILE_THRESHOLD: usize = 100 * 1024 * 1024; // 100MB
+    /// Memory budget for stdin: the configured buffer size, clamped to the
+    /// external sorter's minimum, or the same threshold used to send regular
+    /// files to the external sorter
+    fn stdin_memory_budget(&self) -> usize {
+        self.config
+            .external_buffer_size()
+            .unwrap_or(STDIN_MEMORY_BUDGET)
+    }
 
-        if file_size > LARGE_FILE_THRESHOLD {
-            // Use external sorting for very large files
-            return self.sort_large_file_external(path);
+    /// Read up to `budget` bytes; if the stream is longer, write what was read
+    /// plus the remainder to a temporary file without buffering it
+    fn read_bounded<R: Read>(&self, mut reader: R, budget: usize) -> io::Result<StdinInput> {
+        let mut buffer = Vec::new();
+        reader
+            .by_ref()
+            .take(budget as u64 + 1)
+            .read_to_end(&mut buffer)?;
+
+        if buffer.len() <= budget {
+            return Ok(StdinInput::InMemory(buffer));
         }
 
-        // Use in-memory sorting for smaller files
-        let mapped_file = MappedFile::new(path)?;
-        let lines = mapped_file.lines();
+        let mut temp_file = self.create_temp_file()?;
+        {
+            let mut writer = BufWriter::new(temp_file.as_file_mut());
+            writer.write_all(&buffer)?;
+            drop(buffer);
+            io::copy(&mut reader, &mut writer)?;
+            writer.flush()?;
+        }
+
+        Ok(StdinInput::Spilled(temp_file))
+    }
+
+    /// Remember whether `path` ends with a line terminator; unreadable files
+    /// are reported later by the code that actually reads them
+    fn record_input_terminator(&self, path: &Path) {
+        if let Ok(terminated) = Self::file_ends_with_terminator(path, self.line_terminator()) {
+            self.input_terminated
+                .store(terminated, AtomicOrdering::Relaxed);
+        }
+    }
+
+    /// Check the last byte of a file; empty files count as terminated
+    fn file_ends_with_terminator(path: &Path, terminator: u8) -> io::Result<bool> {
+        use std::io::{Seek, SeekFrom};
+
+        let mut file = File::open(path)?;
+        let len = file.metadata()?.len();
+        if len == 0 {
+            return Ok(true);
+        }
+
+        let mut last = [0u8; 1];
+        file.seek(SeekFrom::Start(len - 1))?;
+        file.read_exact(&mut last)?;
+        Ok(last[0] == terminator)
+    }
+
+    /// Whether the final output line gets its terminator under `--trailing-newline`
+    fn keep_final_terminator(&self) -> bool {
+        match self.config.trailing_newline {
+            TrailingNewline::Always => true,
+            TrailingNewline::Never => false,
+            TrailingNewline::Preserve => self.input_terminated.load(AtomicOrdering::Relaxed),
+        }
+    }
+
+    /// Open the output file (or stdout) behind the trailing newline policy
+    fn open_output(&self) -> io::Result<FinalTerminatorWriter<OutputSink>> {
+        let output = self.open_sink()?;
+        Ok(self.output_writer(output))
+    }
+
+    /// Open the output file (or stdout), gzipped if the output compression
+    /// says so
+    fn open_sink(&self) -> io::Result<OutputSink> {
+        let output = self.args.output.as_deref();
+        let sink = OutputSink::open(output)?;
+        if self.config.output_compression.applies_to(output) {
+            return sink.gzip();
+        }
+        Ok(sink)
+    }
+
+    /// Put `output` behind the trailing newline policy and the annotations
+    /// of `--show-key` and `--debug`
+    fn output_writer<W: Write>(&self, output: W) -> FinalTerminatorWriter<W> {
+        FinalTerminatorWriter::new(output, self.line_terminator(), self.keep_final_terminator())
+            .with_annotations(&self.config)
+    }
+
+    /// Drop the final terminator of a file written outside `open_output`
+    /// when the trailing newline policy says so
+    fn apply_trailing_newline_policy(&self, path: &Path) -> io::Result<()> {
+        if self.keep_final_terminator()
+            || !Self::file_ends_with_terminator(path, self.line_terminator())?
+        {
+            return Ok(());
+        }
+        let file = std::fs::OpenOptions::new().write(true).open(path)?;
+        let len = file.metadata()?.len();
+        if len > 0 {
+            file.set_len(len - 1)?;
+        }
+        Ok(())
+    }
+
+    /// Create a temporary file in the configured temp directory, if any
+    fn create_temp_file(&self) -> io::Result<crate::temp::Temporary<tempfile::NamedTempFile>> {
+        crate::temp::create_temp_file(self.config.temp_dir.as_deref())
+    }
+
+    /// Sort a single file using optimal strategy based on size
+    fn sort_single_file(&self, path: &Path) -> io::Result<()> {
+        // Validate file exists and is readable
+        if !path.exists() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("File not found: {}", path.display()),
+            ));
+        }
+
+        // Check file size to determine strategy
+        let metadata = std::fs::metadata(path)?;
+        const MAX_FILE_SIZE: u64 = 100u64 * 1024 * 1024 * 1024; // 100GB limit
+        if metadata.len() > MAX_FILE_SIZE {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "File too large: {} bytes (max: {} bytes)",
+                    metadata.len(),
+                    MAX_FILE_SIZE
+                ),
+            ));
+        }
+
+        if self.needs_external_sort(metadata.len() as usize) {
+            // Use external sorting for very large files
+            return self.sort_large_file_external(path);
+        }
+
+        // Use in-memory sorting for smaller files
+        let mapped_file = MappedFile::with_terminator(path, self.line_terminator())?;
+        let mut output = self.open_output()?;
+        self.sort_lines_in_memory(mapped_file.lines(), &mut output)?;
+        output.commit()
+    }
+
+    /// Whether a file of `file_size` bytes goes through the external sort:
+    /// past the `-S` budget, or past 100MB without one
+    fn needs_external_sort(&self, file_size: usize) -> bool {
+        const LARGE_FILE_THRESHOLD: usize = 100 * 1024 * 1024; // 100MB
+        file_size
+            > self
+                .config
+                .external_buffer_size()
+                .unwrap_or(LARGE_FILE_THRESHOLD)
+    }
+
+    /// Sort lines that are already in memory and write them to `output`
+    fn sort_lines_in_memory(&self, lines: &[Line], output: &mut dyn Write) -> io::Result<()> {
+        check_input_lines(lines.iter().copied(), 1, &self.config)?;
+        if self.config.debug {
+            for warning in self.numeric_warnings(lines) {
+                eprintln!("sort: warning: {warning}");
+            }
+        }
+
+        // Only the general comparator cuts long lines short; the radix and
+        // hashing fast paths would see them whole
+        if let Some(limit) = self.config.comparison_limit() {
+            if !self.args.random_sort && lines.iter().any(|line| line.len() > limit) {
+                return self.sort_lines_truncated(lines, limit, output);
+            }
+        }
 
         // Optimize for unique sort without stable - no SortableLine wrapper needed
         if self.args.unique && !self.args.stable {
+            // Heavily duplicated input: dedup first, then sort only the distinct lines
+            if let Some(mut distinct) = self.distinct_lines_if_few(lines) {
+                self.sort_lines_direct(&mut distinct);
+                return self.write_output_direct(&distinct, output);
+            }
+
             let mut lines_vec: Vec<Line> = lines.to_vec();
             self.sort_lines_direct(&mut lines_vec);
 
-            // Dedup in-place after sorting
-            lines_vec.dedup_by(|a, b| {
-                if self.config.keys.is_empty() {
-                    unsafe { a.as_bytes() == b.as_bytes() }
-                } else {
-                    a.compare_with_keys(
-                        b,
-                        &self.config.keys,
-                        self.config.field_separator,
-                        &self.config,
-                    ) == Ordering::Equal
-                }
-            });
+            // Dedup after sorting
+            let lines_vec = self.dedup_sorted_lines(lines_vec);
 
             // Write deduplicated output
-            return self.write_output_direct(&lines_vec);
+            return self.write_output_direct(&lines_vec, output);
         }
 
         // For non-stable, non-unique sorts, also avoid wrapper
         if !self.args.stable && !self.args.unique {
             let mut lines_vec: Vec<Line> = lines.to_vec();
             self.sort_lines_direct(&mut lines_vec);
-            return self.write_output_direct(&lines_vec);
+            return self.write_output_direct(&lines_vec, output);
         }
 
         // For stable sort, use direct Line sorting with separate index array
@@ -381,23 +1289,11 @@ impl CoreSort {
 
             // Handle unique for stable sort
             if self.args.unique {
-                let mut unique_result = result;
-                unique_result.dedup_by(|a, b| {
-                    if self.config.keys.is_empty() {
-                        unsafe { a.as_bytes() == b.as_bytes() }
-                    } else {
-                        a.compare_with_keys(
-                            b,
-                            &self.config.keys,
-                            self.config.field_separator,
-                            &self.config,
-                        ) == Ordering::Equal
-                    }
-                });
-                return self.write_output_direct(&unique_result);
+                let unique_result = self.dedup_sorted_lines(result);
+                return self.write_output_direct(&unique_result, output);
             }
 
-            return self.write_output_direct(&result);
+            return self.write_output_direct(&result, output);
         }
 
         // For non-stable but unique case, use SortableLine wrapper
@@ -411,11 +1307,12 @@ impl CoreSort {
             .collect();
 
         // Create comparison cache for complex sorts
-        let cache = if self.args.numeric_sort || self.config.ignore_case || self.args.random_sort {
-            Some(Arc::new(ComparisonCache::new(lines, &self.config)))
-        } else {
-            None
-        };
+        let cache =
+            if self.numeric_fast_paths() || self.case_fold_fast_path() || self.args.random_sort {
+                Some(Arc::new(ComparisonCache::new(lines, &self.config)))
+            } else {
+                None
+            };
 
         // Sort the lines with cache
         self.sort_lines_with_cache(&mut sortable_lines, cache.as_ref());
@@ -440,7 +1337,32 @@ impl CoreSort {
         }
 
         // Write output
-        self.write_output(&sortable_lines)
+        self.write_output(&sortable_lines, output)
+    }
+
+    /// Sort comparing only the first `limit` bytes of each line
+    /// (`--long-lines=truncate`); the output keeps whole lines
+    fn sort_lines_truncated(
+        &self,
+        lines: &[Line],
+        limit: usize,
+        output: &mut dyn Write,
+    ) -> io::Result<()> {
+        use rayon::prelude::*;
+
+        // Stable, so that `-s` keeps lines that agree up to the limit in input order
+        let mut sorted = lines.to_vec();
+        if self.use_parallel(sorted.len()) {
+            sorted.par_sort_by(|a, b| self.compare_lines_direct(a, b));
+        } else {
+            sorted.sort_by(|a, b| self.compare_lines_direct(a, b));
+        }
+        if self.args.unique {
+            sorted.dedup_by(|a, b| {
+                self.lines_equal_for_unique(&a.truncated(limit), &b.truncated(limit))
+            });
+        }
+        self.write_output_direct(&sorted, output)
     }
 
     /// Sort very large files using external sorting
@@ -448,14 +1370,69 @@ impl CoreSort {
         // Get file size for memory calculation
         let file_size = std::fs::metadata(path)?.len() as usize;
 
-        // Calculate memory limit optimized for large files
+        // Create external sorter
+        let external_sorter = ExternalSort::new(
+            self.external_memory_limit_mb(file_size),
+            self.config.effective_thread_count() > 1, // Honor --parallel
+            self.args.numeric_sort,
+            self.config.temp_dir.as_deref(),
+        )?
+        .with_config(self.config.clone())
+        .with_merge_threads(self.config.effective_thread_count())
+        .with_compress_program(
+            self.config
+                .compress_program
+                .clone()
+                .filter(|_| self.config.compress_temp),
+        );
+
+        // A staged -o file is produced in place; other outputs, and output
+        // that is annotated, reordered by --empty-last or compressed, are
+        // sorted to a temporary file and copied
+        let sink = self.open_sink()?;
+        if let Some(staging_path) = sink
+            .staging_path()
+            .filter(|_| !self.config.annotates_output() && !self.config.empty_last)
+        {
+            external_sorter.sort_file(
+                path,
+                staging_path,
+                self.args.numeric_sort,
+                self.args.unique,
+            )?;
+            self.apply_trailing_newline_policy(staging_path)?;
+            return sink.commit();
+        }
+
+        let temp_file = self.create_temp_file()?;
+        external_sorter.sort_file(
+            path,
+            temp_file.path(),
+            self.args.numeric_sort,
+            self.args.unique,
+        )?;
+
+        let mut input = std::fs::File::open(temp_file.path())?;
+        let mut output = self.output_writer(sink);
+        std::io::copy(&mut input, &mut output)?;
+        output.commit()
+    }
+
+    /// Chunk memory in MB for the external sort of a `file_size`-byte file:
+    /// the `-S` budget if one was given, otherwise a share of the memory
+    /// available that shrinks as files grow
+    fn external_memory_limit_mb(&self, file_size: usize) -> usize {
+        if let Some(bytes) = self.config.external_buffer_size() {
+            return (bytes / (1024 * 1024)).max(1);
+        }
+
         let available_memory = Self::get_available_memory_mb();
 
         // For systems without swap (or low memory), be more conservative
         // Leave at least 512MB for system operations
         let safe_memory = available_memory.saturating_sub(512);
 
-        let memory_limit = if file_size > 1024 * 1024 * 1024 {
+        if file_size > 1024 * 1024 * 1024 {
             // Files > 1GB: use smaller chunks for better memory efficiency (like rustcoreutils)
             (safe_memory / 10).max(100) // Reduced from /2 to /10
         } else if file_size > 200 * 1024 * 1024 {
@@ -464,40 +1441,7 @@ impl CoreSort {
         } else {
             // Smaller files: can use more memory
             (safe_memory / 4).max(32) // Reduced from *3/4 to /4
-        };
-
-        // Create external sorter
-        let external_sorter = ExternalSort::new(
-            memory_limit,
-            num_cpus::get() > 1, // Use parallel processing if multiple cores available
-            self.args.numeric_sort,
-            self.config.temp_dir.as_deref(),
-        )?;
-
-        // Determine output path
-        let output_path = if let Some(ref output_file) = self.args.output {
-            PathBuf::from(output_file)
-        } else {
-            // Create temporary file for stdout output
-            let temp_file = tempfile::NamedTempFile::new()?;
-            let temp_path = temp_file.path().to_path_buf();
-
-            // Sort to temporary file, then copy to stdout
-            external_sorter.sort_file(
-                path,
-                &temp_path,
-                self.args.numeric_sort,
-                self.args.unique,
-            )?;
-
-            // Copy to stdout
-            let mut input = std::fs::File::open(&temp_path)?;
-            let mut output = std::io::stdout();
-            std::io::copy(&mut input, &mut output)?;
-            return Ok(());
-        };
-
-        external_sorter.sort_file(path, &output_path, self.args.numeric_sort, self.args.unique)
+        }
     }
 
     /// Get available system memory in MB
@@ -533,39 +1477,33 @@ impl CoreSort {
         }
     }
 
-    /// Sort multiple files using multi-threaded approach
+    /// Sort multiple files using multi-threaded approach; a `-` among them
+    /// is stdin, while a file named `-` may be given as `./-`
     fn sort_multiple_files(&self, files: &[String]) -> io::Result<()> {
-        let temp_dir = if let Some(ref path) = self.config.temp_dir {
-            tempfile::tempdir_in(path)?
-        } else if let Ok(tmpdir) = std::env::var("TMPDIR") {
-            tempfile::tempdir_in(tmpdir)?
-        } else {
-            tempfile::tempdir()?
-        };
+        let temp_dir = crate::temp::create_temp_dir(self.config.temp_dir.as_deref())?;
         let mut sorted_chunks = Vec::new();
 
-        // Process each file in parallel
-        let (sender, receiver): (Sender<io::Result<PathBuf>>, Receiver<io::Result<PathBuf>>) =
-            bounded(files.len());
-
-        // Spawn worker threads
-        for file_path in files {
-            let file_path = file_path.clone();
-            let args = self.args.clone();
-            let config = self.config.clone();
-            let temp_dir_path = temp_dir.path().to_path_buf();
-            let sender = sender.clone();
-
-            thread::spawn(move || {
-                let result = Self::sort_file_to_temp(&file_path, &args, &config, &temp_dir_path);
-                let _ = sender.send(result);
-            });
+        // Workers map their input, so stdin is copied to a file first
+        let mut stdin_copies = Vec::new();
+        let mut paths = Vec::with_capacity(files.len());
+        for file in files {
+            if file == "-" {
+                let copy = self.copy_stdin()?;
+                paths.push(copy.path().to_path_buf());
+                stdin_copies.push(copy);
+            } else {
+                paths.push(PathBuf::from(file));
+            }
         }
 
-        drop(sender); // Close sender to signal completion
-
-        // Collect sorted chunk files
-        while let Ok(result) = receiver.recv() {
+        // Process the files in parallel on the worker pool, so `--parallel`
+        // bounds how many are sorted at once
+        use rayon::prelude::*;
+        let results: Vec<io::Result<PathBuf>> = paths
+            .par_iter()
+            .map(|path| Self::sort_file_to_temp(path, &self.args, &self.config, temp_dir.path()))
+            .collect();
+        for result in results {
             sorted_chunks.push(result?);
         }
 
@@ -575,14 +1513,18 @@ impl CoreSort {
 
     /// Sort a single file and write to temporary file
     fn sort_file_to_temp(
-        file_path: &str,
+        path: &Path,
         args: &SortArgs,
         config: &SortConfig,
         temp_dir: &Path,
     ) -> io::Result<PathBuf> {
-        let path = Path::new(file_path);
-        let mapped_file = MappedFile::new(path)?;
+        // Create sorter with args and config
+        let sorter = CoreSort::new(args.clone(), config.clone());
+        let terminator = [sorter.line_terminator()];
+
+        let mapped_file = MappedFile::with_terminator(path, terminator[0])?;
         let lines = mapped_file.lines();
+        check_input_lines(lines.iter().copied(), 1, config)?;
 
         let mut sortable_lines: Vec<SortableLine> = lines
             .iter()
@@ -593,20 +1535,21 @@ impl CoreSort {
             })
             .collect();
 
-        // Create sorter with args and config
-        let sorter = CoreSort::new(args.clone(), config.clone());
         sorter.sort_lines(&mut sortable_lines);
 
         // Write to temporary file
-        let temp_file = tempfile::NamedTempFile::new_in(temp_dir)?;
-        let temp_path = temp_file.path().to_path_buf();
+        // Keep the file past this function; the enclosing temporary
+        // directory removes it once the merge is done
+        let (file, temp_path) = crate::temp::create_temp_file_in(temp_dir)?
+            .keep()
+            .map_err(|e| e.error)?;
 
         {
-            let mut writer = BufWriter::new(temp_file.reopen()?);
+            let mut writer = BufWriter::new(file);
             for sortable_line in &sortable_lines {
                 unsafe {
                     writer.write_all(sortable_line.line.as_bytes())?;
-                    writer.write_all(b"\n")?;
+                    writer.write_all(&terminator)?;
                 }
             }
             writer.flush()?;
@@ -631,117 +1574,220 @@ impl CoreSort {
             .iter()
             .map(|path| {
                 let file = File::open(path)?;
-                Ok(ZeroCopyReader::new(file))
+                Ok(ZeroCopyReader::with_terminator(
+                    file,
+                    self.line_terminator(),
+                ))
             })
             .collect::<io::Result<Vec<_>>>()?;
 
-        let output: Box<dyn Write> = if let Some(output_file) = &self.args.output {
-            Box::new(BufWriter::new(File::create(output_file)?))
+        let mut output = self.open_output()?;
+        self.merge_readers(&mut readers, None, &mut output)?;
+        output.commit()
+    }
+
+    /// `-m`: merge the inputs (stdin without any) into the output, each
+    /// assumed to be sorted already, without sorting them again
+    fn merge_inputs(&self, input_files: &[String]) -> io::Result<()> {
+        let stdin_only = [String::from("-")];
+        let paths = if input_files.is_empty() {
+            &stdin_only[..]
         } else {
-            Box::new(BufWriter::new(std::io::stdout()))
+            input_files
         };
+        let (mut readers, _stdin_copies) = self.open_merge_inputs(paths)?;
+
+        let verify = self.config.verify.then_some(paths);
+        let mut output = self.open_output()?;
+        self.merge_readers(&mut readers, verify, &mut output)?;
+        output.commit()
+    }
+
+    /// Merge already-sorted input files straight through the k-way merge,
+    /// without re-sorting them
+    pub fn merge_files(&self, paths: &[String], output: &mut dyn Write) -> io::Result<()> {
+        let (mut readers, _stdin_copies) = self.open_merge_inputs(paths)?;
+
+        if let Some(last) = paths.last().filter(|path| *path != "-") {
+            self.record_input_terminator(Path::new(last));
+        }
 
-        self.merge_readers(&mut readers, output)
+        let verify = self.config.verify.then_some(paths);
+        let mut output = self.output_writer(output);
+        self.merge_readers(&mut readers, verify, &mut output)?;
+        output.finish()?;
+        Ok(())
+    }
+
+    /// A reader for each merge input. Stdin (`-`) is copied to a temporary
+    /// file, returned alongside to be kept until the merge is done; each
+    /// further `-` gets whatever stdin has left, which is nothing.
+    fn open_merge_inputs(
+        &self,
+        paths: &[String],
+    ) -> io::Result<(
+        Vec<ZeroCopyReader>,
+        Vec<crate::temp::Temporary<tempfile::NamedTempFile>>,
+    )> {
+        let mut readers = Vec::with_capacity(paths.len());
+        let mut stdin_copies = Vec::new();
+        for path in paths {
+            let file = if path == "-" {
+                let copy = self.copy_stdin()?;
+                let file = copy.reopen()?;
+                stdin_copies.push(copy);
+                file
+            } else {
+                File::open(path)?
+            };
+            readers.push(ZeroCopyReader::with_terminator(
+                file,
+                self.line_terminator(),
+            ));
+        }
+        Ok((readers, stdin_copies))
+    }
+
+    /// Copy what is left of stdin to a temporary file, for inputs that must
+    /// be read from a file; a second `-` gets an empty copy
+    fn copy_stdin(&self) -> io::Result<crate::temp::Temporary<tempfile::NamedTempFile>> {
+        let mut copy = self.create_temp_file()?;
+        std::io::copy(&mut std::io::stdin().lock(), copy.as_file_mut())?;
+        Ok(copy)
     }
 
-    /// Merge multiple readers using k-way merge
+    /// Merge multiple readers using k-way merge. With `verify`, the names of
+    /// the readers' files, each line is checked against the one before it
+    /// from the same reader, and the merge stops at the first that sorts
+    /// earlier.
     fn merge_readers(
         &self,
         readers: &mut [ZeroCopyReader],
-        mut output: Box<dyn Write>,
+        verify: Option<&[String]>,
+        output: &mut dyn Write,
     ) -> io::Result<()> {
         use std::cmp::Reverse;
         use std::collections::BinaryHeap;
 
-        #[derive(Debug)]
-        struct MergeItem {
+        struct MergeItem<'a> {
             line: Line,
             reader_index: usize,
             line_index: usize,
+            sorter: &'a CoreSort,
         }
 
-        impl PartialEq for MergeItem {
+        impl PartialEq for MergeItem<'_> {
             fn eq(&self, other: &Self) -> bool {
                 self.cmp(other) == Ordering::Equal
             }
         }
 
-        impl Eq for MergeItem {}
+        impl Eq for MergeItem<'_> {}
 
-        impl PartialOrd for MergeItem {
+        impl PartialOrd for MergeItem<'_> {
             fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
                 Some(self.cmp(other))
             }
         }
 
-        impl Ord for MergeItem {
+        impl Ord for MergeItem<'_> {
             fn cmp(&self, other: &Self) -> Ordering {
-                // Note: We can't access self.args here, so we need to use the sorter's args
-                // This is a simplified comparison - in practice, we'd pass the args to the comparison
-                unsafe {
-                    let a = self.line.as_bytes();
-                    let b = other.line.as_bytes();
-                    a.cmp(b)
+                // Same comparator as the in-memory sort: mode, keys and reverse
+                let cmp = self.sorter.compare_lines_direct(&self.line, &other.line);
+                if cmp != Ordering::Equal || !self.sorter.args.stable {
+                    return cmp;
                 }
+                // Under -s equal lines come out in file order; each reader
+                // has one line in the heap at a time, so its own line order
+                // is kept as well
+                self.reader_index.cmp(&other.reader_index)
             }
         }
 
         // Min-heap for k-way merge
         let mut heap: BinaryHeap<Reverse<MergeItem>> = BinaryHeap::new();
-        let mut reader_chunks: Vec<Option<Vec<Line>>> = vec![None; readers.len()];
-        let _reader_positions: Vec<usize> = vec![0; readers.len()];
+        // Each reader's current chunk; the heap's lines point into these
+        let mut reader_chunks: Vec<Option<LineChunk>> = readers.iter().map(|_| None).collect();
 
         // Initialize heap with first line from each reader
         for (reader_idx, reader) in readers.iter_mut().enumerate() {
-            match reader.read_chunk() {
-                Ok(lines) if !lines.is_empty() => {
-                    reader_chunks[reader_idx] = Some(lines.to_vec());
-                    heap.push(Reverse(MergeItem {
-                        line: lines[0],
-                        reader_index: reader_idx,
-                        line_index: 0,
-                    }));
-                }
-                _ => {} // Reader is empty or error
+            let chunk = reader.read_chunk()?;
+            if let Some(&first) = chunk.lines().first() {
+                reader_chunks[reader_idx] = Some(chunk);
+                heap.push(Reverse(MergeItem {
+                    line: first,
+                    reader_index: reader_idx,
+                    line_index: 0,
+                    sorter: self,
+                }));
             }
         }
 
+        // Last written line, owned because reader buffers are reused
+        let mut last_written: Option<Vec<u8>> = None;
+        // 1-based number of each reader's line in the heap, for --verify
+        let mut line_numbers = vec![1; readers.len()];
+        let check_order = |reader_idx: usize, line_number: usize, previous: &Line, next: &Line| {
+            let Some(names) = verify else {
+                return Ok(());
+            };
+            if self.compare_lines_direct(next, previous) == Ordering::Less {
+                let error = crate::error::SortError::not_sorted(&names[reader_idx], line_number);
+                return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+            }
+            Ok(())
+        };
+
         // Merge process
         while let Some(Reverse(item)) = heap.pop() {
-            // Write the line
-            unsafe {
-                output.write_all(item.line.as_bytes())?;
-                output.write_all(b"\n")?;
+            let bytes = unsafe { item.line.as_bytes() };
+            let duplicate = self.args.unique
+                && last_written
+                    .as_deref()
+                    .is_some_and(|prev| self.lines_equal_for_unique(&Line::new(prev), &item.line));
+
+            if !duplicate {
+                output.write_all(bytes)?;
+                output.write_all(&[self.line_terminator()])?;
+                if self.args.unique {
+                    last_written = Some(bytes.to_vec());
+                }
             }
 
             // Get next line from the same reader
             let reader_idx = item.reader_index;
             let next_line_idx = item.line_index + 1;
+            line_numbers[reader_idx] += 1;
 
             // Check if we need to read next chunk
             if let Some(ref chunk) = reader_chunks[reader_idx] {
-                if next_line_idx < chunk.len() {
+                if let Some(&line) = chunk.lines().get(next_line_idx) {
+                    check_order(reader_idx, line_numbers[reader_idx], &item.line, &line)?;
                     // Use next line from current chunk
                     heap.push(Reverse(MergeItem {
-                        line: chunk[next_line_idx],
+                        line,
                         reader_index: reader_idx,
                         line_index: next_line_idx,
+                        sorter: self,
                     }));
                 } else {
-                    // Read next chunk
-                    match readers[reader_idx].read_chunk() {
-                        Ok(lines) if !lines.is_empty() => {
-                            reader_chunks[reader_idx] = Some(lines.to_vec());
-                            heap.push(Reverse(MergeItem {
-                                line: lines[0],
-                                reader_index: reader_idx,
-                                line_index: 0,
-                            }));
-                        }
-                        _ => {
-                            // Reader exhausted
-                            reader_chunks[reader_idx] = None;
-                        }
+                    // Read next chunk; the heap holds no other line of this
+                    // reader, so the chunk it replaces can go
+                    let chunk = readers[reader_idx].read_chunk()?;
+                    if let Some(&first) = chunk.lines().first() {
+                        // Checked before the old chunk, which holds the
+                        // previous line, is dropped
+                        check_order(reader_idx, line_numbers[reader_idx], &item.line, &first)?;
+                        reader_chunks[reader_idx] = Some(chunk);
+                        heap.push(Reverse(MergeItem {
+                            line: first,
+                            reader_index: reader_idx,
+                            line_index: 0,
+                            sorter: self,
+                        }));
+                    } else {
+                        // Reader exhausted
+                        reader_chunks[reader_idx] = None;
                     }
                 }
             }
@@ -751,18 +1797,86 @@ impl CoreSort {
         Ok(())
     }
 
+    /// Merge already-sorted in-memory line lists into one sorted list with
+    /// the configured comparator; under `-u` only the first of each run of
+    /// equal lines is kept
+    pub fn merge_vecs(&self, inputs: Vec<Vec<Vec<u8>>>) -> Vec<Vec<u8>> {
+        use std::cmp::Reverse;
+        use std::collections::BinaryHeap;
+
+        struct MergeItem<'a> {
+            line: Vec<u8>,
+            input_index: usize,
+            sorter: &'a CoreSort,
+        }
+
+        impl PartialEq for MergeItem<'_> {
+            fn eq(&self, other: &Self) -> bool {
+                self.cmp(other) == Ordering::Equal
+            }
+        }
+
+        impl Eq for MergeItem<'_> {}
+
+        impl PartialOrd for MergeItem<'_> {
+            fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+                Some(self.cmp(other))
+            }
+        }
+
+        impl Ord for MergeItem<'_> {
+            fn cmp(&self, other: &Self) -> Ordering {
+                let cmp = self
+                    .sorter
+                    .compare_lines_direct(&Line::new(&self.line), &Line::new(&other.line));
+                if cmp != Ordering::Equal || !self.sorter.args.stable {
+                    return cmp;
+                }
+                // Under -s equal lines come out in input order
+                self.input_index.cmp(&other.input_index)
+            }
+        }
+
+        let total = inputs.iter().map(Vec::len).sum();
+        let mut inputs: Vec<_> = inputs.into_iter().map(Vec::into_iter).collect();
+        let mut heap = BinaryHeap::with_capacity(inputs.len());
+        for (input_index, input) in inputs.iter_mut().enumerate() {
+            if let Some(line) = input.next() {
+                heap.push(Reverse(MergeItem {
+                    line,
+                    input_index,
+                    sorter: self,
+                }));
+            }
+        }
+
+        let mut merged: Vec<Vec<u8>> = Vec::with_capacity(total);
+        while let Some(Reverse(item)) = heap.pop() {
+            if let Some(line) = inputs[item.input_index].next() {
+                heap.push(Reverse(MergeItem {
+                    line,
+                    input_index: item.input_index,
+                    sorter: self,
+                }));
+            }
+            let duplicate = self.args.unique
+                && merged.last().is_some_and(|prev| {
+                    self.lines_equal_for_unique(&Line::new(prev), &Line::new(&item.line))
+                });
+            if !duplicate {
+                merged.push(item.line);
+            }
+        }
+        merged
+    }
+
     /// Copy a file to output
     fn copy_file_to_output(&self, path: &Path) -> io::Result<()> {
         let mut input = File::open(path)?;
-        let mut output: Box<dyn Write> = if let Some(output_file) = &self.args.output {
-            Box::new(BufWriter::new(File::create(output_file)?))
-        } else {
-            Box::new(BufWriter::new(std::io::stdout()))
-        };
+        let mut output = self.open_output()?;
 
         std::io::copy(&mut input, &mut output)?;
-        output.flush()?;
-        Ok(())
+        output.commit()
     }
 
     /// Sort lines using hybrid algorithm selection for maximum performance
@@ -782,11 +1896,21 @@ impl CoreSort {
             return;
         }
 
+        // A forced algorithm bypasses the pattern detection below
+        let algorithm = self.config.algorithm;
+        match algorithm {
+            SortAlgorithm::Mergesort => return self.merge_sort_lines(lines, cache),
+            SortAlgorithm::Quicksort if !self.args.stable => {
+                return self.three_way_quicksort_lines(lines)
+            }
+            _ => {}
+        }
+
         // **ULTRA OPTIMIZATION: Pattern detection for adaptive algorithm selection**
         let _adaptive_sorter = AdaptiveSort::new();
 
         // Detect data patterns
-        let pattern = if lines.len() > 100 {
+        let pattern = if lines.len() > 100 && algorithm == SortAlgorithm::Auto {
             let sample_lines: Vec<Vec<u8>> = lines
                 .iter()
                 .step_by(lines.len() / 100)
@@ -807,30 +1931,21 @@ impl CoreSort {
 
         // Handle special patterns
         match pattern {
-            DataPattern::MostlySorted => {
+            DataPattern::MostlySorted if lines.len() < 100000 => {
                 // Already mostly sorted - use insertion sort for best performance
-                if lines.len() < 100000 {
-                    self.insertion_sort_lines(lines);
-                    if self.args.reverse {
-                        lines.reverse();
-                    }
-                    return;
-                }
+                self.insertion_sort_lines(lines);
+                return;
             }
-            DataPattern::MostlyReversed => {
+            // Reversing would turn equal lines around under -s
+            DataPattern::MostlyReversed if !self.args.stable => {
                 // Reverse first, then sort
                 lines.reverse();
                 // Continue with normal sorting
             }
-            DataPattern::ManyDuplicates => {
+            DataPattern::ManyDuplicates if !self.numeric_fast_paths() && !self.args.stable => {
                 // Use three-way quicksort for high duplication
-                if !self.args.numeric_sort {
-                    self.three_way_quicksort_lines(lines, 0, lines.len());
-                    if self.args.reverse {
-                        lines.reverse();
-                    }
-                    return;
-                }
+                self.three_way_quicksort_lines(lines);
+                return;
             }
             _ => {}
         }
@@ -839,26 +1954,21 @@ impl CoreSort {
         let mut simple_lines: Vec<Line> = lines.iter().map(|sl| sl.line).collect();
 
         // **BREAKTHROUGH OPTIMIZATION: Use Radix Sort for numeric data**
-        if self.args.numeric_sort {
+        // It leaves equal values such as `1` and `01` in byte order, so -s
+        // takes the comparison sort below
+        if self.numeric_fast_paths() && !self.args.stable {
             const RADIX_THRESHOLD: usize = 1000;
-            const PARALLEL_THRESHOLD: usize = 8192;
 
-            let use_parallel = lines.len() >= PARALLEL_THRESHOLD && num_cpus::get() > 1;
-            let radix_sorter = RadixSort::new(use_parallel);
+            let radix_sorter = RadixSort::new(self.use_parallel(lines.len()));
 
-            if lines.len() >= RADIX_THRESHOLD {
+            if lines.len() >= RADIX_THRESHOLD || algorithm == SortAlgorithm::Radix {
                 // Use ultra-fast radix sort for numeric data (O(n) vs O(n log n))
                 radix_sorter.sort_numeric_lines(&mut simple_lines);
 
-                // Reconstruct SortableLine array maintaining original indices for stability
-                if self.args.stable {
-                    // For stable sort, we need to preserve original order for equal elements
-                    self.reconstruct_stable_sortable_lines(lines, &simple_lines);
-                } else {
-                    // For unstable sort, just update the lines
-                    for (i, line) in simple_lines.into_iter().enumerate() {
-                        lines[i].line = line;
-                    }
+                // Equal values are ordered by the whole line
+                radix_sorter.order_equal_values_bytewise(&mut simple_lines);
+                for (i, line) in simple_lines.into_iter().enumerate() {
+                    lines[i].line = line;
                 }
 
                 // Apply reverse if needed
@@ -870,50 +1980,30 @@ impl CoreSort {
         }
 
         // Fall back to comparison-based sorting for other cases
-        const PARALLEL_THRESHOLD: usize = 8192;
-        if lines.len() >= PARALLEL_THRESHOLD && num_cpus::get() > 1 {
+        if self.use_parallel(lines.len()) {
             self.parallel_sort_lines_with_cache(lines, cache);
         } else {
             self.sequential_sort_lines_with_cache(lines, cache);
         }
     }
 
-    /// Reconstruct SortableLine array while preserving stability
-    fn reconstruct_stable_sortable_lines(
-        &self,
-        sortable_lines: &mut [SortableLine],
-        sorted_simple_lines: &[Line],
-    ) {
-        // Create a mapping from sorted lines back to original indices
-        // Group original indices by line content
-        let mut line_to_indices: HashMap<Vec<u8>, Vec<usize>> = HashMap::new();
-        for (idx, sortable_line) in sortable_lines.iter().enumerate() {
-            unsafe {
-                let bytes = sortable_line.line.as_bytes().to_vec();
-                line_to_indices.entry(bytes).or_default().push(idx);
-            }
-        }
-
-        // Create new sortable lines array
-        let original_lines = sortable_lines.to_vec();
-        let mut next_indices: HashMap<Vec<u8>, usize> = HashMap::new();
+    /// Stable merge sort (`--algorithm=mergesort`)
+    fn merge_sort_lines(&self, lines: &mut [SortableLine], cache: Option<&Arc<ComparisonCache>>) {
+        use rayon::prelude::*;
 
-        for (i, simple_line) in sorted_simple_lines.iter().enumerate() {
-            unsafe {
-                let bytes = simple_line.as_bytes().to_vec();
-                // Use expect with a descriptive message instead of unwrap
-                let indices = line_to_indices
-                    .get(&bytes)
-                    .expect("Missing line index in stable sort reconstruction");
-                let next_idx = next_indices.get(&bytes).copied().unwrap_or(0);
-
-                if next_idx < indices.len() {
-                    let original_idx = indices[next_idx];
-                    sortable_lines[i] = original_lines[original_idx];
-                    sortable_lines[i].line = *simple_line;
-                    next_indices.insert(bytes, next_idx + 1);
-                }
-            }
+        let compare = |a: &SortableLine, b: &SortableLine| match cache {
+            Some(cache) => self.compare_with_cache(a, b, cache),
+            None => a.line.compare_with_keys(
+                &b.line,
+                &self.config.keys,
+                self.config.field_separator,
+                &self.config,
+            ),
+        };
+        if self.use_parallel(lines.len()) {
+            lines.par_sort_by(compare);
+        } else {
+            lines.sort_by(compare);
         }
     }
 
@@ -1010,12 +2100,27 @@ impl CoreSort {
         // Use ultra-optimized hash-based random sort
         // This is 10x faster than the old sort-based approach!
 
-        if lines.len() < 100_000 {
+        let seed = self.args.random_seed;
+        if !self.config.keys.is_empty() {
+            // Lines group by their keys, not by the whole line
+            let mut keyed: Vec<(Vec<u8>, SortableLine)> = lines
+                .iter()
+                .map(|line| (self.random_group_key(&line.line), *line))
+                .collect();
+            if keyed.len() < 100_000 {
+                HashSort::hash_sort(&mut keyed, seed, |(key, _)| key);
+            } else {
+                HashSort::parallel_hash_sort(&mut keyed, seed, |(key, _)| key);
+            }
+            for (line, (_, shuffled)) in lines.iter_mut().zip(keyed) {
+                *line = shuffled;
+            }
+        } else if lines.len() < 100_000 {
             // Single-threaded for smaller datasets
-            HashSort::hash_sort(lines, |line| unsafe { line.line.as_bytes() });
+            HashSort::hash_sort(lines, seed, |line| unsafe { line.line.as_bytes() });
         } else {
             // Parallel processing for large datasets
-            HashSort::parallel_hash_sort(lines, |line| unsafe { line.line.as_bytes() });
+            HashSort::parallel_hash_sort(lines, seed, |line| unsafe { line.line.as_bytes() });
         }
 
         // Apply reverse if needed
@@ -1024,6 +2129,24 @@ impl CoreSort {
         }
     }
 
+    /// What `-R` groups lines by: the whole line, or with keys each key's
+    /// bytes behind their length, so that lines with equal keys group
+    /// together however the rest of them differs
+    fn random_group_key(&self, line: &Line) -> Vec<u8> {
+        if self.config.keys.is_empty() {
+            return unsafe { line.as_bytes().to_vec() };
+        }
+        let mut group_key = Vec::new();
+        for key in &self.config.keys {
+            let field = line
+                .extract_key_with_config(key, self.config.field_separator, &self.config)
+                .unwrap_or_default();
+            group_key.extend_from_slice(&field.len().to_le_bytes());
+            group_key.extend_from_slice(field);
+        }
+        group_key
+    }
+
     /// Try string interning for datasets with many duplicates
     #[allow(dead_code)]
     fn try_string_interning(&self, lines: &mut [SortableLine]) -> bool {
@@ -1084,49 +2207,112 @@ impl CoreSort {
         true
     }
 
-    /// Three-way quicksort for data with many duplicates
-    fn three_way_quicksort_lines(&self, lines: &mut [SortableLine], left: usize, right: usize) {
-        if right <= left + 1 {
-            return;
+    /// Distinct lines, in first-seen order, when sampling suggests that `-u`
+    /// input has very few of them; hashing them out first avoids sorting
+    /// every duplicate. Only whole-line byte equality can be hashed, so keyed
+    /// sorts and any order under which differing bytes compare equal (`-n`,
+    /// `-f` and the like) never take this path.
+    fn distinct_lines_if_few(&self, lines: &[Line]) -> Option<Vec<Line>> {
+        use std::collections::HashSet;
+
+        const MIN_LINES: usize = 1000;
+        if lines.len() < MIN_LINES || !self.is_plain_byte_order() {
+            return None;
         }
 
-        // Choose pivot (median of three)
-        let mid = left + (right - left) / 2;
-        let pivot_idx = self.median_of_three(lines, left, mid, right - 1);
-        lines.swap(left, pivot_idx);
+        // Sample like `try_string_interning`, but require under 1% distinct
+        let sample_size = (lines.len() / 10).clamp(100, 1000);
+        let step = lines.len() / sample_size;
+        let sampled: HashSet<&[u8]> = (0..lines.len())
+            .step_by(step)
+            .map(|i| unsafe { lines[i].as_bytes() })
+            .collect();
+        if sampled.len() * 100 > sample_size {
+            return None;
+        }
+
+        // Give up once the distinct lines stop being a small fraction
+        let limit = lines.len() / 100;
+        let mut seen = HashSet::with_capacity(sampled.len() * 2);
+        let mut distinct = Vec::new();
+        for line in lines {
+            if seen.insert(unsafe { line.as_bytes() }) {
+                if distinct.len() == limit {
+                    return None;
+                }
+                distinct.push(*line);
+            }
+        }
+        Some(distinct)
+    }
 
-        let pivot = lines[left];
-        let mut lt = left; // Elements < pivot
-        let mut i = left + 1; // Current element
-        let mut gt = right; // Elements > pivot
+    /// Three-way quicksort for data with many duplicates
+    ///
+    /// Recursion only descends into the smaller partition, and once a range
+    /// has been partitioned `2 * log2(n)` times it is finished with the
+    /// standard merge sort, so adversarial input can neither overflow the
+    /// stack nor degrade to quadratic time.
+    fn three_way_quicksort_lines(&self, lines: &mut [SortableLine]) {
+        let depth_limit = 2 * (usize::BITS - lines.len().leading_zeros()) as usize;
+        self.three_way_quicksort_range(lines, depth_limit);
+    }
 
-        while i < gt {
-            let cmp = lines[i].line.compare_with_keys(
-                &pivot.line,
+    /// Sort `lines` with at most `depth_limit` further levels of partitioning
+    fn three_way_quicksort_range(&self, mut lines: &mut [SortableLine], mut depth_limit: usize) {
+        let compare = |a: &SortableLine, b: &SortableLine| {
+            a.line.compare_with_keys(
+                &b.line,
                 &self.config.keys,
                 self.config.field_separator,
                 &self.config,
-            );
+            )
+        };
 
-            match cmp {
-                Ordering::Less => {
-                    lines.swap(i, lt);
-                    lt += 1;
-                    i += 1;
-                }
-                Ordering::Greater => {
-                    gt -= 1;
-                    lines.swap(i, gt);
-                }
-                Ordering::Equal => {
-                    i += 1;
+        while lines.len() > 1 {
+            if depth_limit == 0 {
+                lines.sort_by(compare);
+                return;
+            }
+            depth_limit -= 1;
+
+            // Choose pivot (median of three)
+            let mid = lines.len() / 2;
+            let pivot_idx = self.median_of_three(lines, 0, mid, lines.len() - 1);
+            lines.swap(0, pivot_idx);
+
+            let pivot = lines[0];
+            let mut lt = 0; // Elements < pivot
+            let mut i = 1; // Current element
+            let mut gt = lines.len(); // Elements > pivot
+
+            while i < gt {
+                match compare(&lines[i], &pivot) {
+                    Ordering::Less => {
+                        lines.swap(i, lt);
+                        lt += 1;
+                        i += 1;
+                    }
+                    Ordering::Greater => {
+                        gt -= 1;
+                        lines.swap(i, gt);
+                    }
+                    Ordering::Equal => {
+                        i += 1;
+                    }
                 }
             }
-        }
 
-        // Recursively sort left and right parts
-        self.three_way_quicksort_lines(lines, left, lt);
-        self.three_way_quicksort_lines(lines, gt, right);
+            // Recurse into the smaller side and keep looping on the larger
+            let (less, rest) = std::mem::take(&mut lines).split_at_mut(lt);
+            let greater = &mut rest[gt - lt..];
+            if less.len() < greater.len() {
+                self.three_way_quicksort_range(less, depth_limit);
+                lines = greater;
+            } else {
+                self.three_way_quicksort_range(greater, depth_limit);
+                lines = less;
+            }
+        }
     }
 
     /// Find median of three elements for pivot selection
@@ -1199,64 +2385,75 @@ impl CoreSort {
     fn sort_lines_direct(&self, lines: &mut [Line]) {
         use rayon::prelude::*;
 
-        const PARALLEL_THRESHOLD: usize = 8192;
-
         // Handle random sort
         if self.args.random_sort {
             self.random_sort_lines_direct(lines);
             return;
         }
 
+        let algorithm = self.config.algorithm;
+        if algorithm == SortAlgorithm::Mergesort {
+            if self.use_parallel(lines.len()) {
+                lines.par_sort_by(|a, b| self.compare_lines_direct(a, b));
+            } else {
+                lines.sort_by(|a, b| self.compare_lines_direct(a, b));
+            }
+            return;
+        }
+        // Left to choose, radix sorts are kept for inputs of `threshold`
+        // lines or more, where they pay off
+        let len = lines.len();
+        let radix = |threshold: usize| match algorithm {
+            SortAlgorithm::Radix => true,
+            SortAlgorithm::Quicksort => false,
+            _ => len >= threshold,
+        };
+
         // Handle numeric sort with radix optimization
-        if self.args.numeric_sort && lines.len() >= 1000 {
-            let use_parallel = lines.len() >= PARALLEL_THRESHOLD && num_cpus::get() > 1;
-            let radix_sorter = RadixSort::new(use_parallel);
+        if self.numeric_fast_paths() && radix(1000) {
+            let radix_sorter = RadixSort::new(self.use_parallel(lines.len()));
             radix_sorter.sort_numeric_lines(lines);
+            radix_sorter.order_equal_values_bytewise(lines);
             if self.args.reverse {
                 lines.reverse();
             }
             return;
         }
 
+        // Large plain byte-order sorts use MSD radix instead of comparisons
+        const MSD_RADIX_THRESHOLD: usize = 100_000;
+        if radix(MSD_RADIX_THRESHOLD) && self.is_plain_byte_order() {
+            let radix_sorter = RadixSort::new(self.use_parallel(lines.len()));
+            radix_sorter.sort_lexicographic_lines(lines);
+            if self.config.reverse {
+                lines.reverse();
+            }
+            return;
+        }
+
         // Use parallel or sequential sort based on size
-        if lines.len() >= PARALLEL_THRESHOLD && num_cpus::get() > 1 {
-            lines.par_sort_unstable_by(|a, b| {
-                let cmp = a.compare_with_keys(
-                    b,
-                    &self.config.keys,
-                    self.config.field_separator,
-                    &self.config,
-                );
-                if self.args.reverse {
-                    cmp.reverse()
-                } else {
-                    cmp
-                }
-            });
+        if self.use_parallel(lines.len()) {
+            lines.par_sort_unstable_by(|a, b| self.compare_lines_direct(a, b));
         } else {
-            lines.sort_unstable_by(|a, b| {
-                let cmp = a.compare_with_keys(
-                    b,
-                    &self.config.keys,
-                    self.config.field_separator,
-                    &self.config,
-                );
-                if self.args.reverse {
-                    cmp.reverse()
-                } else {
-                    cmp
-                }
-            });
+            lines.sort_unstable_by(|a, b| self.compare_lines_direct(a, b));
         }
     }
 
     /// Random sort without SortableLine wrapper
     fn random_sort_lines_direct(&self, lines: &mut [Line]) {
-        // Group identical lines
+        // Group lines with identical keys, listing the groups in input order
+        // so that a seed always gives the same shuffle
         let mut groups: HashMap<Vec<u8>, Vec<usize>> = HashMap::new();
+        let mut group_keys: Vec<Vec<u8>> = Vec::new();
         for (idx, line) in lines.iter().enumerate() {
-            let key = unsafe { line.as_bytes().to_vec() };
-            groups.entry(key).or_default().push(idx);
+            let key = self.random_group_key(line);
+            groups
+                .entry(key)
+                .or_insert_with_key(|key| {
+                    group_keys.push(key.clone());
+                    Vec::new()
+                })
+                .push(idx);
         }
 
         // Create shuffled order for groups
@@ -1266,12 +2463,8 @@ impl CoreSort {
             StdRng::from_entropy()
         };
 
-        let mut group_keys: Vec<Vec<u8>> = groups.keys().cloned().collect();
-        for _ in 0..group_keys.len() {
-            let i = rng.gen_range(0..group_keys.len());
-            let j = rng.gen_range(0..group_keys.len());
-            group_keys.swap(i, j);
-        }
+        // Fisher-Yates, so every order of the groups is equally likely
+        group_keys.shuffle(&mut rng);
 
         // Rebuild lines array in shuffled order
         let mut result = Vec::with_capacity(lines.len());
@@ -1287,21 +2480,16 @@ impl CoreSort {
     }
 
     /// Write output directly from Line slice (no SortableLine wrapper)
-    fn write_output_direct(&self, lines: &[Line]) -> io::Result<()> {
-        let mut output: Box<dyn Write> = if let Some(output_file) = &self.args.output {
-            Box::new(BufWriter::new(File::create(output_file)?))
-        } else {
-            Box::new(BufWriter::new(std::io::stdout()))
-        };
+    fn write_output_direct(&self, lines: &[Line], output: &mut dyn Write) -> io::Result<()> {
+        let terminator = [self.line_terminator()];
 
         for line in lines {
             unsafe {
                 output.write_all(line.as_bytes())?;
-                output.write_all(b"\n")?;
+                output.write_all(&terminator)?;
             }
         }
 
-        output.flush()?;
         Ok(())
     }
 
@@ -1309,6 +2497,21 @@ impl CoreSort {
     fn sort_lines_direct_stable(&self, lines: &mut [Line]) -> Vec<Line> {
         use rayon::prelude::*;
 
+        // Integers take a radix sort on `(value, index)` pairs, which keeps
+        // equal values in input order
+        let radix = match self.config.algorithm {
+            SortAlgorithm::Radix => true,
+            SortAlgorithm::Auto => lines.len() >= 1000,
+            _ => false,
+        };
+        if self.numeric_fast_paths()
+            && radix
+            && RadixSort::new(self.use_parallel(lines.len()))
+                .stable_sort_integer_lines(lines, self.args.reverse)
+        {
+            return lines.to_vec();
+        }
+
         // Create array of (Line, original_index) tuples for stability
         let mut indexed_lines: Vec<(Line, usize)> = lines
             .iter()
@@ -1316,10 +2519,8 @@ impl CoreSort {
             .map(|(idx, line)| (*line, idx))
             .collect();
 
-        const PARALLEL_THRESHOLD: usize = 8192;
-
         // Use parallel or sequential stable sort
-        if indexed_lines.len() >= PARALLEL_THRESHOLD && num_cpus::get() > 1 {
+        if self.use_parallel(indexed_lines.len()) {
             indexed_lines.par_sort_by(|a, b| {
                 let cmp = self.compare_lines_direct(&a.0, &b.0);
                 if cmp == Ordering::Equal {
@@ -1346,22 +2547,17 @@ impl CoreSort {
     }
 
     /// Write sorted output
-    fn write_output(&self, lines: &[SortableLine]) -> io::Result<()> {
-        let mut output: Box<dyn Write> = if let Some(output_file) = &self.args.output {
-            Box::new(BufWriter::new(File::create(output_file)?))
-        } else {
-            Box::new(BufWriter::new(std::io::stdout()))
-        };
+    fn write_output(&self, lines: &[SortableLine], output: &mut dyn Write) -> io::Result<()> {
+        let terminator = [self.line_terminator()];
 
         // Regular output - unique is handled earlier in the pipeline
         for line in lines {
             unsafe {
                 output.write_all(line.line.as_bytes())?;
-                output.write_all(b"\n")?;
+                output.write_all(&terminator)?;
             }
         }
 
-        output.flush()?;
         Ok(())
     }
 }
@@ -1411,18 +2607,19 @@ impl ComparisonCache {
                     }
                 }
 
-                // Pre-compute case-folded version if needed
+                // Pre-compute case-folded version if needed; GNU folds to
+                // uppercase, so `_` sorts after the letters
                 if config.ignore_case {
                     unsafe {
                         let bytes = line.as_bytes();
-                        entry.folded_bytes = Some(bytes.to_ascii_lowercase());
+                        entry.folded_bytes = Some(bytes.to_ascii_uppercase());
                     }
                 }
 
                 // Pre-compute hash for random sort
                 if config.mode == crate::config::SortMode::Random {
                     use std::hash::{Hash, Hasher};
-                    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                    let mut hasher = crate::hash_sort::RandomHasher::default();
                     unsafe {
                         line.as_bytes().hash(&mut hasher);
                     }
@@ -1437,46 +2634,7 @@ impl ComparisonCache {
     }
 
     fn parse_numeric(bytes: &[u8]) -> Option<f64> {
-        // Skip leading whitespace
-        let trimmed = bytes
-            .iter()
-            .position(|&b| !b.is_ascii_whitespace())
-            .map(|pos| &bytes[pos..])
-            .unwrap_or(bytes);
-
-        if trimmed.is_empty() {
-            return Some(0.0);
-        }
-
-        // Try to parse as number
-        let mut end = 0;
-        let mut has_digit = false;
-        let mut has_dot = false;
-
-        for (i, &b) in trimmed.iter().enumerate() {
-            match b {
-                b'0'..=b'9' => {
-                    has_digit = true;
-                    end = i + 1;
-                }
-                b'.' if !has_dot => {
-                    has_dot = true;
-                    end = i + 1;
-                }
-                b'-' | b'+' if i == 0 => {
-                    end = i + 1;
-                }
-                _ => break,
-            }
-        }
-
-        if has_digit && end > 0 {
-            std::str::from_utf8(&trimmed[..end])
-                .ok()
-                .and_then(|s| s.parse::<f64>().ok())
-        } else {
-            Some(0.0)
-        }
+        Some(crate::numeric::numeric_value(bytes))
     }
 }
 
@@ -1485,9 +2643,15 @@ impl ComparisonCache {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::cell::Cell;
     use std::fs;
     use tempfile::TempDir;
 
+    thread_local! {
+        /// Worker threads spawned by pools built on this thread
+        pub(super) static THREADS_SPAWNED: Cell<usize> = const { Cell::new(0) };
+    }
+
     #[test]
     fn test_ultimate_sort_basic() -> io::Result<()> {
         let temp_dir = TempDir::new()?;
@@ -1516,6 +2680,232 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn test_worker_threads_are_named() -> io::Result<()> {
+        let config = crate::config::SortConfig::default().with_parallel_threads(Some(3));
+        let sorter = CoreSort::new(SortArgs::default(), config);
+        let pool = sorter.worker_pool()?;
+
+        let mut names = pool.broadcast(|_| thread::current().name().map(str::to_string));
+        names.sort();
+        assert_eq!(
+            names,
+            ["sort-worker-0", "sort-worker-1", "sort-worker-2"].map(|n| Some(n.to_string()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_unique_keeps_first_of_equal_lines() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let first = temp_dir.path().join("first.txt");
+        let second = temp_dir.path().join("second.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        // Over 1MB of numbers written as `7`, `07` or `7.0`, in no fixed order
+        let lines: Vec<String> = (0..200_000u64)
+            .map(|n| {
+                let value = (n * 7919) % 5_003;
+                match n % 3 {
+                    0 => format!("{value}"),
+                    1 => format!("0{value}"),
+                    _ => format!("{value}.0"),
+                }
+            })
+            .collect();
+        let input = lines.join("\n") + "\n";
+        fs::write(&first, lines[..100_000].join("\n") + "\n")?;
+        fs::write(&second, lines[100_000..].join("\n") + "\n")?;
+        let whole = temp_dir.path().join("whole.txt");
+        fs::write(&whole, &input)?;
+
+        // Each value is represented by the line it first appeared as
+        let mut kept: Vec<(u64, &str)> = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        for line in &lines {
+            let value: f64 = line.parse().unwrap();
+            if seen.insert(value as u64) {
+                kept.push((value as u64, line));
+            }
+        }
+        kept.sort();
+        let expected: String = kept.iter().map(|(_, line)| format!("{line}\n")).collect();
+
+        let (config, _) = crate::config::SortConfig::from_args(&["sort", "-nu"]).unwrap();
+        let sorted = crate::sort_bytes(&config, input.as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        assert_eq!(String::from_utf8_lossy(&sorted), expected);
+
+        let external = config.clone().with_buffer_size(Some(1024 * 1024));
+        for (files, config) in [(vec![&first, &second], config), (vec![&whole], external)] {
+            let args = SortArgs {
+                files: files
+                    .iter()
+                    .map(|f| f.to_string_lossy().to_string())
+                    .collect(),
+                output: Some(output_file.to_string_lossy().to_string()),
+                numeric_sort: true,
+                unique: true,
+                ..Default::default()
+            };
+            CoreSort::new(args, config).sort()?;
+            assert_eq!(fs::read_to_string(&output_file)?, expected, "{files:?}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_keys_compare_to_the_end_of_the_key() {
+        // Expected outputs are GNU sort's
+        let cases: [(&[&str], &[u8], &[u8]); 4] = [
+            (&["-u", "-k2"], b"a x 2\nb x 1\n", b"b x 1\na x 2\n"),
+            (&["-u", "-k2"], b"a x\nb x\nc  x\n", b"c  x\na x\n"),
+            (
+                &["-u", "-k1,1"],
+                b"b 2\n b 1\nb 1\na 3\nb\n  a\n",
+                b"  a\n b 1\na 3\nb 2\n",
+            ),
+            (&["-nu", "-k2"], b"a 1 z\nb 1 y\nc 01 y\n", b"a 1 z\n"),
+        ];
+        for (args, input, expected) in cases {
+            let argv: Vec<&str> = std::iter::once("sort")
+                .chain(args.iter().copied())
+                .collect();
+            let (config, _) = crate::config::SortConfig::from_args(&argv).unwrap();
+            let sorted = crate::sort_bytes(&config, input).unwrap();
+            assert_eq!(
+                String::from_utf8_lossy(&sorted),
+                String::from_utf8_lossy(expected),
+                "{args:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parallel_one_spawns_a_single_thread() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let first = temp_dir.path().join("first.txt");
+        let second = temp_dir.path().join("second.txt");
+        let output_file = temp_dir.path().join("output.txt");
+        let sorted = |lines: &[String]| {
+            let mut lines = lines.to_vec();
+            lines.sort();
+            lines.join("\n") + "\n"
+        };
+        let lines: Vec<String> = (0..200_000u64)
+            .map(|n| format!("{:07}", (n * 7919) % 1_000_003))
+            .collect();
+        let (head, tail) = lines.split_at(100_000);
+        fs::write(&first, head.join("\n") + "\n")?;
+        fs::write(&second, tail.join("\n") + "\n")?;
+
+        let one_thread = crate::config::SortConfig::default().with_parallel_threads(Some(1));
+        let cases = [
+            (vec![&first], one_thread.clone(), sorted(head)),
+            (vec![&first, &second], one_thread.clone(), sorted(&lines)),
+            // Past the 1MB buffer, so chunks are sorted and merged on disk
+            (
+                vec![&first],
+                one_thread.with_buffer_size(Some(1024 * 1024)),
+                sorted(head),
+            ),
+        ];
+        for (files, config, expected) in cases {
+            let args = SortArgs {
+                files: files
+                    .iter()
+                    .map(|f| f.to_string_lossy().to_string())
+                    .collect(),
+                output: Some(output_file.to_string_lossy().to_string()),
+                ..Default::default()
+            };
+            let before = THREADS_SPAWNED.with(Cell::get);
+            CoreSort::new(args, config).sort()?;
+            assert_eq!(THREADS_SPAWNED.with(Cell::get) - before, 1);
+            assert_eq!(fs::read_to_string(&output_file)?, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_random_sort_groups_lines_by_key() {
+        let data: Vec<String> = (0..60).map(|i| format!("k{} rest{i}", i % 6)).collect();
+        let (config, _) = SortConfig::from_args(&["sort", "-R", "-k1,1"]).unwrap();
+        let args = SortArgs {
+            random_sort: true,
+            ..Default::default()
+        };
+        let sorter = CoreSort::new(args, config);
+
+        // Every first field forms one contiguous run
+        let assert_grouped = |sorted: Vec<&[u8]>| {
+            let mut seen: Vec<&[u8]> = Vec::new();
+            for line in &sorted {
+                let key = line.split(|&b| b == b' ').next().unwrap();
+                if seen.last() != Some(&key) {
+                    assert!(!seen.contains(&key), "{key:?} split up");
+                    seen.push(key);
+                }
+            }
+            assert_eq!(seen.len(), 6);
+            assert_eq!(sorted.len(), data.len());
+        };
+
+        let mut lines: Vec<Line> = data.iter().map(|s| Line::new(s.as_bytes())).collect();
+        sorter.random_sort_lines_direct(&mut lines);
+        assert_grouped(
+            lines
+                .iter()
+                .map(|line| unsafe { line.as_bytes() })
+                .collect(),
+        );
+
+        let mut lines: Vec<SortableLine> = data
+            .iter()
+            .enumerate()
+            .map(|(original_index, s)| SortableLine {
+                line: Line::new(s.as_bytes()),
+                original_index,
+            })
+            .collect();
+        sorter.random_sort_lines(&mut lines);
+        assert_grouped(lines.iter().map(|l| unsafe { l.line.as_bytes() }).collect());
+    }
+
+    #[test]
+    fn test_random_sort_group_orders_are_uniform() {
+        let data = ["b", "a", "c", "a", "b", "c"];
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        const SEEDS: u64 = 6_000;
+        for seed in 0..SEEDS {
+            let args = SortArgs {
+                random_sort: true,
+                random_seed: Some(seed),
+                ..Default::default()
+            };
+            let sorter = CoreSort::new(args, SortConfig::default());
+            let mut lines: Vec<Line> = data.iter().map(|s| Line::new(s.as_bytes())).collect();
+            sorter.random_sort_lines_direct(&mut lines);
+            let order: String = lines
+                .iter()
+                .map(|line| unsafe { line.as_bytes()[0] as char })
+                .collect();
+            // Equal lines come out together, so each order is three pairs
+            let groups: String = order.chars().step_by(2).collect();
+            assert_eq!(
+                order,
+                groups.chars().flat_map(|c| [c, c]).collect::<String>()
+            );
+            *counts.entry(groups).or_default() += 1;
+        }
+
+        // All six orders, each within a few standard deviations of 1000
+        assert_eq!(counts.len(), 6);
+        for (order, count) in counts {
+            assert!((850..1150).contains(&count), "{order}: {count}");
+        }
+    }
+
     #[test]
     fn test_numeric_sort() -> io::Result<()> {
         let temp_dir = TempDir::new()?;
@@ -1545,4 +2935,1160 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_numeric_ties_order_by_whole_line() {
+        let config =
+            crate::config::SortConfig::default().with_mode(crate::config::SortMode::Numeric);
+        // A few lines take the comparison sort, a few thousand the radix sort
+        for repeats in [1, 1000] {
+            let input = ["7\n", "007\n", "07\n", "3\n"].repeat(repeats).concat();
+            let expected = [
+                "3\n".repeat(repeats),
+                "007\n".repeat(repeats),
+                "07\n".repeat(repeats),
+                "7\n".repeat(repeats),
+            ]
+            .concat();
+            let sorted = crate::sort_bytes(&config, input.as_bytes()).unwrap();
+            assert_eq!(String::from_utf8(sorted).unwrap(), expected);
+
+            // -s keeps the equal values in input order instead
+            let stable =
+                crate::sort_bytes(&config.clone().with_stable(true), input.as_bytes()).unwrap();
+            let stable = String::from_utf8(stable).unwrap();
+            assert!(stable[2 * repeats..].starts_with("7\n007\n07\n"));
+        }
+    }
+
+    #[test]
+    fn test_ignore_case_orders_like_gnu() -> io::Result<()> {
+        let input = "_x\n[b\nA_\nAPPLE\napple\nApple\n@z\n`q\nb\nB\nzeta\n{c\n";
+        // GNU folds to uppercase, so `[ _ \`` sort after every letter
+        let folding = SortConfig {
+            ignore_case: true,
+            ..Default::default()
+        };
+        for (config, expected) in [
+            (
+                folding.clone(),
+                "@z APPLE Apple apple A_ B b zeta [b _x `q {c",
+            ),
+            (
+                folding.clone().with_reverse(true),
+                "{c `q _x [b zeta b B A_ apple Apple APPLE @z",
+            ),
+            (
+                folding.clone().with_stable(true),
+                "@z APPLE apple Apple A_ b B zeta [b _x `q {c",
+            ),
+        ] {
+            let expected: String = expected
+                .split(' ')
+                .map(|line| format!("{line}\n"))
+                .collect();
+            let sorted = crate::sort_bytes(&config, input.as_bytes()).unwrap();
+            assert_eq!(String::from_utf8_lossy(&sorted), expected);
+
+            // The cached folded lines of the SortableLine path
+            let sorter = CoreSort::new(crate::sort_args(&config, &[]), config);
+            assert!(sorter.case_fold_fast_path());
+            let parsed = crate::zero_copy::parse_lines(input.as_bytes());
+            let mut lines: Vec<SortableLine> = parsed
+                .iter()
+                .enumerate()
+                .map(|(original_index, &line)| SortableLine {
+                    line,
+                    original_index,
+                })
+                .collect();
+            let cache = Arc::new(ComparisonCache::new(&parsed, &sorter.config));
+            sorter.sort_lines_with_cache(&mut lines, Some(&cache));
+            let mut output = Vec::new();
+            sorter.write_output(&lines, &mut output)?;
+            assert_eq!(String::from_utf8_lossy(&output), expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_keyed_sorts_skip_whole_line_fast_paths() {
+        // Enough lines for the radix and cached paths, whose whole-line
+        // values would ignore the keys
+        let input: String = (0..3000)
+            .map(|n| format!("{} {}\n", ["B", "a"][n % 2], 3000 - n))
+            .collect();
+        let mut lines: Vec<&str> = input.lines().collect();
+        let field = |line: &str| line.split(' ').nth(1).unwrap().parse::<u32>().unwrap();
+        lines.sort_by_key(|&line| field(line));
+        let expected: String = lines.iter().map(|line| format!("{line}\n")).collect();
+
+        for config in [
+            crate::config::SortConfig::default().with_mode(crate::config::SortMode::Numeric),
+            crate::config::SortConfig::default()
+                .with_mode(crate::config::SortMode::Numeric)
+                .with_unique(true),
+        ] {
+            let config = config.add_key(crate::config::SortKey::parse("2").unwrap());
+            let sorted = crate::sort_bytes(&config, input.as_bytes()).unwrap();
+            assert_eq!(String::from_utf8(sorted).unwrap(), expected);
+        }
+
+        let config = crate::config::SortConfig {
+            ignore_case: true,
+            ..Default::default()
+        }
+        .with_unique(true)
+        .add_key(crate::config::SortKey::parse("2,2n").unwrap());
+        let sorted = crate::sort_bytes(&config, input.as_bytes()).unwrap();
+        assert_eq!(String::from_utf8(sorted).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_stdin_spills_past_memory_budget() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let output_file = temp_dir.path().join("output.txt");
+
+        let input: String = (0..200_000).rev().map(|n| format!("{n}\n")).collect();
+        let budget = crate::config::MIN_EXTERNAL_BUFFER_SIZE;
+        assert!(input.len() > budget);
+
+        let args = SortArgs {
+            output: Some(output_file.to_string_lossy().to_string()),
+            numeric_sort: true,
+            ..Default::default()
+        };
+        let mut config =
+            crate::config::SortConfig::default().with_mode(crate::config::SortMode::Numeric);
+        config.buffer_size = Some(budget);
+        let sorter = CoreSort::new(args, config);
+
+        // Only budget + 1 bytes are buffered before the rest is streamed to disk
+        match sorter.read_bounded(io::Cursor::new(input.as_bytes()), budget)? {
+            StdinInput::Spilled(temp_file) => {
+                assert_eq!(fs::read(temp_file.path())?, input.as_bytes());
+            }
+            StdinInput::InMemory(_) => panic!("input larger than the budget was kept in memory"),
+        }
+
+        sorter.sort_reader(io::Cursor::new(input.as_bytes()))?;
+        let expected: String = (0..200_000).map(|n| format!("{n}\n")).collect();
+        assert_eq!(fs::read_to_string(&output_file)?, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_mode_interleaves_sorted_files() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let output_file = temp_dir.path().join("output.txt");
+        let mut files = Vec::new();
+        for (name, contents) in [
+            ("a", "1\n4\n7\n"),
+            ("b", "2\n5\n8\n10\n"),
+            ("c", "3\n6\n9\n"),
+        ] {
+            let path = temp_dir.path().join(name);
+            fs::write(&path, contents)?;
+            files.push(path.to_string_lossy().to_string());
+        }
+
+        let config = crate::config::SortConfig::default()
+            .with_mode(crate::config::SortMode::Numeric)
+            .with_merge(true);
+        let mut args = crate::sort_args(&config, &files);
+        args.output = Some(output_file.to_string_lossy().to_string());
+        CoreSort::new(args, config.clone()).sort()?;
+        assert_eq!(
+            fs::read_to_string(&output_file)?,
+            "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n"
+        );
+
+        // Inputs are taken as sorted, not sorted again
+        fs::write(temp_dir.path().join("a"), "7\n1\n")?;
+        let mut args = crate::sort_args(&config, &files[..1]);
+        args.output = Some(output_file.to_string_lossy().to_string());
+        CoreSort::new(args, config).sort()?;
+        assert_eq!(fs::read_to_string(&output_file)?, "7\n1\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_tiny_buffer_size_is_clamped_for_spilling() {
+        let config = crate::config::SortConfig::default().with_buffer_size(Some(1024));
+        let sorter = CoreSort::new(SortArgs::default(), config);
+        assert_eq!(
+            sorter.stdin_memory_budget(),
+            crate::config::MIN_EXTERNAL_BUFFER_SIZE
+        );
+    }
+
+    #[test]
+    fn test_small_buffer_size_sorts_file_externally() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        // About 3MB: well under the default threshold, over a 1MB -S
+        let mut lines: Vec<String> = (0..200_000u64)
+            .map(|n| format!("{:07}", (n * 7919) % 1_000_003))
+            .collect();
+        fs::write(&input_file, lines.join("\n") + "\n")?;
+        let file_size = fs::metadata(&input_file)?.len() as usize;
+
+        let unlimited = CoreSort::new(SortArgs::default(), Default::default());
+        assert!(!unlimited.needs_external_sort(file_size));
+
+        let config = crate::config::SortConfig::default().with_buffer_size(Some(1024 * 1024));
+        let args = SortArgs {
+            files: vec![input_file.to_string_lossy().to_string()],
+            output: Some(output_file.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let sorter = CoreSort::new(args, config);
+        assert!(sorter.needs_external_sort(file_size));
+        assert_eq!(sorter.external_memory_limit_mb(file_size), 1);
+        sorter.sort()?;
+
+        lines.sort();
+        assert_eq!(fs::read_to_string(&output_file)?, lines.join("\n") + "\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_stdin_within_budget_sorts_in_memory() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let output_file = temp_dir.path().join("output.txt");
+
+        let args = SortArgs {
+            output: Some(output_file.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let sorter = CoreSort::new(args, crate::config::SortConfig::default());
+
+        let input = b"pear\napple\nfig\n";
+        assert!(matches!(
+            sorter.read_bounded(&input[..], input.len())?,
+            StdinInput::InMemory(ref buffer) if buffer == input
+        ));
+
+        sorter.sort_reader(&input[..])?;
+        assert_eq!(fs::read_to_string(&output_file)?, "apple\nfig\npear\n");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gz_output_is_gzipped() -> io::Result<()> {
+        use std::io::Read;
+
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("out.gz");
+        fs::write(&input_file, "pear\napple\nfig\n")?;
+
+        let args = SortArgs {
+            files: vec![input_file.to_string_lossy().to_string()],
+            output: Some(output_file.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        CoreSort::new(args, crate::config::SortConfig::default()).sort()?;
+
+        let mut sorted = String::new();
+        flate2::read::GzDecoder::new(File::open(&output_file)?).read_to_string(&mut sorted)?;
+        assert_eq!(sorted, "apple\nfig\npear\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_sort_matches_sequential_around_threshold() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+
+        let sort_with_threads = |config: &crate::config::SortConfig,
+                                 threads: usize,
+                                 len: usize|
+         -> io::Result<String> {
+            let output_file = temp_dir.path().join(format!("out_{threads}.txt"));
+            let config = config.clone().with_parallel_threads(Some(threads));
+            let args = SortArgs {
+                files: vec![input_file.to_string_lossy().to_string()],
+                output: Some(output_file.to_string_lossy().to_string()),
+                ..crate::sort_args(&config, &[])
+            };
+            let sorter = CoreSort::new(args, config);
+            assert_eq!(sorter.use_parallel(len), threads > 1 && len >= 8192);
+            sorter.sort()?;
+            fs::read_to_string(output_file)
+        };
+
+        // Either side of the 8192-line threshold, through the radix, the
+        // default comparison and the merge sort, with zero-padded ties
+        let numeric =
+            crate::config::SortConfig::default().with_mode(crate::config::SortMode::Numeric);
+        let configs = [
+            numeric.clone(),
+            numeric.with_algorithm(SortAlgorithm::Mergesort),
+            crate::config::SortConfig::default()
+                .add_key(crate::config::SortKey::parse("1.2").unwrap()),
+        ];
+        for count in [8_191usize, 8_192, 20_000] {
+            let input: String = (0..count)
+                .map(|i| format!("{:0width$}\n", (i * 7919) % 5000, width = i % 3))
+                .collect();
+            fs::write(&input_file, &input)?;
+            for config in &configs {
+                let sequential = sort_with_threads(config, 1, count)?;
+                let parallel = sort_with_threads(config, 4, count)?;
+                assert_eq!(sequential, parallel, "{count} lines, {config:?}");
+                assert_eq!(sequential.len(), input.len());
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_exponent_under_numeric_and_general_numeric() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        fs::write(&input_file, "1e3\n2\n1\n")?;
+
+        let sort_in_mode = |mode: crate::config::SortMode| -> io::Result<String> {
+            let output_file = temp_dir.path().join("output.txt");
+            let args = SortArgs {
+                files: vec![input_file.to_string_lossy().to_string()],
+                output: Some(output_file.to_string_lossy().to_string()),
+                numeric_sort: mode == crate::config::SortMode::Numeric,
+                general_numeric_sort: mode == crate::config::SortMode::GeneralNumeric,
+                ..Default::default()
+            };
+            let config = crate::config::SortConfig::default().with_mode(mode);
+            CoreSort::new(args, config).sort()?;
+            fs::read_to_string(output_file)
+        };
+
+        // -n: "1e3" equals 1, and the whole-line tie-break puts "1" first
+        assert_eq!(
+            sort_in_mode(crate::config::SortMode::Numeric)?,
+            "1\n1e3\n2\n"
+        );
+        // -g: "1e3" is 1000
+        assert_eq!(
+            sort_in_mode(crate::config::SortMode::GeneralNumeric)?,
+            "1\n2\n1e3\n"
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trailing_newline_policies() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        let cases = [
+            ("b\na\n", TrailingNewline::Preserve, "a\nb\n"),
+            ("b\na", TrailingNewline::Preserve, "a\nb"),
+            ("b\na\n", TrailingNewline::Always, "a\nb\n"),
+            ("b\na", TrailingNewline::Always, "a\nb\n"),
+            ("b\na\n", TrailingNewline::Never, "a\nb"),
+            ("b\na", TrailingNewline::Never, "a\nb"),
+        ];
+
+        for (input, policy, expected) in cases {
+            fs::write(&input_file, input)?;
+            let args = SortArgs {
+                files: vec![input_file.to_string_lossy().to_string()],
+                output: Some(output_file.to_string_lossy().to_string()),
+                ..Default::default()
+            };
+            let config = crate::config::SortConfig::default().with_trailing_newline(policy);
+            CoreSort::new(args, config.clone()).sort()?;
+            assert_eq!(
+                fs::read_to_string(&output_file)?,
+                expected,
+                "file input {input:?} with {policy}"
+            );
+
+            // Same policy when reading the input as a stream
+            let args = SortArgs {
+                output: Some(output_file.to_string_lossy().to_string()),
+                ..Default::default()
+            };
+            CoreSort::new(args, config).sort_reader(input.as_bytes())?;
+            assert_eq!(
+                fs::read_to_string(&output_file)?,
+                expected,
+                "stream input {input:?} with {policy}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_last_moves_blank_lines_to_the_end() -> io::Result<()> {
+        let input = "3\n\nb\n  \n10\na\n\t\n2";
+        let cases = [
+            (
+                crate::config::SortMode::Lexicographic,
+                "10\n2\n3\na\nb\n\n\t\n  \n",
+            ),
+            (
+                crate::config::SortMode::Numeric,
+                "a\nb\n2\n3\n10\n\n\t\n  \n",
+            ),
+        ];
+        for (mode, expected) in cases {
+            let config = crate::config::SortConfig::default()
+                .with_mode(mode)
+                .with_empty_last(true)
+                .with_trailing_newline(TrailingNewline::Always);
+            let sorter = CoreSort::new(crate::sort_args(&config, &[]), config);
+            let mut output = Vec::new();
+            sorter.sort_buffer(input.as_bytes(), &mut output)?;
+            assert_eq!(String::from_utf8_lossy(&output), expected, "{mode:?}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_failed_merge_leaves_output_untouched() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let sorted = temp_dir.path().join("sorted.txt");
+        let output_file = temp_dir.path().join("output.txt");
+        fs::write(&sorted, "a\nb\n")?;
+        fs::write(&output_file, "original\n")?;
+
+        // Reading a directory fails partway through the merge
+        let unreadable = temp_dir.path().join("subdir");
+        fs::create_dir(&unreadable)?;
+
+        let args = SortArgs {
+            output: Some(output_file.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        let sorter = CoreSort::new(args, crate::config::SortConfig::default());
+        assert!(sorter.merge_sorted_files(&[sorted, unreadable]).is_err());
+
+        assert_eq!(fs::read_to_string(&output_file)?, "original\n");
+        let leftovers: Vec<_> = fs::read_dir(temp_dir.path())?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with(".sort"))
+            .collect();
+        assert!(leftovers.is_empty(), "staging file left behind");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_output_may_be_an_input() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let file = temp_dir.path().join("data.txt");
+        fs::write(&file, "c\na\nb\n")?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&file, fs::Permissions::from_mode(0o640))?;
+        }
+
+        let path = file.to_string_lossy().to_string();
+        let args = SortArgs {
+            files: vec![path.clone()],
+            output: Some(path),
+            ..Default::default()
+        };
+        CoreSort::new(args, crate::config::SortConfig::default()).sort()?;
+
+        assert_eq!(fs::read_to_string(&file)?, "a\nb\nc\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&file)?.permissions().mode() & 0o777, 0o640);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multiple_files_sort_through_temp_dir() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let first = temp_dir.path().join("first.txt");
+        let second = temp_dir.path().join("second.txt");
+        let output = temp_dir.path().join("out.txt");
+        fs::write(&first, "pear\napple\n")?;
+        fs::write(&second, "fig\nbanana\n")?;
+
+        let files = [&first, &second].map(|p| p.to_string_lossy().to_string());
+        let config = crate::config::SortConfig::default()
+            .with_temp_dir(Some(temp_dir.path().to_string_lossy().to_string()));
+        let args = SortArgs {
+            files: files.to_vec(),
+            output: Some(output.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        CoreSort::new(args.clone(), config).sort()?;
+        assert_eq!(fs::read_to_string(&output)?, "apple\nbanana\nfig\npear\n");
+
+        // An unusable -T directory falls back to TMPDIR or the system default
+        fs::remove_file(&output)?;
+        let unwritable = first.join("tmp").to_string_lossy().to_string();
+        let config = crate::config::SortConfig::default().with_temp_dir(Some(unwritable));
+        CoreSort::new(args, config).sort()?;
+        assert_eq!(fs::read_to_string(&output)?, "apple\nbanana\nfig\npear\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_file_named_dash_is_not_stdin() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let dash = temp_dir.path().join("-");
+        let other = temp_dir.path().join("other.txt");
+        let output = temp_dir.path().join("out.txt");
+        fs::write(&dash, "pear\napple\n")?;
+        fs::write(&other, "fig\n")?;
+
+        // Only a bare `-` means stdin; any other spelling names the file
+        let dash = format!("{}/./-", temp_dir.path().display());
+        let other = other.to_string_lossy().to_string();
+        for (files, expected) in [
+            (vec![dash.clone()], "apple\npear\n"),
+            (vec![dash.clone(), other], "apple\nfig\npear\n"),
+        ] {
+            let args = SortArgs {
+                files: files.clone(),
+                output: Some(output.to_string_lossy().to_string()),
+                ..Default::default()
+            };
+            CoreSort::new(args, Default::default()).sort()?;
+            assert_eq!(fs::read_to_string(&output)?, expected, "{files:?}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_forced_algorithm_sorts_correctly() {
+        use crate::config::{SortAlgorithm, SortConfig, SortMode};
+
+        // Mostly sorted, then heavily duplicated: inputs the pattern
+        // detection would send down its insertion sort and quicksort branches
+        let mut mostly_sorted: Vec<String> = (0..2000).map(|n| format!("{n:05}")).collect();
+        mostly_sorted.swap(10, 1500);
+        let duplicated: Vec<String> = (0..2000).map(|n| format!("{}", n % 3)).collect();
+
+        for algorithm in [
+            SortAlgorithm::Mergesort,
+            SortAlgorithm::Quicksort,
+            SortAlgorithm::Radix,
+        ] {
+            for mode in [SortMode::Lexicographic, SortMode::Numeric] {
+                let config = SortConfig::default()
+                    .with_mode(mode)
+                    .with_algorithm(algorithm);
+                let sorter = CoreSort::new(crate::sort_args(&config, &[]), config.clone());
+                for input in [&mostly_sorted, &duplicated] {
+                    let mut expected = input.clone();
+                    expected.sort();
+                    let text = input.join("\n") + "\n";
+
+                    // The SortableLine path used per file of a multi-file sort
+                    let parsed = crate::zero_copy::parse_lines(text.as_bytes());
+                    let mut lines: Vec<SortableLine> = parsed
+                        .iter()
+                        .enumerate()
+                        .map(|(original_index, &line)| SortableLine {
+                            line,
+                            original_index,
+                        })
+                        .collect();
+                    sorter.sort_lines(&mut lines);
+                    let sorted: Vec<String> = lines
+                        .iter()
+                        .map(|l| unsafe { String::from_utf8_lossy(l.line.as_bytes()) }.into_owned())
+                        .collect();
+                    assert_eq!(sorted, expected, "{algorithm:?} {mode:?}");
+
+                    // The direct path of an in-memory sort
+                    let sorted = crate::sort_bytes(&config, text.as_bytes()).unwrap();
+                    assert_eq!(sorted, (expected.join("\n") + "\n").as_bytes());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_numeric_prefix_ignores_trailing_junk() -> io::Result<()> {
+        use crate::config::{SortAlgorithm, SortConfig, SortMode};
+
+        // `123abc` is 123: after `123`, which wins the whole-line tie-break,
+        // and before 124
+        assert_eq!(
+            Line::new(b"123abc").compare_numeric(&Line::new(b"123")),
+            Ordering::Equal
+        );
+        assert_eq!(crate::numeric::parse_integer(b"123abc"), None);
+        assert_eq!(ComparisonCache::parse_numeric(b"123abc"), Some(123.0));
+
+        let input = "124\n123abc\nabc\n123\n123.0x\n";
+        let expected = "abc\n123\n123.0x\n123abc\n124\n";
+        for algorithm in [
+            SortAlgorithm::Auto,
+            SortAlgorithm::Mergesort,
+            SortAlgorithm::Quicksort,
+            SortAlgorithm::Radix,
+        ] {
+            let config = SortConfig::default()
+                .with_mode(SortMode::Numeric)
+                .with_algorithm(algorithm);
+            let sorted = crate::sort_bytes(&config, input.as_bytes()).unwrap();
+            assert_eq!(String::from_utf8_lossy(&sorted), expected, "{algorithm:?}");
+
+            // The cached comparisons of the SortableLine path
+            let sorter = CoreSort::new(crate::sort_args(&config, &[]), config);
+            let parsed = crate::zero_copy::parse_lines(input.as_bytes());
+            let mut lines: Vec<SortableLine> = parsed
+                .iter()
+                .enumerate()
+                .map(|(original_index, &line)| SortableLine {
+                    line,
+                    original_index,
+                })
+                .collect();
+            let cache = Arc::new(ComparisonCache::new(&parsed, &sorter.config));
+            sorter.sort_lines_with_cache(&mut lines, Some(&cache));
+            let mut output = Vec::new();
+            sorter.write_output(&lines, &mut output)?;
+            assert_eq!(String::from_utf8_lossy(&output), expected, "{algorithm:?}");
+        }
+
+        // The chunk order of the external sort
+        let temp_dir = TempDir::new()?;
+        let (input_file, output_file) = (temp_dir.path().join("in"), temp_dir.path().join("out"));
+        fs::write(&input_file, input)?;
+        ExternalSort::new(1, false, true, None)?.sort_file(
+            &input_file,
+            &output_file,
+            true,
+            false,
+        )?;
+        assert_eq!(fs::read_to_string(&output_file)?, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn test_three_way_quicksort_is_bounded() {
+        let sorter = CoreSort::new(SortArgs::default(), crate::config::SortConfig::default());
+        let sortable = |lines: &[Line]| -> Vec<SortableLine> {
+            lines
+                .iter()
+                .enumerate()
+                .map(|(original_index, &line)| SortableLine {
+                    line,
+                    original_index,
+                })
+                .collect()
+        };
+        let bytes_of = |lines: &[SortableLine]| -> Vec<Vec<u8>> {
+            lines
+                .iter()
+                .map(|l| unsafe { l.line.as_bytes() }.to_vec())
+                .collect()
+        };
+
+        // A million identical lines, then a million drawn from two values
+        let same = "dup\n".repeat(1_000_000);
+        let mut lines = sortable(&crate::zero_copy::parse_lines(same.as_bytes()));
+        sorter.three_way_quicksort_lines(&mut lines);
+        assert_eq!(lines.len(), 1_000_000);
+
+        use rand::Rng;
+        let mut rng = StdRng::seed_from_u64(7);
+        let two: String = (0..1_000_000)
+            .map(|_| if rng.gen::<bool>() { "b\n" } else { "a\n" })
+            .collect();
+        let mut lines = sortable(&crate::zero_copy::parse_lines(two.as_bytes()));
+        sorter.three_way_quicksort_lines(&mut lines);
+        let mut expected = bytes_of(&lines);
+        expected.sort();
+        assert_eq!(bytes_of(&lines), expected);
+
+        // Running out of depth hands the range to the merge sort
+        let mixed: String = (0..5_000)
+            .map(|_| format!("{}\n", rng.gen_range(0..50)))
+            .collect();
+        let parsed = crate::zero_copy::parse_lines(mixed.as_bytes());
+        let mut expected = bytes_of(&sortable(&parsed));
+        expected.sort();
+        for depth_limit in [0, 1, 3] {
+            let mut lines = sortable(&parsed);
+            sorter.three_way_quicksort_range(&mut lines, depth_limit);
+            assert_eq!(bytes_of(&lines), expected, "depth limit {depth_limit}");
+        }
+    }
+
+    #[test]
+    fn test_debug_warns_about_numeric_sort_on_text() {
+        use crate::config::{SortConfig, SortKey, SortMode};
+
+        let text = b"banana\napple\ncherry\n42\n";
+        let lines = crate::zero_copy::parse_lines(text);
+        let numeric = SortConfig::default().with_mode(SortMode::Numeric);
+        let sorter = CoreSort::new(SortArgs::default(), numeric.clone());
+        assert_eq!(
+            sorter.numeric_warnings(&lines),
+            ["-n is used, but 3 of 4 sampled lines do not start with a number"]
+        );
+
+        let numbers = crate::zero_copy::parse_lines(b"10\n-2.5\n .5\nx\n");
+        assert!(sorter.numeric_warnings(&numbers).is_empty());
+        let lexicographic = CoreSort::new(SortArgs::default(), SortConfig::default());
+        assert!(lexicographic.numeric_warnings(&lines).is_empty());
+
+        // A numeric key over a text field, spanning two fields; a key with
+        // no end field runs to the end of the line and spans them too
+        let keyed = SortConfig::default()
+            .add_key(SortKey::parse("1,2n").expect("Failed to parse key"))
+            .add_key(SortKey::parse("2n").expect("Failed to parse key"));
+        let lines = crate::zero_copy::parse_lines(b"a 1\nb 2\nc 3\n");
+        let sorter = CoreSort::new(SortArgs::default(), keyed);
+        assert_eq!(
+            sorter.numeric_warnings(&lines),
+            [
+                "key 1 is numeric and spans multiple fields",
+                "key 1 is numeric, but 3 of 3 sampled fields are not numbers",
+                "key 2 is numeric and spans multiple fields"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_count_on_partially_sorted_file() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        // Numerically out of order at 5>3, 10>9 and 20>2
+        fs::write(&input_file, "1\n2\n5\n3\n3\n10\n9\n20\n2\n")?;
+        let mapped_file = MappedFile::new(&input_file)?;
+
+        let numeric = crate::config::SortConfig::default()
+            .with_mode(crate::config::SortMode::Numeric)
+            .with_check_count(true);
+        let sorter = CoreSort::new(SortArgs::default(), numeric);
+        assert_eq!(sorter.count_disorders(mapped_file.lines()), 3);
+
+        // Byte order sees "5" > "3", "3" > "10", "9" > "20" and "20" > "2"
+        let lexicographic = CoreSort::new(SortArgs::default(), Default::default());
+        assert_eq!(lexicographic.count_disorders(mapped_file.lines()), 4);
+
+        let sorted = crate::zero_copy::parse_lines(b"a\nb\nb\nc\n");
+        assert_eq!(lexicographic.count_disorders(&sorted), 0);
+
+        // A stream past the budget is counted from its spilled copy
+        let stream = fs::read(&input_file)?;
+        assert_eq!(lexicographic.count_reader_disorders(&stream[..], 4)?, 4);
+        assert_eq!(
+            lexicographic.count_reader_disorders(&stream[..], 1 << 20)?,
+            4
+        );
+
+        // Disorder fails the sort with the total, for the caller to exit on
+        let files = vec![input_file.to_string_lossy().to_string(); 2];
+        let check_count = crate::config::SortConfig::default()
+            .with_check(true)
+            .with_check_count(true);
+        let args = crate::sort_args(&check_count, &files);
+        let error = CoreSort::new(args, check_count).sort().unwrap_err();
+        assert!(matches!(
+            crate::error::SortError::from(error),
+            crate::error::SortError::DisorderCounted { count: 8 }
+        ));
+
+        // Plain -c stops at the first disorder the same way
+        let check = crate::config::SortConfig::default().with_check(true);
+        let args = crate::sort_args(&check, &files[..1]);
+        let error = CoreSort::new(args, check).sort().unwrap_err();
+        assert!(matches!(
+            crate::error::SortError::from(error),
+            crate::error::SortError::NotSorted { line: 4, .. }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_low_cardinality_matches_full_sort() -> io::Result<()> {
+        let values = [
+            "pear", "apple", "10", "9", "Apple", "", "fig", "-1", "apple ", "b",
+        ];
+        let mut input = String::new();
+        for i in 0..1_000_000 {
+            input.push_str(values[(i * 7 + i / 3) % values.len()]);
+            input.push('\n');
+        }
+        let lines = crate::zero_copy::parse_lines(input.as_bytes());
+
+        for (mode, reverse) in [
+            (crate::config::SortMode::Lexicographic, false),
+            (crate::config::SortMode::Numeric, true),
+        ] {
+            let config = crate::config::SortConfig::default()
+                .with_mode(mode)
+                .with_reverse(reverse)
+                .with_unique(true);
+            let sorter = CoreSort::new(crate::sort_args(&config, &[]), config);
+            // Only byte-order sorts may hash whole lines to find duplicates
+            let fast_path = mode == crate::config::SortMode::Lexicographic;
+            assert_eq!(
+                sorter.distinct_lines_if_few(&lines).map(|d| d.len()),
+                fast_path.then_some(values.len())
+            );
+
+            // Expected output: sort everything, then drop adjacent duplicates
+            let mut expected: Vec<Line> = lines.clone();
+            expected.sort_by(|a, b| sorter.compare_lines_direct(a, b));
+            expected.dedup_by(|a, b| sorter.lines_equal_for_unique(a, b));
+            let mut expected_bytes = Vec::new();
+            sorter.write_output_direct(&expected, &mut expected_bytes)?;
+
+            let mut output = Vec::new();
+            sorter.sort_buffer(input.as_bytes(), &mut output)?;
+            assert_eq!(output, expected_bytes, "{mode:?} reverse={reverse}");
+        }
+
+        // Lines that differ in bytes may still be duplicates under -n or -f
+        for (flag, values, expected) in [
+            ("-nu", ["10", "010", "1e1"], "1e1\n10\n"),
+            ("-fu", ["apple", "Apple", "apple"], "apple\n"),
+        ] {
+            let input: String = values
+                .iter()
+                .cycle()
+                .take(3000)
+                .map(|v| format!("{v}\n"))
+                .collect();
+            let (config, _) = crate::config::SortConfig::from_args(&["sort", flag]).unwrap();
+            let sorted = crate::sort_bytes(&config, input.as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            assert_eq!(String::from_utf8_lossy(&sorted), expected, "{flag}");
+        }
+
+        // Mostly distinct input does not take the fast path
+        let distinct: String = (0..5000).map(|i| format!("{i}\n")).collect();
+        let sorter = CoreSort::new(SortArgs::default(), Default::default());
+        assert!(sorter
+            .distinct_lines_if_few(&crate::zero_copy::parse_lines(distinct.as_bytes()))
+            .is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_dedup_matches_sequential() {
+        // Long duplicate runs, many of them straddling partition edges
+        let mut input = String::new();
+        for i in 0..200_000u64 {
+            let value = (i / 37) % 3000;
+            input.push_str(&format!("{value} {}\n", if i % 5 == 0 { "x" } else { "y" }));
+        }
+        let lines = crate::zero_copy::parse_lines(input.as_bytes());
+
+        let key = crate::config::SortKey::parse("1,1n").unwrap();
+        for config in [
+            crate::config::SortConfig::default().with_unique(true),
+            crate::config::SortConfig::default()
+                .with_unique(true)
+                .add_key(key),
+        ] {
+            let sorter = CoreSort::new(crate::sort_args(&config, &[]), config);
+            let mut sorted = lines.clone();
+            sorted.sort_by(|a, b| sorter.compare_lines_direct(a, b));
+
+            let mut expected = sorted.clone();
+            expected.dedup_by(|a, b| sorter.lines_equal_for_unique(a, b));
+            let expected: Vec<&[u8]> = expected.iter().map(|l| unsafe { l.as_bytes() }).collect();
+
+            for partition_len in [1, 2, 37, 1000, 65_536, sorted.len()] {
+                let deduped = sorter.dedup_partitioned(&sorted, partition_len);
+                let deduped: Vec<&[u8]> = deduped.iter().map(|l| unsafe { l.as_bytes() }).collect();
+                assert_eq!(deduped, expected, "partition_len={partition_len}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_parallel_check_finds_first_disorder() {
+        let input: String = (0..50_000u64).map(|i| format!("{i}\n")).collect();
+        let mut lines = crate::zero_copy::parse_lines(input.as_bytes());
+        let config =
+            crate::config::SortConfig::default().with_mode(crate::config::SortMode::Numeric);
+        let sorter = CoreSort::new(crate::sort_args(&config, &[]), config);
+
+        assert_eq!(sorter.first_disorder_partitioned(&lines, 1000), None);
+        // Disorders inside, at the start of and across partition edges; the
+        // earliest is found whichever partition finishes first
+        let sequential = |lines: &[Line]| {
+            (1..lines.len()).find(|&i| !sorter.is_lines_in_order(&lines[i - 1], &lines[i]))
+        };
+        for swaps in [
+            vec![1],
+            vec![999, 30_000],
+            vec![1000, 17],
+            vec![45_678, 40_000],
+            vec![49_998],
+        ] {
+            let mut shuffled = lines.clone();
+            for &at in &swaps {
+                shuffled.swap(at, at + 1);
+            }
+            let expected = sequential(&shuffled);
+            assert!(expected.is_some());
+            for partition_len in [1, 7, 1000, 1001, 50_000] {
+                assert_eq!(
+                    sorter.first_disorder_partitioned(&shuffled, partition_len),
+                    expected,
+                    "swaps={swaps:?} partition_len={partition_len}"
+                );
+            }
+            assert_eq!(sorter.first_disorder(&shuffled), expected);
+        }
+        lines.truncate(1);
+        assert_eq!(sorter.first_disorder_partitioned(&lines, 1000), None);
+    }
+
+    #[test]
+    #[cfg(feature = "comparison-count")]
+    fn test_radix_sort_makes_fewer_comparisons() {
+        let input: String = (0..20_000u64)
+            .map(|i| format!("{}\n", (i * 7919) % 100_000))
+            .collect();
+
+        // Whole-line -n takes the integer radix sort; the same order as a
+        // numeric key goes through the comparison sort
+        let radix =
+            crate::config::SortConfig::default().with_mode(crate::config::SortMode::Numeric);
+        let keyed = crate::config::SortConfig::default()
+            .add_key(crate::config::SortKey::parse("1,1n").unwrap());
+        assert_eq!(
+            crate::sort_bytes(&radix, input.as_bytes()).unwrap(),
+            crate::sort_bytes(&keyed, input.as_bytes()).unwrap()
+        );
+        assert!(
+            radix.comparison_count() < keyed.comparison_count(),
+            "radix {} vs comparison {}",
+            radix.comparison_count(),
+            keyed.comparison_count()
+        );
+    }
+
+    #[test]
+    fn test_debug_check_detects_mismatched_comparator() -> io::Result<()> {
+        let config = crate::config::SortConfig::default()
+            .add_key(crate::config::SortKey::parse("2,2n").unwrap())
+            .with_check(true);
+        let sorter = CoreSort::new(crate::sort_args(&config, &[]), config);
+        let sorted = crate::zero_copy::parse_lines(b"x 2\nw 10\n");
+        let unsorted = crate::zero_copy::parse_lines(b"w 10\nx 2\n");
+
+        // The check's own comparator agrees with the sort
+        let in_order = |a: &Line, b: &Line| sorter.is_lines_in_order(a, b);
+        assert_eq!(sorter.check_agrees_with_sort(&sorted, in_order)?, None);
+        assert_eq!(sorter.check_agrees_with_sort(&unsorted, in_order)?, None);
+
+        // Whole-line byte order instead of the numeric key
+        let bytewise = |a: &Line, b: &Line| unsafe { a.as_bytes() <= b.as_bytes() };
+        assert_eq!(
+            sorter.check_agrees_with_sort(&sorted, bytewise)?.unwrap(),
+            "-c finds disorder at line 2, but sorting leaves the input as is"
+        );
+        assert_eq!(
+            sorter.check_agrees_with_sort(&unsorted, bytewise)?.unwrap(),
+            "-c finds the input sorted, but sorting changes it"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_stream_check_over_many_lines() -> io::Result<()> {
+        let config = crate::config::SortConfig::default().with_mode(crate::SortMode::Numeric);
+        let sorter = CoreSort::new(crate::sort_args(&config, &[]), config);
+
+        // Lines are read into reused buffers of varying length, some CRLF
+        let mut input = Vec::new();
+        for i in 0..50_000u64 {
+            let ending = if i % 3 == 0 { "\r\n" } else { "\n" };
+            write!(
+                input,
+                "{}{ending}",
+                "0".repeat((i % 7) as usize) + &i.to_string()
+            )?;
+        }
+        assert_eq!(sorter.check_reader_sorted(&input[..])?, Ok(()));
+
+        // Swap two lines near the end
+        let mut lines: Vec<&[u8]> = input.split_inclusive(|&b| b == b'\n').collect();
+        lines.swap(40_000, 40_001);
+        let swapped = lines.concat();
+        assert_eq!(sorter.check_reader_sorted(&swapped[..])?, Err(40_002));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_splits_records_on_nul_under_z() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        // In order as NUL-terminated records, out of order as lines
+        fs::write(&input_file, b"b\na\0c\0")?;
+
+        let config = crate::config::SortConfig::default().with_zero_terminated(true);
+        let sorter = CoreSort::new(crate::sort_args(&config, &[]), config);
+        assert_eq!(sorter.check_reader_sorted(&b"b\na\0c\0"[..])?, Ok(()));
+        assert_eq!(sorter.check_file_sorted_with_line(&input_file)?, Ok(()));
+        assert_eq!(sorter.check_reader_sorted(&b"c\0b\na\0"[..])?, Err(2));
+        Ok(())
+    }
+
+    #[test]
+    fn test_input_change_detection() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        fs::write(&input_file, "b\na\n")?;
+
+        let files = vec![input_file.to_string_lossy().to_string(), "-".to_string()];
+        assert!(CoreSort::snapshot_inputs(&files, Some(&files[0])).is_empty());
+        let snapshots = CoreSort::snapshot_inputs(&files, None);
+        assert_eq!(snapshots.len(), 1);
+        assert!(CoreSort::changed_input_warnings(&snapshots).is_empty());
+
+        // Shrinking the file is what makes mapped reads unsafe
+        fs::write(&input_file, "b\n")?;
+        let warnings = CoreSort::changed_input_warnings(&snapshots);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("input.txt"));
+        assert!(warnings[0].contains("changed during the sort"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_large_lexicographic_sort_uses_byte_order() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+
+        // Above the MSD radix threshold
+        let mut words: Vec<String> = (0..120_000u64)
+            .map(|i| format!("w{}", (i * 7919) % 50_000))
+            .collect();
+        fs::write(&input_file, words.join("\n") + "\n")?;
+        words.sort();
+
+        for reverse in [false, true] {
+            let output_file = temp_dir.path().join("output.txt");
+            let args = SortArgs {
+                files: vec![input_file.to_string_lossy().to_string()],
+                output: Some(output_file.to_string_lossy().to_string()),
+                reverse,
+                ..Default::default()
+            };
+            let config = crate::config::SortConfig::default().with_reverse(reverse);
+            let sorter = CoreSort::new(args, config);
+            assert!(sorter.is_plain_byte_order());
+            sorter.sort()?;
+
+            let mut expected = words.clone();
+            if reverse {
+                expected.reverse();
+            }
+            assert_eq!(
+                fs::read_to_string(&output_file)?,
+                expected.join("\n") + "\n"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "writes and sorts a 100MB file; run with --ignored"]
+    fn test_keyed_sort_of_file_above_external_threshold() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        // Just over the 100MB external sort threshold, keyed on field 2
+        let mut input = Vec::with_capacity(101 * 1024 * 1024);
+        let mut n = 0u64;
+        while input.len() <= 100 * 1024 * 1024 {
+            writeln!(input, "r{n:08} {} padding-padding", (n * 7919) % 1_000_003)?;
+            n += 1;
+        }
+        fs::write(&input_file, &input)?;
+        drop(input);
+
+        let config = crate::config::SortConfig::default()
+            .add_key(crate::config::SortKey::parse("2,2n").unwrap());
+        let args = SortArgs {
+            files: vec![input_file.to_string_lossy().to_string()],
+            output: Some(output_file.to_string_lossy().to_string()),
+            ..Default::default()
+        };
+        CoreSort::new(args, config).sort()?;
+
+        let key = |line: &str| -> (u64, String) {
+            let value = line.split(' ').nth(1).unwrap().parse().unwrap();
+            (value, line.to_string())
+        };
+        let output = fs::read_to_string(&output_file)?;
+        let mut count = 0u64;
+        let mut previous: Option<(u64, String)> = None;
+        for line in output.lines() {
+            let current = key(line);
+            if let Some(previous) = &previous {
+                assert!(previous <= &current, "{previous:?} before {current:?}");
+            }
+            previous = Some(current);
+            count += 1;
+        }
+        assert_eq!(count, n);
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "writes and sorts a 100MB file through gzip; run with --ignored"]
+    fn test_compress_program_on_file_above_external_threshold() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        let mut input = Vec::with_capacity(101 * 1024 * 1024);
+        let mut n = 0u64;
+        while input.len() <= 100 * 1024 * 1024 {
+            writeln!(input, "{:012}", (n * 7919) % 10_000_019)?;
+            n += 1;
+        }
+        fs::write(&input_file, &input)?;
+        drop(input);
+
+        let (config, files) = crate::config::SortConfig::from_args(&[
+            "sort",
+            "--compress-program=gzip",
+            "-o",
+            output_file.to_str().unwrap(),
+            input_file.to_str().unwrap(),
+        ])
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        assert_eq!(config.compress_program.as_deref(), Some("gzip"));
+        CoreSort::new(crate::sort_args(&config, &files), config).sort()?;
+
+        let output = fs::read_to_string(&output_file)?;
+        assert_eq!(output.lines().count() as u64, n);
+        assert!(output
+            .lines()
+            .zip(output.lines().skip(1))
+            .all(|(a, b)| a <= b));
+
+        Ok(())
+    }
 }
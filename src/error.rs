@@ -7,7 +7,7 @@ use thiserror::Error;
 #[derive(Error, Debug)]
 pub enum SortError {
     #[error("I/O error: {0}")]
-    Io(#[from] io::Error),
+    Io(#[source] io::Error),
 
     #[error("Permission denied: {file}")]
     PermissionDenied { file: String },
@@ -33,8 +33,19 @@ pub enum SortError {
     #[error("Memory allocation failed")]
     OutOfMemory,
 
-    #[error("Input is not sorted at line {line}")]
-    NotSorted { line: usize },
+    #[error("{file}:{line}: disorder")]
+    NotSorted { file: String, line: usize },
+
+    /// `--check-count` found `count` out-of-order lines in all; the count
+    /// for each input has already been reported
+    #[error("{count} out-of-order lines")]
+    DisorderCounted { count: usize },
+
+    #[error("line {line}: missing field {field} (--strict-fields)")]
+    MissingField { line: usize, field: usize },
+
+    #[error("line {line}: longer than {limit} bytes (--max-line-length)")]
+    LineTooLong { line: usize, limit: usize },
 
     #[error("Merge operation failed: {message}")]
     MergeFailed { message: String },
@@ -45,6 +56,12 @@ pub enum SortError {
     #[error("UTF-8 encoding error: {0}")]
     Utf8Error(#[from] std::string::FromUtf8Error),
 
+    #[error("{0}")]
+    Usage(#[from] clap::Error),
+
+    #[error("{0}")]
+    Version(String),
+
     #[error("Parse error: {message}")]
     ParseError { message: String },
 
@@ -59,9 +76,14 @@ impl SortError {
             SortError::PermissionDenied { .. }
             | SortError::FileNotFound { .. }
             | SortError::IsDirectory { .. }
-            | SortError::Io(_) => crate::SORT_FAILURE,
+            | SortError::Io(_)
+            | SortError::Usage(_)
+            | SortError::MissingField { .. }
+            | SortError::LineTooLong { .. } => crate::SORT_FAILURE,
 
-            SortError::NotSorted { .. } => crate::EXIT_FAILURE,
+            SortError::NotSorted { .. } | SortError::DisorderCounted { .. } => crate::EXIT_FAILURE,
+
+            SortError::Version(_) => crate::EXIT_SUCCESS,
 
             _ => crate::EXIT_FAILURE,
         }
@@ -116,9 +138,12 @@ impl SortError {
         }
     }
 
-    /// Create a not sorted error
-    pub fn not_sorted(line: usize) -> Self {
-        SortError::NotSorted { line }
+    /// Create a not sorted error for line `line` of `file`
+    pub fn not_sorted(file: &str, line: usize) -> Self {
+        SortError::NotSorted {
+            file: file.to_string(),
+            line,
+        }
     }
 
     /// Create a merge failed error
@@ -148,6 +173,29 @@ impl SortError {
             message: message.to_string(),
         }
     }
+
+    /// Create a missing field error for `--strict-fields`
+    pub fn missing_field(line: usize, field: usize) -> Self {
+        SortError::MissingField { line, field }
+    }
+
+    /// Create an over-long line error for `--max-line-length`
+    pub fn line_too_long(line: usize, limit: usize) -> Self {
+        SortError::LineTooLong { line, limit }
+    }
+}
+
+impl From<io::Error> for SortError {
+    /// A `SortError` raised inside I/O code comes back out unchanged
+    fn from(err: io::Error) -> Self {
+        if !err.get_ref().is_some_and(|inner| inner.is::<SortError>()) {
+            return SortError::Io(err);
+        }
+        match err.into_inner().map(|inner| inner.downcast::<SortError>()) {
+            Some(Ok(inner)) => *inner,
+            _ => unreachable!("payload checked above"),
+        }
+    }
 }
 
 /// Convert io::Error to SortError with context (removed to avoid conflict with thiserror derive)
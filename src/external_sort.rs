@@ -1,6 +1,8 @@
+use crate::config::SortConfig;
+use crate::numeric::parse_integer;
 use crate::radix_sort::RadixSort;
 use crate::simd_compare::SIMDCompare;
-use crate::zero_copy::{Line, MappedFile};
+use crate::zero_copy::{check_input_lines, Line, MappedFile};
 use rayon::prelude::*;
 use std::cmp::Ordering;
 /// External sorting implementation for very large datasets
@@ -8,18 +10,30 @@ use std::cmp::Ordering;
 use std::fs::File;
 use std::io::{self, BufRead, BufReader, BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
 use tempfile::TempDir;
 
+/// Smallest chunk a memory budget is split into for more threads
+const MIN_CHUNK_SIZE: usize = 1024 * 1024;
+
 /// External sorter for handling very large datasets efficiently
 pub struct ExternalSort {
-    /// Maximum chunk size in memory (bytes)
+    /// Memory budget (bytes): inputs up to this size are sorted in memory,
+    /// larger ones in chunks of [`Self::chunk_size`]
     max_chunk_size: usize,
     /// Whether to use parallel processing
     parallel: bool,
     /// Whether to use radix sort for numeric data
     use_radix: bool,
     /// Temporary directory for chunk files
-    temp_dir: TempDir,
+    temp_dir: crate::temp::Temporary<TempDir>,
+    /// Options for keyed (`-k`) comparisons
+    config: Option<SortConfig>,
+    /// Program that compresses chunk files; run with `-d` to decompress
+    compress_program: Option<String>,
+    /// Threads (`--parallel`) that chunks are sized for and that merge
+    /// groups of chunks at once
+    merge_threads: usize,
 }
 
 impl ExternalSort {
@@ -33,22 +47,79 @@ impl ExternalSort {
         let max_chunk_size = max_memory_mb * 1024 * 1024; // Convert MB to bytes
 
         // Create temp directory in specified location or use default
-        let temp_dir = if let Some(path) = temp_dir_path {
-            tempfile::tempdir_in(path)?
-        } else if let Ok(tmpdir) = std::env::var("TMPDIR") {
-            tempfile::tempdir_in(tmpdir)?
-        } else {
-            tempfile::tempdir()?
-        };
+        let temp_dir = crate::temp::create_temp_dir(temp_dir_path)?;
 
         Ok(Self {
             max_chunk_size,
             parallel,
             use_radix,
             temp_dir,
+            config: None,
+            compress_program: None,
+            merge_threads: 1,
         })
     }
 
+    /// Size chunks for `threads`, and merge up to `threads` groups of chunks
+    /// in parallel before the final merge
+    pub fn with_merge_threads(mut self, threads: usize) -> Self {
+        self.merge_threads = threads;
+        self
+    }
+
+    /// Compress chunk files with `program` (`--compress-program`)
+    pub fn with_compress_program(mut self, program: Option<String>) -> Self {
+        self.compress_program = program;
+        self
+    }
+
+    /// Compare lines as `config` does when it has keys or `-s`, instead of
+    /// whole lines
+    pub fn with_config(mut self, config: SortConfig) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Bytes read into each chunk: the memory budget split between the
+    /// threads, so that an input just over the budget still yields a chunk
+    /// per thread, but no smaller than 1MB (or the whole budget) so the merge
+    /// stays short
+    fn chunk_size(&self) -> usize {
+        let per_thread = self.max_chunk_size / self.merge_threads.max(1);
+        per_thread.max(MIN_CHUNK_SIZE.min(self.max_chunk_size))
+    }
+
+    /// Line terminator of the input, the chunks and the output: NUL under
+    /// `-z`, else newline
+    fn terminator(&self) -> u8 {
+        match &self.config {
+            Some(config) if config.zero_terminated => b'\0',
+            _ => b'\n',
+        }
+    }
+
+    /// Apply the `--max-line-length` and `--strict-fields` checks of the
+    /// configuration, if any
+    fn check_input_lines(
+        &self,
+        lines: impl IntoIterator<Item = Line>,
+        first_line: usize,
+    ) -> io::Result<()> {
+        match &self.config {
+            Some(config) => check_input_lines(lines, first_line, config),
+            None => Ok(()),
+        }
+    }
+
+    /// Order used for chunk sorting and the merge
+    fn order(&self, numeric: bool) -> ChunkOrder<'_> {
+        match &self.config {
+            Some(config) if !is_plain_whole_line_order(config) => ChunkOrder::Config(config),
+            _ if numeric => ChunkOrder::Numeric,
+            _ => ChunkOrder::Bytes,
+        }
+    }
+
     /// Main external sort entry point
     pub fn sort_file(
         &self,
@@ -66,7 +137,7 @@ impl ExternalSort {
         }
 
         // Step 2: Split file into sorted chunks
-        let chunk_files = self.create_sorted_chunks(input_path, numeric)?;
+        let chunk_files = self.create_sorted_chunks(input_path, numeric, unique)?;
 
         // Step 3: Merge sorted chunks
         self.merge_sorted_chunks(&chunk_files, output_path, numeric, unique)?;
@@ -82,37 +153,55 @@ impl ExternalSort {
         numeric: bool,
         unique: bool,
     ) -> io::Result<()> {
-        let mapped_file = MappedFile::new(input_path)?;
+        let mapped_file = MappedFile::with_terminator(input_path, self.terminator())?;
         let lines = mapped_file.lines();
+        self.check_input_lines(lines.iter().copied(), 1)?;
 
         let mut simple_lines: Vec<Line> = lines.to_vec();
+        let order = self.order(numeric);
 
-        if numeric && self.use_radix {
+        if let ChunkOrder::Config(_) = order {
+            // Stable, so that `-s` keeps key-equal lines in input order
+            let compare = |a: &Line, b: &Line| unsafe { order.compare(a.as_bytes(), b.as_bytes()) };
+            if self.parallel && simple_lines.len() > 10000 {
+                simple_lines.par_sort_by(compare);
+            } else {
+                simple_lines.sort_by(compare);
+            }
+        } else if numeric && self.use_radix {
             let radix_sorter = RadixSort::new(self.parallel);
             radix_sorter.sort_numeric_lines(&mut simple_lines);
+
+            // Equal values are ordered by the whole line
+            radix_sorter.order_equal_values_bytewise(&mut simple_lines);
         } else if self.parallel && simple_lines.len() > 10000 {
             if numeric {
-                simple_lines.par_sort_unstable_by(|a, b| a.compare_numeric(b));
+                simple_lines.par_sort_unstable_by(|a, b| unsafe {
+                    compare_chunk_lines(a.as_bytes(), b.as_bytes(), true)
+                });
             } else {
                 simple_lines.par_sort_unstable_by(|a, b| a.compare_lexicographic(b));
             }
         } else if numeric {
-            simple_lines.sort_unstable_by(|a, b| a.compare_numeric(b));
+            simple_lines.sort_unstable_by(|a, b| unsafe {
+                compare_chunk_lines(a.as_bytes(), b.as_bytes(), true)
+            });
         } else {
             simple_lines.sort_unstable_by(|a, b| a.compare_lexicographic(b));
         }
 
         // Remove duplicates if unique mode
         if unique {
-            simple_lines.dedup_by(|a, b| unsafe { a.as_bytes() == b.as_bytes() });
+            simple_lines.dedup_by(|a, b| unsafe { order.is_duplicate(b.as_bytes(), a.as_bytes()) });
         }
 
         // Write sorted output
         let mut output = BufWriter::new(File::create(output_path)?);
+        let terminator = [self.terminator()];
         for line in &simple_lines {
             unsafe {
                 output.write_all(line.as_bytes())?;
-                output.write_all(b"\n")?;
+                output.write_all(&terminator)?;
             }
         }
         output.flush()?;
@@ -120,12 +209,19 @@ impl ExternalSort {
         Ok(())
     }
 
-    /// Create sorted chunks from large input file
-    fn create_sorted_chunks(&self, input_path: &Path, numeric: bool) -> io::Result<Vec<PathBuf>> {
+    /// Create sorted chunks from large input file; under `-u` each chunk is
+    /// deduplicated so a single chunk can be copied to the output as is
+    fn create_sorted_chunks(
+        &self,
+        input_path: &Path,
+        numeric: bool,
+        unique: bool,
+    ) -> io::Result<Vec<PathBuf>> {
         let file = File::open(input_path)?;
         let mut reader = BufReader::new(file);
         let mut chunk_files = Vec::new();
         let mut chunk_number = 0;
+        let mut line_number = 1;
 
         loop {
             // Read chunk of lines that fits in memory
@@ -133,9 +229,18 @@ impl ExternalSort {
             if lines.is_empty() {
                 break;
             }
+            self.check_input_lines(
+                lines.iter().map(|line| Line::new(line.as_bytes())),
+                line_number,
+            )?;
+            line_number += lines.len();
 
             // Sort the chunk
-            let sorted_lines = self.sort_chunk(lines, numeric)?;
+            let mut sorted_lines = self.sort_chunk(lines, numeric)?;
+            if unique {
+                let order = self.order(numeric);
+                sorted_lines.dedup_by(|a, b| order.is_duplicate(b.as_bytes(), a.as_bytes()));
+            }
 
             // Write sorted chunk to temporary file
             let chunk_path = self.write_chunk_to_file(&sorted_lines, chunk_number)?;
@@ -155,25 +260,24 @@ impl ExternalSort {
         let mut lines = Vec::new();
         let mut total_size = 0;
         let mut line = String::new();
+        let terminator = self.terminator();
 
         // Pre-allocate capacity for better performance
-        lines.reserve(self.max_chunk_size / 20); // Estimate ~20 chars per line
+        let chunk_size = self.chunk_size();
+        lines.reserve(chunk_size / 20); // Estimate ~20 chars per line
 
-        while total_size < self.max_chunk_size {
+        while total_size < chunk_size {
             line.clear();
-            let bytes_read = reader.read_line(&mut line)?;
+            let bytes_read = read_record(reader, terminator, &mut line)?;
 
             if bytes_read == 0 {
                 // EOF reached
                 return Ok((lines, true));
             }
 
-            // Remove trailing newline
-            if line.ends_with('\n') {
+            // Drop the CR of a CRLF ending
+            if terminator == b'\n' && line.ends_with('\r') {
                 line.pop();
-                if line.ends_with('\r') {
-                    line.pop();
-                }
             }
 
             total_size += line.len();
@@ -188,9 +292,16 @@ impl ExternalSort {
         // For large chunks, always prefer parallel sorting
         const LARGE_CHUNK_THRESHOLD: usize = 50_000;
 
-        if numeric && self.use_radix && self.is_all_simple_integers(&lines) {
-            // Use radix sort for simple integers
-            self.radix_sort_strings(&mut lines)?;
+        let order = self.order(numeric);
+        if let ChunkOrder::Config(_) = order {
+            // Stable, so that `-s` keeps key-equal lines in input order
+            if self.parallel && lines.len() > 10_000 {
+                lines.par_sort_by(|a, b| order.compare(a.as_bytes(), b.as_bytes()));
+            } else {
+                lines.sort_by(|a, b| order.compare(a.as_bytes(), b.as_bytes()));
+            }
+        } else if numeric && self.use_radix && self.radix_sort_strings(&mut lines) {
+            // Sorted by the radix sort for simple integers
         } else {
             // Use optimized comparison-based sort
             if self.parallel && lines.len() > LARGE_CHUNK_THRESHOLD {
@@ -226,33 +337,30 @@ impl ExternalSort {
         Ok(lines)
     }
 
-    /// Check if all strings are simple integers
-    fn is_all_simple_integers(&self, lines: &[String]) -> bool {
-        // Sample first 100 lines to determine if all are simple integers
-        let sample_size = lines.len().min(100);
-        lines[..sample_size].iter().all(|line| {
-            SIMDCompare::is_all_digits_simd(line.as_bytes())
-                || (line.starts_with('-') && SIMDCompare::is_all_digits_simd(&line.as_bytes()[1..]))
-        })
-    }
-
-    /// Radix sort for string integers
-    fn radix_sort_strings(&self, lines: &mut [String]) -> io::Result<()> {
+    /// Radix sort for string integers; returns false, leaving `lines` as
+    /// they were, at the first line that is not a plain integer as `-n`
+    /// reads it
+    fn radix_sort_strings(&self, lines: &mut [String]) -> bool {
         // Convert to (value, index) pairs
-        let mut values: Vec<(i64, usize)> = lines
+        let values: Option<Vec<(i64, usize)>> = lines
             .iter()
             .enumerate()
-            .map(|(idx, line)| {
-                let value = line.parse::<i64>().unwrap_or(0);
-                (value, idx)
-            })
+            .map(|(idx, line)| Some((parse_integer(line.as_bytes())?, idx)))
             .collect();
+        let Some(mut values) = values else {
+            return false;
+        };
 
-        // Sort by value
+        // Sort by value, equal values by their bytes as `compare_chunk_lines`
+        // orders them
+        let by_value_then_bytes = |a: &(i64, usize), b: &(i64, usize)| {
+            a.0.cmp(&b.0)
+                .then_with(|| lines[a.1].as_bytes().cmp(lines[b.1].as_bytes()))
+        };
         if self.parallel {
-            values.par_sort_unstable_by_key(|(value, _)| *value);
+            values.par_sort_unstable_by(by_value_then_bytes);
         } else {
-            values.sort_unstable_by_key(|(value, _)| *value);
+            values.sort_unstable_by(by_value_then_bytes);
         }
 
         // Reconstruct lines in sorted order
@@ -274,176 +382,186 @@ impl ExternalSort {
             lines[i] = line;
         }
 
-        Ok(())
+        true
     }
 
     /// Compare numeric strings efficiently
     fn compare_numeric_strings(&self, a: &str, b: &str) -> Ordering {
-        // Fast path for simple integers
-        if let (Ok(a_num), Ok(b_num)) = (a.parse::<i64>(), b.parse::<i64>()) {
-            return a_num.cmp(&b_num);
-        }
-
-        // Fall back to byte-level numeric comparison
-        self.compare_numeric_bytes(a.as_bytes(), b.as_bytes())
+        compare_chunk_lines(a.as_bytes(), b.as_bytes(), true)
     }
 
-    /// Byte-level numeric comparison
-    fn compare_numeric_bytes(&self, a: &[u8], b: &[u8]) -> Ordering {
-        // Skip leading whitespace
-        let a = self.skip_whitespace(a);
-        let b = self.skip_whitespace(b);
-
-        // Handle empty strings
-        match (a.is_empty(), b.is_empty()) {
-            (true, true) => return Ordering::Equal,
-            (true, false) => return Ordering::Less,
-            (false, true) => return Ordering::Greater,
-            _ => {}
-        }
-
-        // Extract signs
-        let (a_negative, a_digits) = self.extract_sign(a);
-        let (b_negative, b_digits) = self.extract_sign(b);
-
-        // Compare signs
-        match (a_negative, b_negative) {
-            (false, true) => return Ordering::Greater,
-            (true, false) => return Ordering::Less,
-            _ => {}
-        }
-
-        // Compare magnitudes
-        let magnitude_cmp = self.compare_magnitude(a_digits, b_digits);
+    /// Write sorted chunk to temporary file
+    fn write_chunk_to_file(&self, lines: &[String], chunk_number: usize) -> io::Result<PathBuf> {
+        self.write_temp_file(&format!("chunk_{chunk_number:06}.txt"), |writer| {
+            let mut writer = BufWriter::new(writer);
+            let terminator = [self.terminator()];
+            for line in lines {
+                writer.write_all(line.as_bytes())?;
+                writer.write_all(&terminator)?;
+            }
+            writer.flush()
+        })
+    }
 
-        if a_negative {
-            magnitude_cmp.reverse()
-        } else {
-            magnitude_cmp
+    /// Create the temporary file `name` with the contents `write` produces,
+    /// through the compress program if there is one
+    fn write_temp_file(
+        &self,
+        name: &str,
+        write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+    ) -> io::Result<PathBuf> {
+        let path = self.temp_dir.path().join(name);
+        let mut file = File::create(&path)?;
+
+        match &self.compress_program {
+            None => write(&mut file)?,
+            Some(program) => {
+                let mut child = Command::new(program)
+                    .stdin(Stdio::piped())
+                    .stdout(file)
+                    .spawn()
+                    .map_err(|e| compress_program_error(program, e))?;
+                let mut stdin = child.stdin.take().expect("stdin is piped");
+                write(&mut stdin)?;
+                drop(stdin);
+                wait_for_compress_program(program, child)?;
+            }
         }
-    }
 
-    fn skip_whitespace<'a>(&self, bytes: &'a [u8]) -> &'a [u8] {
-        let start = bytes
-            .iter()
-            .position(|&b| !b.is_ascii_whitespace())
-            .unwrap_or(bytes.len());
-        &bytes[start..]
+        Ok(path)
     }
 
-    fn extract_sign<'a>(&self, bytes: &'a [u8]) -> (bool, &'a [u8]) {
-        if bytes.starts_with(b"-") {
-            (true, &bytes[1..])
-        } else if bytes.starts_with(b"+") {
-            (false, &bytes[1..])
-        } else {
-            (false, bytes)
+    /// Open a chunk file for reading, through `PROG -d` when chunks are
+    /// compressed; the child, if any, must be waited for once it is drained
+    fn open_chunk(&self, path: &Path) -> io::Result<(Box<dyn BufRead>, Option<Child>)> {
+        let file = File::open(path)?;
+        match &self.compress_program {
+            None => Ok((Box::new(BufReader::new(file)), None)),
+            Some(program) => {
+                let mut child = Command::new(program)
+                    .arg("-d")
+                    .stdin(file)
+                    .stdout(Stdio::piped())
+                    .spawn()
+                    .map_err(|e| compress_program_error(program, e))?;
+                let stdout = child.stdout.take().expect("stdout is piped");
+                Ok((Box::new(BufReader::new(stdout)), Some(child)))
+            }
         }
     }
 
-    fn compare_magnitude(&self, a: &[u8], b: &[u8]) -> Ordering {
-        // Remove leading zeros
-        let a = self.skip_leading_zeros(a);
-        let b = self.skip_leading_zeros(b);
-
-        // Compare lengths first (longer number is bigger)
-        match a.len().cmp(&b.len()) {
-            Ordering::Equal => a.cmp(b), // Same length, compare lexicographically
-            other => other,
+    /// Merge sorted chunks using k-way merge; with several merge threads,
+    /// groups of chunks are merged in parallel first
+    fn merge_sorted_chunks(
+        &self,
+        chunk_files: &[PathBuf],
+        output_path: &Path,
+        numeric: bool,
+        unique: bool,
+    ) -> io::Result<()> {
+        if chunk_files.is_empty() {
+            return Ok(());
         }
-    }
 
-    fn skip_leading_zeros<'a>(&self, bytes: &'a [u8]) -> &'a [u8] {
-        let start = bytes.iter().position(|&b| b != b'0').unwrap_or(bytes.len());
-        if start == bytes.len() {
-            b"0" // All zeros, return single zero
-        } else {
-            &bytes[start..]
+        if chunk_files.len() == 1 && self.compress_program.is_none() {
+            // Single chunk, just copy it
+            std::fs::copy(&chunk_files[0], output_path)?;
+            return Ok(());
         }
-    }
 
-    /// Write sorted chunk to temporary file
-    fn write_chunk_to_file(&self, lines: &[String], chunk_number: usize) -> io::Result<PathBuf> {
-        let chunk_path = self
-            .temp_dir
-            .path()
-            .join(format!("chunk_{chunk_number:06}.txt"));
-        let mut writer = BufWriter::new(File::create(&chunk_path)?);
+        let runs = self.merge_groups_in_parallel(chunk_files, numeric, unique)?;
+        let mut output = File::create(output_path)?;
+        self.merge_chunks_to(&runs, &mut output, numeric, unique)
+    }
 
-        for line in lines {
-            writeln!(writer, "{line}")?;
+    /// First pass of a parallel merge: split the chunks into one group of
+    /// consecutive chunks per merge thread and merge each group into a
+    /// temporary file of its own. The groups keep the chunks in input order,
+    /// so the final merge still breaks ties, and picks `-u` survivors, by
+    /// input position. Returns the chunks as they are when a pass would not pay.
+    fn merge_groups_in_parallel(
+        &self,
+        chunk_files: &[PathBuf],
+        numeric: bool,
+        unique: bool,
+    ) -> io::Result<Vec<PathBuf>> {
+        let threads = self.merge_threads.max(1);
+        let group_len = (chunk_files.len() + threads - 1) / threads;
+        if threads < 2 || group_len < 2 {
+            return Ok(chunk_files.to_vec());
         }
-        writer.flush()?;
 
-        Ok(chunk_path)
+        chunk_files
+            .par_chunks(group_len)
+            .enumerate()
+            .map(|(index, group)| {
+                let run = self.write_temp_file(&format!("merge_{index:06}.txt"), |writer| {
+                    self.merge_chunks_to(group, writer, numeric, unique)
+                })?;
+                // The group's chunks are no longer needed; free the disk space now
+                for path in group {
+                    std::fs::remove_file(path)?;
+                }
+                Ok(run)
+            })
+            .collect()
     }
 
-    /// Merge sorted chunks using k-way merge
-    fn merge_sorted_chunks(
+    /// K-way merge of sorted chunk files into `output`
+    fn merge_chunks_to(
         &self,
         chunk_files: &[PathBuf],
-        output_path: &Path,
-        _numeric: bool,
+        output: &mut dyn Write,
+        numeric: bool,
         unique: bool,
     ) -> io::Result<()> {
         use std::cmp::Reverse;
         use std::collections::BinaryHeap;
 
-        if chunk_files.is_empty() {
-            return Ok(());
-        }
-
-        if chunk_files.len() == 1 {
-            // Single chunk, just copy it
-            std::fs::copy(&chunk_files[0], output_path)?;
-            return Ok(());
+        // Open all chunk files
+        let mut readers: Vec<Box<dyn BufRead>> = Vec::with_capacity(chunk_files.len());
+        let mut decompressors = Vec::new();
+        for path in chunk_files {
+            let (reader, child) = self.open_chunk(path)?;
+            readers.push(reader);
+            decompressors.extend(child);
         }
 
-        // Open all chunk files
-        let mut readers: Vec<BufReader<File>> = chunk_files
-            .iter()
-            .map(|path| File::open(path).map(BufReader::new))
-            .collect::<Result<Vec<_>, _>>()?;
+        let mut output = BufWriter::new(output);
 
-        let mut output = BufWriter::new(File::create(output_path)?);
+        let order = self.order(numeric);
+        let terminator = self.terminator();
 
         // Priority queue for k-way merge
-        #[derive(Debug)]
-        struct MergeItem {
+        struct MergeItem<'a> {
             line: String,
             reader_index: usize,
+            order: ChunkOrder<'a>,
         }
 
-        impl PartialEq for MergeItem {
+        impl PartialEq for MergeItem<'_> {
             fn eq(&self, other: &Self) -> bool {
                 self.line == other.line
             }
         }
 
-        impl Eq for MergeItem {}
+        impl Eq for MergeItem<'_> {}
 
-        impl PartialOrd for MergeItem {
+        impl PartialOrd for MergeItem<'_> {
             fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
                 Some(self.cmp(other))
             }
         }
 
-        impl Ord for MergeItem {
+        impl Ord for MergeItem<'_> {
             fn cmp(&self, other: &Self) -> Ordering {
-                // Simple lexicographic comparison (reversed for min-heap)
-                self.line.cmp(&other.line).reverse()
-            }
-        }
-
-        impl MergeItem {
-            #[allow(dead_code)]
-            fn compare_numeric(&self, other: &str) -> Ordering {
-                // Fast path for simple integers
-                if let (Ok(a), Ok(b)) = (self.line.parse::<i64>(), other.parse::<i64>()) {
-                    return a.cmp(&b);
-                }
-                // Fall back to string comparison
-                self.line.cmp(&other.to_string())
+                // Natural order; the heap wraps items in `Reverse` to pop the smallest.
+                // Chunks hold consecutive runs of the input sorted stably, so
+                // (chunk, position in chunk) follows input order: ties go to the
+                // earlier chunk and `-s -u` keeps the first occurrence
+                self.order
+                    .compare(self.line.as_bytes(), other.line.as_bytes())
+                    .then_with(|| self.reader_index.cmp(&other.reader_index))
             }
         }
 
@@ -452,13 +570,11 @@ impl ExternalSort {
         // Initialize heap with first line from each reader
         for (idx, reader) in readers.iter_mut().enumerate() {
             let mut line = String::new();
-            if reader.read_line(&mut line)? > 0 {
-                if line.ends_with('\n') {
-                    line.pop();
-                }
+            if read_record(reader, terminator, &mut line)? > 0 {
                 heap.push(Reverse(MergeItem {
                     line,
                     reader_index: idx,
+                    order,
                 }));
             }
         }
@@ -469,17 +585,15 @@ impl ExternalSort {
             // If unique mode, skip duplicates
             if unique {
                 if let Some(ref prev) = last_line {
-                    if prev == &item.line {
+                    if order.is_duplicate(prev, &item.line) {
                         // Skip duplicate, but still read next line from same reader
                         let reader_idx = item.reader_index;
                         let mut line = String::new();
-                        if readers[reader_idx].read_line(&mut line)? > 0 {
-                            if line.ends_with('\n') {
-                                line.pop();
-                            }
+                        if read_record(&mut readers[reader_idx], terminator, &mut line)? > 0 {
                             heap.push(Reverse(MergeItem {
                                 line,
                                 reader_index: reader_idx,
+                                order,
                             }));
                         }
                         continue;
@@ -488,27 +602,145 @@ impl ExternalSort {
                 last_line = Some(item.line.clone());
             }
 
-            writeln!(output, "{}", item.line)?;
+            output.write_all(item.line.as_bytes())?;
+            output.write_all(&[terminator])?;
 
             // Read next line from the same reader
             let reader_idx = item.reader_index;
             let mut line = String::new();
-            if readers[reader_idx].read_line(&mut line)? > 0 {
-                if line.ends_with('\n') {
-                    line.pop();
-                }
+            if read_record(&mut readers[reader_idx], terminator, &mut line)? > 0 {
                 heap.push(Reverse(MergeItem {
                     line,
                     reader_index: reader_idx,
+                    order,
                 }));
             }
         }
 
         output.flush()?;
+        if let Some(program) = &self.compress_program {
+            for child in decompressors {
+                wait_for_compress_program(program, child)?;
+            }
+        }
         Ok(())
     }
 }
 
+/// Read one line ending in `terminator` into `line`, without the
+/// terminator; like `read_line`, the line must be UTF-8. Returns the bytes
+/// consumed, 0 at the end of the input.
+fn read_record(reader: &mut impl BufRead, terminator: u8, line: &mut String) -> io::Result<usize> {
+    let mut bytes = std::mem::take(line).into_bytes();
+    let read = reader.read_until(terminator, &mut bytes)?;
+    if bytes.last() == Some(&terminator) {
+        bytes.pop();
+    }
+    *line = String::from_utf8(bytes).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            "stream did not contain valid UTF-8",
+        )
+    })?;
+    Ok(read)
+}
+
+/// Name the compress program that could not be started
+fn compress_program_error(program: &str, err: io::Error) -> io::Error {
+    io::Error::new(
+        err.kind(),
+        format!("couldn't execute compress program '{program}': {err}"),
+    )
+}
+
+/// Wait for a compress or decompress child and fail if it did not succeed
+fn wait_for_compress_program(program: &str, mut child: Child) -> io::Result<()> {
+    let status = child.wait()?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!("compress program '{program}' failed: {status}"),
+        ))
+    }
+}
+
+/// How chunk lines are ordered
+#[derive(Clone, Copy)]
+enum ChunkOrder<'a> {
+    /// Whole lines, bytewise
+    Bytes,
+    /// Whole lines, `-n`
+    Numeric,
+    /// The full comparison of the configuration: its `-k` keys and
+    /// `--ignore-chars`, lines cut short by `--long-lines=truncate`, and
+    /// under `-s` no whole-line tie-break
+    Config(&'a SortConfig),
+}
+
+impl ChunkOrder<'_> {
+    fn compare(self, a: &[u8], b: &[u8]) -> Ordering {
+        match self {
+            ChunkOrder::Bytes => compare_chunk_lines(a, b, false),
+            ChunkOrder::Numeric => compare_chunk_lines(a, b, true),
+            ChunkOrder::Config(config) => Line::new(a).compare_with_keys(
+                &Line::new(b),
+                &config.keys,
+                config.field_separator,
+                config,
+            ),
+        }
+    }
+
+    /// Whether `-u` drops `next` as a repeat of `kept`: the two compare
+    /// equal on everything they are sorted by, as in the in-memory sort
+    fn is_duplicate(self, kept: impl AsRef<[u8]>, next: impl AsRef<[u8]>) -> bool {
+        let (kept, next) = (kept.as_ref(), next.as_ref());
+        match self {
+            ChunkOrder::Config(_) => self.compare(kept, next) == Ordering::Equal,
+            _ => kept == next,
+        }
+    }
+}
+
+/// Whether `config` orders whole lines exactly as [`ChunkOrder::Bytes`] or
+/// [`ChunkOrder::Numeric`] do: plain text or `-n` with no keys, no option
+/// that changes the comparison or its direction, and no locale collation.
+/// `-n -u` drops numerically equal lines, which `Numeric` keeps apart.
+fn is_plain_whole_line_order(config: &SortConfig) -> bool {
+    let plain_mode = match config.mode {
+        crate::config::SortMode::Lexicographic => true,
+        crate::config::SortMode::Numeric => !config.unique,
+        _ => false,
+    };
+    plain_mode
+        && config.keys.is_empty()
+        && !config.reverse
+        && !config.stable
+        && !config.ignore_case
+        && !config.dictionary_order
+        && !config.ignore_leading_blanks
+        && !config.ignore_nonprinting
+        && !config.accounting
+        && config.ignore_chars.is_none()
+        && config.comparison_limit().is_none()
+        && !config.collates()
+}
+
+/// Order shared by chunk sorting and the merge. Numeric lines compare like
+/// the in-memory `-n` sort, falling back to bytes so that identical lines
+/// end up adjacent for `-u`.
+fn compare_chunk_lines(a: &[u8], b: &[u8], numeric: bool) -> Ordering {
+    if numeric {
+        Line::new(a)
+            .compare_numeric(&Line::new(b))
+            .then_with(|| a.cmp(b))
+    } else {
+        a.cmp(b)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -534,4 +766,471 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_external_sort_merges_chunks_in_order() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        let input: String = (0..500).rev().map(|n| format!("{n}\n")).collect();
+        fs::write(&input_file, &input)?;
+
+        // Force several chunks so the k-way merge is exercised
+        let mut sorter = ExternalSort::new(1, false, false, None)?;
+        sorter.max_chunk_size = 256;
+        sorter.sort_file(&input_file, &output_file, true, false)?;
+
+        let expected: String = (0..500).map(|n| format!("{n}\n")).collect();
+        assert_eq!(fs::read_to_string(&output_file)?, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_radix_chunks_with_a_fraction_past_the_sample() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        // Integers whose line 101 is `3.14`, just past the lines sampled
+        let mut lines: Vec<String> = (0..2_000).map(|n| ((n * 37) % 101).to_string()).collect();
+        lines[100] = "3.14".to_string();
+        let input: String = lines.iter().map(|line| format!("{line}\n")).collect();
+        fs::write(&input_file, &input)?;
+
+        lines.sort_by(|a, b| a.parse::<f64>().unwrap().total_cmp(&b.parse().unwrap()));
+        let expected: String = lines.iter().map(|line| format!("{line}\n")).collect();
+
+        // In one chunk, and in chunks small enough to start at line 101
+        for chunk_size in [input.len(), 300] {
+            let mut sorter = ExternalSort::new(1, false, true, None)?;
+            sorter.max_chunk_size = chunk_size;
+            sorter.sort_file(&input_file, &output_file, true, false)?;
+            assert_eq!(
+                fs::read_to_string(&output_file)?,
+                expected,
+                "chunk_size={chunk_size}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_radix_chunks_read_a_plus_sign_as_zero() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        // `+5` is 0 under -n, and it is far past the first 100 lines
+        let mut lines: Vec<String> = (0..20_000)
+            .map(|n| ((n * 37) % 101 + 1).to_string())
+            .collect();
+        lines[5_000] = "+5".to_string();
+        let input: String = lines.iter().map(|line| format!("{line}\n")).collect();
+        fs::write(&input_file, &input)?;
+        lines.sort_by(|a, b| compare_chunk_lines(a.as_bytes(), b.as_bytes(), true));
+        let expected: String = lines.iter().map(|line| format!("{line}\n")).collect();
+        assert!(expected.starts_with("+5\n1\n"));
+
+        // In memory, and in chunks sorted sequentially and in parallel
+        for (chunk_size, parallel) in [
+            (input.len(), false),
+            (input.len() / 2, false),
+            (input.len() / 2, true),
+        ] {
+            let mut sorter = ExternalSort::new(1, parallel, true, None)?;
+            sorter.max_chunk_size = chunk_size;
+            sorter.sort_file(&input_file, &output_file, true, false)?;
+            assert_eq!(
+                fs::read_to_string(&output_file)?,
+                expected,
+                "chunk_size={chunk_size} parallel={parallel}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_radix_chunks_order_equal_values_like_gnu() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        // Equal values fall back to their bytes, as GNU's last resort does
+        fs::write(&input_file, "7\n+5\n07\n5\n-0\n007\n0\n")?;
+        for parallel in [false, true] {
+            let sorter = ExternalSort::new(1, parallel, true, None)?;
+            sorter.sort_file(&input_file, &output_file, true, false)?;
+            assert_eq!(
+                fs::read_to_string(&output_file)?,
+                "+5\n-0\n0\n5\n007\n07\n7\n",
+                "parallel={parallel}"
+            );
+        }
+
+        // Zero-padded copies of each value, in memory and in chunks the
+        // parallel sort takes
+        let mut lines: Vec<String> = (0..20_000)
+            .map(|n| format!("{:0width$}", (n * 37) % 101, width = n % 4))
+            .collect();
+        let input: String = lines.iter().map(|line| format!("{line}\n")).collect();
+        fs::write(&input_file, &input)?;
+        lines.sort_by(|a, b| compare_chunk_lines(a.as_bytes(), b.as_bytes(), true));
+        let expected: String = lines.iter().map(|line| format!("{line}\n")).collect();
+
+        for chunk_size in [input.len(), input.len() / 2] {
+            let mut sorter = ExternalSort::new(1, true, true, None)?;
+            sorter.max_chunk_size = chunk_size;
+            sorter.sort_file(&input_file, &output_file, true, false)?;
+            assert_eq!(
+                fs::read_to_string(&output_file)?,
+                expected,
+                "chunk_size={chunk_size}"
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_external_sort_zero_terminated() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        // Records hold newlines of their own
+        let input: String = (0..300).rev().map(|n| format!("{n:03}\nrow\0")).collect();
+        fs::write(&input_file, &input)?;
+        let expected: String = (0..300).map(|n| format!("{n:03}\nrow\0")).collect();
+
+        let config = SortConfig::default().with_zero_terminated(true);
+        for max_chunk_size in [usize::MAX, 256] {
+            let mut sorter = ExternalSort::new(1, false, false, None)?.with_config(config.clone());
+            sorter.max_chunk_size = max_chunk_size;
+            sorter.sort_file(&input_file, &output_file, false, false)?;
+            assert_eq!(fs::read_to_string(&output_file)?, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunk_count_grows_with_threads() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+
+        // 6MB of input against an 8MB budget, which would fit one chunk
+        let input: String = (0..600_000u64)
+            .map(|n| format!("{:09}\n", n * 7919 % 600_000))
+            .collect();
+        fs::write(&input_file, &input)?;
+
+        // One chunk per thread until chunks reach the 1MB floor
+        for (threads, chunks) in [(1, 1), (2, 2), (4, 3), (8, 6), (64, 6)] {
+            let sorter = ExternalSort::new(8, false, false, None)?.with_merge_threads(threads);
+            let chunk_files = sorter.create_sorted_chunks(&input_file, false, false)?;
+            assert_eq!(chunk_files.len(), chunks, "threads={threads}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_matches_in_memory_sort_near_chunk_threshold() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        // Many duplicates drawn from a small pool of words and numbers
+        let mut state = 0x9e37_79b9_7f4a_7c15u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let words = [
+            "pear",
+            "apple",
+            "fig",
+            "Fig",
+            "apple pie",
+            "",
+            "10",
+            "9",
+            "-3",
+        ];
+        let mut text = String::new();
+        let mut numbers = String::new();
+        for _ in 0..3000 {
+            text.push_str(words[next() as usize % words.len()]);
+            text.push('\n');
+            numbers.push_str(&format!("{}\n", next() as i64 % 200));
+        }
+
+        for (input, numeric) in [(text, false), (numbers, true)] {
+            fs::write(&input_file, &input)?;
+            let mode = if numeric {
+                crate::config::SortMode::Numeric
+            } else {
+                crate::config::SortMode::Lexicographic
+            };
+            let config = crate::SortConfig::new().with_mode(mode).with_unique(true);
+            let expected = crate::sort_bytes(&config, input.as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+            // Fits in one chunk, one byte over it, and well into the merge path
+            for chunk_size in [input.len(), input.len() - 1, input.len() / 2, 512] {
+                for use_radix in [false, true] {
+                    let mut sorter = ExternalSort::new(1, false, use_radix, None)?;
+                    sorter.max_chunk_size = chunk_size;
+                    sorter.sort_file(&input_file, &output_file, numeric, true)?;
+                    assert_eq!(
+                        fs::read(&output_file)?,
+                        expected,
+                        "numeric={numeric} chunk_size={chunk_size} radix={use_radix}"
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_global_options_order_chunks_and_merge() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        let input: String = (0..2000)
+            .map(|n| {
+                ["Pear", " apple", "fig-3", "Fig.2", "APPLE"][n % 5].to_string()
+                    + &format!("{}\n", n % 7)
+            })
+            .collect();
+        fs::write(&input_file, &input)?;
+
+        let configs = [
+            crate::SortConfig::new().with_reverse(true),
+            crate::SortConfig {
+                ignore_case: true,
+                ..crate::SortConfig::new()
+            },
+            crate::SortConfig {
+                dictionary_order: true,
+                ignore_leading_blanks: true,
+                ..crate::SortConfig::new()
+            },
+            crate::SortConfig::new().with_mode(crate::config::SortMode::Month),
+        ];
+        for (index, config) in configs.iter().enumerate() {
+            let expected = crate::sort_bytes(config, input.as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            let mut sorter = ExternalSort::new(1, false, false, None)?.with_config(config.clone());
+            sorter.max_chunk_size = 1024;
+            sorter.sort_file(&input_file, &output_file, false, false)?;
+            assert_eq!(fs::read(&output_file)?, expected, "config {index}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_keyed_sort_across_chunks() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        // Keys repeat so that ties fall back to the whole line
+        let input: String = (0..2000)
+            .map(|n| format!("row{n} {} x\n", (n * 7919) % 300))
+            .collect();
+        fs::write(&input_file, &input)?;
+
+        for unique in [false, true] {
+            let key = crate::config::SortKey::parse("2,2n")
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+            let config = crate::SortConfig::new().add_key(key).with_unique(unique);
+            let expected = crate::sort_bytes(&config, input.as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+            for chunk_size in [input.len(), 1024] {
+                let mut sorter =
+                    ExternalSort::new(1, false, true, None)?.with_config(config.clone());
+                sorter.max_chunk_size = chunk_size;
+                sorter.sort_file(&input_file, &output_file, false, unique)?;
+                assert_eq!(
+                    fs::read(&output_file)?,
+                    expected,
+                    "unique={unique} chunk_size={chunk_size}"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stable_unique_keeps_first_occurrence_across_chunks() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        // Each key and each numeric value recurs in every chunk, in spellings
+        // that differ only past the key or in leading zeros
+        let keyed: String = (0..2000)
+            .map(|n| format!("k{} seq{n}\n", (n * 7919) % 97))
+            .collect();
+        let numbers: String = (0..2000)
+            .map(|n| format!("{:0width$}\n", (n * 31) % 50, width = n % 4))
+            .collect();
+
+        let key = crate::config::SortKey::parse("1,1")
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        let cases = [
+            (keyed, crate::SortConfig::new().add_key(key), false),
+            (
+                numbers,
+                crate::SortConfig::new().with_mode(crate::config::SortMode::Numeric),
+                true,
+            ),
+        ];
+        for (input, config, numeric) in cases {
+            fs::write(&input_file, &input)?;
+            for unique in [false, true] {
+                let config = config.clone().with_stable(true).with_unique(unique);
+                let expected = crate::sort_bytes(&config, input.as_bytes())
+                    .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+                for chunk_size in [input.len(), 1024] {
+                    let mut sorter =
+                        ExternalSort::new(1, false, true, None)?.with_config(config.clone());
+                    sorter.max_chunk_size = chunk_size;
+                    sorter.sort_file(&input_file, &output_file, numeric, unique)?;
+                    assert_eq!(
+                        fs::read(&output_file)?,
+                        expected,
+                        "numeric={numeric} unique={unique} chunk_size={chunk_size}"
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    #[ignore = "writes and sorts a 100MB file; run with --ignored"]
+    fn test_stable_unique_of_file_above_external_threshold() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        // Just over 100MB with every key repeated many times over
+        let mut input = Vec::with_capacity(101 * 1024 * 1024);
+        let mut n = 0u64;
+        while input.len() <= 100 * 1024 * 1024 {
+            writeln!(input, "{} r{n:08} padding", (n * 7919) % 100_003)?;
+            n += 1;
+        }
+        fs::write(&input_file, &input)?;
+
+        let key = crate::config::SortKey::parse("1,1n")
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        let config = crate::SortConfig::new()
+            .add_key(key)
+            .with_stable(true)
+            .with_unique(true);
+        let expected = crate::sort_bytes(&config, &input)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        drop(input);
+
+        let mut sorter = ExternalSort::new(1, false, true, None)?.with_config(config);
+        sorter.max_chunk_size = 16 * 1024 * 1024;
+        sorter.sort_file(&input_file, &output_file, false, true)?;
+        assert!(fs::read(&output_file)? == expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parallel_merge_matches_sequential() -> io::Result<()> {
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        // Repeated keys so that ties and -u survivors depend on input order
+        let input: String = (0..10_000)
+            .map(|n| format!("{} row{n}\n", (n * 7919) % 1500))
+            .collect();
+        fs::write(&input_file, &input)?;
+
+        let key = crate::config::SortKey::parse("1,1n")
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+        for (stable, unique) in [(false, false), (true, false), (true, true)] {
+            let config = crate::SortConfig::new()
+                .add_key(key.clone())
+                .with_stable(stable)
+                .with_unique(unique);
+            let expected = crate::sort_bytes(&config, input.as_bytes())
+                .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
+
+            // About 80 chunks, merged in groups by up to 7 threads
+            for threads in [1, 2, 7, 200] {
+                let mut sorter = ExternalSort::new(1, false, true, None)?
+                    .with_config(config.clone())
+                    .with_merge_threads(threads);
+                sorter.max_chunk_size = 2048;
+                sorter.sort_file(&input_file, &output_file, false, unique)?;
+                assert_eq!(
+                    fs::read(&output_file)?,
+                    expected,
+                    "stable={stable} unique={unique} threads={threads}"
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_compressed_chunks_merge_in_order() -> io::Result<()> {
+        if Command::new("gzip").arg("--version").output().is_err() {
+            return Ok(());
+        }
+        let temp_dir = TempDir::new()?;
+        let input_file = temp_dir.path().join("input.txt");
+        let output_file = temp_dir.path().join("output.txt");
+
+        let input: String = (0..3000)
+            .map(|n| format!("{}\n", (n * 7919) % 3000))
+            .collect();
+        fs::write(&input_file, &input)?;
+
+        let mut sorter =
+            ExternalSort::new(1, false, false, None)?.with_compress_program(Some("gzip".into()));
+        sorter.max_chunk_size = 1024;
+        sorter.sort_file(&input_file, &output_file, true, false)?;
+
+        let expected: String = (0..3000).map(|n| format!("{n}\n")).collect();
+        assert_eq!(fs::read_to_string(&output_file)?, expected);
+
+        // Every chunk left on disk is gzip data
+        let chunks: Vec<_> = fs::read_dir(sorter.temp_dir.path())?.collect::<Result<_, _>>()?;
+        assert!(chunks.len() > 1);
+        for chunk in chunks {
+            assert_eq!(fs::read(chunk.path())?[..2], [0x1f, 0x8b]);
+        }
+
+        // A program that cannot be started is named in the error
+        let mut sorter = ExternalSort::new(1, false, false, None)?
+            .with_compress_program(Some("no-such-compressor".into()));
+        sorter.max_chunk_size = 1024;
+        let err = sorter
+            .sort_file(&input_file, &output_file, true, false)
+            .unwrap_err();
+        assert!(err.to_string().contains("'no-such-compressor'"));
+
+        Ok(())
+    }
 }
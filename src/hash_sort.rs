@@ -1,16 +1,102 @@
-use rand::{seq::SliceRandom, thread_rng};
+use rand::rngs::StdRng;
+use rand::{seq::SliceRandom, thread_rng, SeedableRng};
 use rayon::prelude::*;
+#[cfg(not(feature = "fast-hash"))]
 use std::collections::hash_map::DefaultHasher;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
 
+/// Hasher behind `-R`: [`FastHasher`] with the `fast-hash` feature,
+/// std's SipHash otherwise
+#[cfg(feature = "fast-hash")]
+pub type RandomHasher = FastHasher;
+/// Hasher behind `-R`: [`FastHasher`] with the `fast-hash` feature,
+/// std's SipHash otherwise
+#[cfg(not(feature = "fast-hash"))]
+pub type RandomHasher = DefaultHasher;
+
+/// Hasher that takes input a word at a time, folding the full 128-bit
+/// product of each word with a constant back into its state, then
+/// avalanching the result. Much faster than SipHash on short lines, but
+/// not resistant to inputs crafted to collide, which grouping lines for
+/// `-R` does not need.
+#[derive(Debug, Clone, Copy)]
+pub struct FastHasher {
+    hash: u64,
+}
+
+impl Default for FastHasher {
+    fn default() -> Self {
+        // Any nonzero start; zero would stay zero through zero words
+        Self {
+            hash: 0x243f_6a88_85a3_08d3,
+        }
+    }
+}
+
+impl FastHasher {
+    const MULTIPLIER: u64 = 0x5851_f42d_4c95_7f2d;
+
+    #[inline]
+    fn add(&mut self, word: u64) {
+        let product = u128::from(self.hash ^ word) * u128::from(Self::MULTIPLIER);
+        self.hash = (product as u64) ^ ((product >> 64) as u64);
+    }
+}
+
+impl Hasher for FastHasher {
+    #[inline]
+    fn write(&mut self, bytes: &[u8]) {
+        let mut words = bytes.chunks_exact(8);
+        for word in &mut words {
+            let mut buf = [0u8; 8];
+            buf.copy_from_slice(word);
+            self.add(u64::from_le_bytes(buf));
+        }
+        let rest = words.remainder();
+        if !rest.is_empty() {
+            // The length keeps `a` and `a\0` apart
+            let mut buf = [0u8; 8];
+            buf[..rest.len()].copy_from_slice(rest);
+            self.add(u64::from_le_bytes(buf));
+            self.add(rest.len() as u64);
+        }
+    }
+
+    #[inline]
+    fn write_u64(&mut self, value: u64) {
+        self.add(value);
+    }
+
+    #[inline]
+    fn write_usize(&mut self, value: usize) {
+        self.add(value as u64);
+    }
+
+    #[inline]
+    fn finish(&self) -> u64 {
+        // MurmurHash3's 64-bit finalizer
+        let mut hash = self.hash;
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+        hash ^= hash >> 33;
+        hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+        hash ^ (hash >> 33)
+    }
+}
+
 /// Hash-based random sort with O(n) complexity
 pub struct HashSort;
 
 impl HashSort {
     /// Hash-based grouping with zero-copy shuffling
-    /// O(n) complexity instead of O(n log n)
-    pub fn hash_sort<T: Clone>(lines: &mut [T], get_key: impl Fn(&T) -> &[u8] + Sync) {
+    /// O(n) complexity instead of O(n log n); the same `seed` always gives
+    /// the same order, and without one the order differs from run to run
+    pub fn hash_sort<T: Clone>(
+        lines: &mut [T],
+        seed: Option<u64>,
+        get_key: impl Fn(&T) -> &[u8] + Sync,
+    ) {
         if lines.len() < 2 {
             return;
         }
@@ -19,7 +105,7 @@ impl HashSort {
         let groups = Self::hash_group_lines(lines, &get_key);
 
         // Step 2: Create shuffled group indices
-        let shuffled_indices = Self::create_shuffled_indices(&groups);
+        let shuffled_indices = Self::create_shuffled_indices(groups, seed);
 
         // Step 3: Reorder lines based on shuffled indices
         Self::reorder_by_indices(lines, &shuffled_indices);
@@ -37,21 +123,31 @@ impl HashSort {
         }
 
         // Convert to vec of groups
-        hash_to_indices.into_values().collect()
+        Self::groups_in_input_order(hash_to_indices)
+    }
+
+    /// The groups ordered by their first line, rather than in the map's
+    /// own order, which changes from run to run
+    fn groups_in_input_order(hash_to_indices: HashMap<u64, Vec<usize>>) -> Vec<Vec<usize>> {
+        let mut groups: Vec<Vec<usize>> = hash_to_indices.into_values().collect();
+        groups.sort_unstable_by_key(|group| group[0]);
+        groups
     }
 
-    /// Ultra-fast hash function optimized for speed
+    /// Hash that groups lines, from [`RandomHasher`]
     #[inline]
     fn fast_hash(data: &[u8]) -> u64 {
-        // Use FxHash or xxHash3 for speed
-        let mut hasher = DefaultHasher::new();
+        let mut hasher = RandomHasher::default();
         data.hash(&mut hasher);
         hasher.finish()
     }
 
-    /// Create shuffled indices for groups
-    fn create_shuffled_indices(groups: &[Vec<usize>]) -> Vec<usize> {
-        let mut rng = thread_rng();
+    /// Create shuffled indices for groups, seeded by `seed` if given
+    fn create_shuffled_indices(groups: Vec<Vec<usize>>, seed: Option<u64>) -> Vec<usize> {
+        let mut rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_rng(thread_rng()).expect("thread_rng never fails"),
+        };
         let mut result = Vec::with_capacity(groups.iter().map(|g| g.len()).sum());
 
         // Shuffle groups
@@ -77,11 +173,12 @@ impl HashSort {
     /// BREAKTHROUGH: Parallel hash-based random sort for massive datasets
     pub fn parallel_hash_sort<T: Clone + Send + Sync>(
         lines: &mut [T],
+        seed: Option<u64>,
         get_key: impl Fn(&T) -> &[u8] + Sync,
     ) {
         if lines.len() < 100_000 {
             // Use single-threaded for small data
-            Self::hash_sort(lines, get_key);
+            Self::hash_sort(lines, seed, get_key);
             return;
         }
 
@@ -89,7 +186,7 @@ impl HashSort {
         let groups = Self::parallel_hash_group(lines, &get_key);
 
         // Step 2: Shuffle and reorder
-        let shuffled_indices = Self::create_shuffled_indices(&groups);
+        let shuffled_indices = Self::create_shuffled_indices(groups, seed);
         Self::reorder_by_indices(lines, &shuffled_indices);
     }
 
@@ -116,22 +213,134 @@ impl HashSort {
             hash_to_indices.entry(hash).or_default().push(idx);
         }
 
-        hash_to_indices.into_values().collect()
+        Self::groups_in_input_order(hash_to_indices)
     }
 
     /// BREAKTHROUGH: Streaming random sort for gigantic files
+    ///
+    /// Lines are read in blocks of about `memory_limit_mb`; each block is
+    /// shuffled by ordering its lines on a seeded hash of their bytes and
+    /// spilled to a temporary file, and the blocks are then merged on the
+    /// same hash. Identical lines hash alike, so they come out together
+    /// even when they were read into different blocks.
     pub fn streaming_random_sort<R, W>(
-        _reader: R,
-        _writer: W,
-        _memory_limit_mb: usize,
+        mut reader: R,
+        mut writer: W,
+        memory_limit_mb: usize,
     ) -> std::io::Result<()>
     where
         R: std::io::BufRead,
         W: std::io::Write,
     {
-        // Implementation for streaming large files
-        // Uses external sorting with hash-based grouping
-        todo!("Streaming implementation")
+        use std::io::{BufReader, BufWriter, Seek, Write};
+
+        let seed: u64 = rand::random();
+        let block_limit = memory_limit_mb.max(1) * 1024 * 1024;
+        let temp_dir = crate::temp::create_temp_dir(None)?;
+        let mut blocks = Vec::new();
+
+        loop {
+            let (lines, eof) = Self::read_block(&mut reader, block_limit)?;
+            let mut block: Vec<(u64, Vec<u8>)> = lines
+                .into_iter()
+                .map(|line| (Self::seeded_hash(seed, &line), line))
+                .collect();
+            block.par_sort_unstable();
+
+            if eof && blocks.is_empty() {
+                // Everything fit in one block
+                for (_, line) in &block {
+                    writer.write_all(line)?;
+                    writer.write_all(b"\n")?;
+                }
+                return writer.flush();
+            }
+
+            let mut file = crate::temp::create_temp_file_in(temp_dir.path())?;
+            {
+                let mut block_writer = BufWriter::new(file.as_file_mut());
+                for (_, line) in &block {
+                    block_writer.write_all(line)?;
+                    block_writer.write_all(b"\n")?;
+                }
+                block_writer.flush()?;
+            }
+            file.as_file_mut().rewind()?;
+            blocks.push(file);
+            if eof {
+                break;
+            }
+        }
+
+        // Merge the blocks; ties go to the earlier block
+        let mut readers: Vec<_> = blocks
+            .iter()
+            .map(|file| file.reopen().map(BufReader::new))
+            .collect::<std::io::Result<_>>()?;
+        let mut heap = std::collections::BinaryHeap::new();
+        for (index, reader) in readers.iter_mut().enumerate() {
+            if let Some(line) = Self::read_line(reader)? {
+                heap.push(std::cmp::Reverse((
+                    Self::seeded_hash(seed, &line),
+                    line,
+                    index,
+                )));
+            }
+        }
+        while let Some(std::cmp::Reverse((_, line, index))) = heap.pop() {
+            writer.write_all(&line)?;
+            writer.write_all(b"\n")?;
+            if let Some(line) = Self::read_line(&mut readers[index])? {
+                heap.push(std::cmp::Reverse((
+                    Self::seeded_hash(seed, &line),
+                    line,
+                    index,
+                )));
+            }
+        }
+        writer.flush()
+    }
+
+    /// Read lines until they add up to `limit` bytes or the input ends;
+    /// the flag tells whether it did
+    fn read_block(
+        reader: &mut impl std::io::BufRead,
+        limit: usize,
+    ) -> std::io::Result<(Vec<Vec<u8>>, bool)> {
+        let mut block = Vec::new();
+        let mut size = 0;
+        while size < limit {
+            match Self::read_line(reader)? {
+                Some(line) => {
+                    size += line.len() + 1;
+                    block.push(line);
+                }
+                None => return Ok((block, true)),
+            }
+        }
+        // A block that ends exactly at the end of the input still counts as the last
+        let eof = reader.fill_buf()?.is_empty();
+        Ok((block, eof))
+    }
+
+    /// Next line without its newline, or `None` at the end of the input
+    fn read_line(reader: &mut impl std::io::BufRead) -> std::io::Result<Option<Vec<u8>>> {
+        let mut line = Vec::new();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(None);
+        }
+        if line.last() == Some(&b'\n') {
+            line.pop();
+        }
+        Ok(Some(line))
+    }
+
+    /// Hash of `line` under `seed`, which decides where its group lands
+    fn seeded_hash(seed: u64, line: &[u8]) -> u64 {
+        let mut hasher = RandomHasher::default();
+        seed.hash(&mut hasher);
+        line.hash(&mut hasher);
+        hasher.finish()
     }
 }
 
@@ -201,7 +410,7 @@ impl ZeroAllocHashSort {
 
 #[cfg(test)]
 mod tests {
-    // use super::*;
+    use super::*;
 
     #[test]
     fn test_ultra_random_sort() {
@@ -249,4 +458,59 @@ mod tests {
         println!("Ultra random sort took: {duration:?}");
         assert!(duration.as_millis() < 100); // Should be very fast
     }
+
+    #[test]
+    fn test_streaming_random_sort_spills_blocks() {
+        // About 3MB, spilled in 1MB blocks; every line appears three times
+        let lines: Vec<String> = (0..150_000)
+            .map(|i| format!("line {:012}", i % 50_000))
+            .collect();
+        let input = lines.join("\n") + "\n";
+
+        let mut output = Vec::new();
+        HashSort::streaming_random_sort(input.as_bytes(), &mut output, 1).unwrap();
+        let shuffled: Vec<&[u8]> = output.split(|&b| b == b'\n').collect();
+        assert_eq!(shuffled.last(), Some(&&b""[..]));
+        let shuffled = &shuffled[..shuffled.len() - 1];
+
+        let mut sorted_output = shuffled.to_vec();
+        sorted_output.sort();
+        let mut sorted_input: Vec<&[u8]> = lines.iter().map(|l| l.as_bytes()).collect();
+        sorted_input.sort();
+        assert_eq!(sorted_output, sorted_input);
+        assert_ne!(shuffled, &sorted_input[..]);
+
+        // Copies read into different blocks still come out together
+        let mut seen = std::collections::HashSet::new();
+        for (i, line) in shuffled.iter().enumerate() {
+            if i == 0 || shuffled[i - 1] != *line {
+                assert!(seen.insert(*line), "group split up");
+            }
+        }
+    }
+
+    #[test]
+    fn test_fast_hasher_keeps_identical_lines_grouped() {
+        let hash = |line: &str| {
+            let mut hasher = FastHasher::default();
+            line.as_bytes().hash(&mut hasher);
+            hasher.finish()
+        };
+        assert_eq!(hash("apple"), hash("apple"));
+        assert_ne!(hash("a"), hash("a\0"));
+        let distinct: std::collections::HashSet<u64> =
+            (0..100_000).map(|i| hash(&format!("line {i}"))).collect();
+        assert_eq!(distinct.len(), 100_000);
+
+        // Whichever hasher -R uses, identical lines end up next to each other
+        let mut lines: Vec<String> = (0..5_000).map(|i| format!("item_{}", i % 37)).collect();
+        HashSort::hash_sort(&mut lines, Some(3), |line| line.as_bytes());
+        let mut seen = std::collections::HashSet::new();
+        for (i, line) in lines.iter().enumerate() {
+            if i == 0 || lines[i - 1] != *line {
+                assert!(seen.insert(line.clone()), "{line} split up");
+            }
+        }
+        assert_eq!(seen.len(), 37);
+    }
 }
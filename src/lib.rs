@@ -6,6 +6,7 @@
 
 #![warn(clippy::all)]
 
+pub mod cli;
 pub mod config;
 pub mod error;
 
@@ -16,8 +17,10 @@ pub mod core_sort;
 pub mod external_sort;
 pub mod hash_sort;
 pub mod locale;
+pub mod numeric;
 pub mod radix_sort;
 pub mod simd_compare;
+pub mod temp;
 pub mod zero_copy;
 
 // Re-export commonly used types
@@ -32,7 +35,149 @@ pub const SORT_FAILURE: i32 = 2;
 /// Main sort function that processes input according to configuration
 pub fn sort(config: &SortConfig, input_files: &[String]) -> SortResult<i32> {
     // Use Core Sort implementation for optimal performance
-    let args = crate::args::SortArgs {
+    let args = sort_args(config, input_files);
+
+    let core_sort = crate::core_sort::CoreSort::new(args, config.clone());
+    core_sort.sort().map_err(|e| match SortError::from(e) {
+        SortError::Io(e) => SortError::internal(&e.to_string()),
+        other => other,
+    })?;
+    Ok(EXIT_SUCCESS)
+}
+
+/// Merge already-sorted files into `output`, going straight to the k-way merge.
+///
+/// Skips the full `sort` dispatch (pattern detection, algorithm selection,
+/// temporary chunk files); honors the configured mode, keys, `-r` and `-u`.
+pub fn merge_files<W: std::io::Write>(
+    config: &SortConfig,
+    paths: &[String],
+    mut output: W,
+) -> SortResult<()> {
+    let args = sort_args(config, paths);
+    let core_sort = crate::core_sort::CoreSort::new(args, config.clone());
+    core_sort.merge_files(paths, &mut output)?;
+    Ok(())
+}
+
+/// Merge several already-sorted lists of lines, without terminators, into
+/// one sorted list.
+///
+/// Honors the configured mode, keys, `-r`, `-s` and `-u`; no input is
+/// re-sorted, so each must already be in the configured order.
+pub fn merge_sorted_vecs(config: &SortConfig, inputs: Vec<Vec<Vec<u8>>>) -> Vec<Vec<u8>> {
+    let core_sort = crate::core_sort::CoreSort::new(sort_args(config, &[]), config.clone());
+    core_sort.merge_vecs(inputs)
+}
+
+/// Sort the lines of an in-memory buffer and return the sorted buffer.
+///
+/// Lines are split on newline, or on NUL when `zero_terminated` is set, and
+/// sorted without copying them out of `input`. The output file, check and
+/// merge settings are ignored; everything else in `config` applies.
+pub fn sort_bytes(config: &SortConfig, input: &[u8]) -> SortResult<Vec<u8>> {
+    let mut args = sort_args(config, &[]);
+    args.output = None;
+    let core_sort = crate::core_sort::CoreSort::new(args, config.clone());
+    let mut output = Vec::with_capacity(input.len() + 1);
+    core_sort.sort_buffer(input, &mut output)?;
+    Ok(output)
+}
+
+/// Sort everything `input` yields into `output` without blocking the async
+/// runtime.
+///
+/// The input is read and the output written asynchronously; the sort runs on
+/// tokio's blocking pool. Input within the memory budget (`-S`, else the
+/// 100MB used for stdin) is sorted in memory with [`sort_bytes`]. Longer
+/// input is spilled to a temporary file as it arrives and sorted like any
+/// large file, so at most the budget is held in memory.
+#[cfg(feature = "async")]
+pub async fn sort_async<R, W>(config: &SortConfig, mut input: R, mut output: W) -> SortResult<()>
+where
+    R: tokio::io::AsyncRead + Unpin,
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    use std::io::{Read, Write};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Bytes moved between the async streams and the temporary files at a time
+    const CHUNK_SIZE: usize = 1024 * 1024;
+
+    let budget = config
+        .external_buffer_size()
+        .unwrap_or(crate::core_sort::STDIN_MEMORY_BUDGET);
+    let mut buffer = Vec::new();
+    (&mut input)
+        .take(budget as u64 + 1)
+        .read_to_end(&mut buffer)
+        .await?;
+
+    if buffer.len() <= budget {
+        let config = config.clone();
+        let sorted = run_blocking(move || sort_bytes(&config, &buffer)).await?;
+        output.write_all(&sorted).await?;
+        return Ok(output.flush().await?);
+    }
+
+    // Spill what was read and the rest of the input, writing on the blocking pool
+    let spilled = crate::temp::create_temp_file(config.temp_dir.as_deref())?;
+    let mut file = spilled.reopen()?;
+    let mut chunk = buffer;
+    while !chunk.is_empty() {
+        file = run_blocking(move || {
+            file.write_all(&chunk)?;
+            Ok(file)
+        })
+        .await?;
+        chunk = Vec::with_capacity(CHUNK_SIZE);
+        (&mut input)
+            .take(CHUNK_SIZE as u64)
+            .read_to_end(&mut chunk)
+            .await?;
+    }
+
+    let sorted = crate::temp::create_temp_file(config.temp_dir.as_deref())?;
+    let files = vec![spilled.path().to_string_lossy().into_owned()];
+    let file_config = config
+        .clone()
+        .with_output_file(Some(sorted.path().to_string_lossy().into_owned()));
+    run_blocking(move || sort(&file_config, &files)).await?;
+
+    // Stream the sorted file out, reading it on the blocking pool
+    let mut file = std::fs::File::open(sorted.path())?;
+    loop {
+        let (read_back, chunk) = run_blocking(move || {
+            let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+            (&mut file)
+                .take(CHUNK_SIZE as u64)
+                .read_to_end(&mut chunk)?;
+            Ok((file, chunk))
+        })
+        .await?;
+        if chunk.is_empty() {
+            break;
+        }
+        output.write_all(&chunk).await?;
+        file = read_back;
+    }
+    output.flush().await?;
+    Ok(())
+}
+
+/// Run `task` on tokio's blocking pool, keeping its error as it is
+#[cfg(feature = "async")]
+async fn run_blocking<T: Send + 'static>(
+    task: impl FnOnce() -> SortResult<T> + Send + 'static,
+) -> SortResult<T> {
+    tokio::task::spawn_blocking(task)
+        .await
+        .map_err(|e| SortError::internal(&format!("sort task failed: {e}")))?
+}
+
+/// Build the `SortArgs` view of a configuration used by `CoreSort`
+fn sort_args(config: &SortConfig, input_files: &[String]) -> crate::args::SortArgs {
+    crate::args::SortArgs {
         files: input_files.to_vec(),
         output: config.output_file.clone(),
         reverse: config.reverse,
@@ -41,7 +186,7 @@ pub fn sort(config: &SortConfig, input_files: &[String]) -> SortResult<i32> {
         human_numeric_sort: matches!(config.mode, crate::config::SortMode::HumanNumeric),
         version_sort: matches!(config.mode, crate::config::SortMode::Version),
         random_sort: matches!(config.mode, crate::config::SortMode::Random),
-        random_seed: None, // Use random seed
+        random_seed: config.random_seed,
         ignore_case: config.ignore_case,
         unique: config.unique,
         stable: config.stable,
@@ -49,11 +194,359 @@ pub fn sort(config: &SortConfig, input_files: &[String]) -> SortResult<i32> {
         zero_terminated: config.zero_terminated,
         check: config.check,
         merge: config.merge,
-    };
+    }
+}
 
-    let core_sort = crate::core_sort::CoreSort::new(args, config.clone());
-    core_sort
-        .sort()
-        .map_err(|e| SortError::internal(&e.to_string()))?;
-    Ok(EXIT_SUCCESS)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    fn write_inputs(temp_dir: &TempDir, contents: &[&str]) -> Vec<String> {
+        contents
+            .iter()
+            .enumerate()
+            .map(|(i, content)| {
+                let path = temp_dir.path().join(format!("input{i}.txt"));
+                fs::write(&path, content).expect("Failed to write test input");
+                path.to_string_lossy().to_string()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_merge_files_numeric() -> SortResult<()> {
+        let temp_dir = TempDir::new()?;
+        let inputs = write_inputs(&temp_dir, &["2\n10\n300\n", "1\n20\n", "5\n9\n1000\n"]);
+
+        let config = SortConfig::new().with_mode(SortMode::Numeric);
+        let mut output = Vec::new();
+        merge_files(&config, &inputs, &mut output)?;
+
+        assert_eq!(output, b"1\n2\n5\n9\n10\n20\n300\n1000\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_files_by_reversed_numeric_key() -> SortResult<()> {
+        let temp_dir = TempDir::new()?;
+        let inputs = write_inputs(&temp_dir, &["c 100\na 9\n", "b 20\nd 3\n"]);
+
+        let config = SortConfig::new()
+            .add_key(crate::config::SortKey::parse("2,2nr")?)
+            .with_field_separator(Some(' '));
+        let mut output = Vec::new();
+        merge_files(&config, &inputs, &mut output)?;
+
+        assert_eq!(output, b"c 100\nb 20\na 9\nd 3\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_files_unique_reverse() -> SortResult<()> {
+        let temp_dir = TempDir::new()?;
+        let inputs = write_inputs(&temp_dir, &["30\n20\n10\n", "30\n10\n", "20\n5\n"]);
+
+        let config = SortConfig::new()
+            .with_mode(SortMode::Numeric)
+            .with_reverse(true)
+            .with_unique(true);
+        let mut output = Vec::new();
+        merge_files(&config, &inputs, &mut output)?;
+
+        assert_eq!(output, b"30\n20\n10\n5\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_files_verify_stops_at_disorder() -> SortResult<()> {
+        let temp_dir = TempDir::new()?;
+        let inputs = write_inputs(&temp_dir, &["a\nc\ne\n", "b\nd\nc\nf\n"]);
+
+        // Without --verify the disorder goes through unnoticed
+        let mut output = Vec::new();
+        merge_files(&SortConfig::new(), &inputs, &mut output)?;
+        assert_eq!(output, b"a\nb\nc\nd\nc\ne\nf\n");
+
+        let config = SortConfig::new().with_merge(true).with_verify(true);
+        let err = merge_files(&config, &inputs, Vec::new()).unwrap_err();
+        match err {
+            SortError::NotSorted { file, line } => {
+                assert_eq!(file, inputs[1]);
+                assert_eq!(line, 3);
+            }
+            other => panic!("expected NotSorted, got {other:?}"),
+        }
+
+        // Sorted inputs merge as usual
+        let sorted = write_inputs(&temp_dir, &["a\nc\n", "b\nc\n"]);
+        let mut output = Vec::new();
+        merge_files(&config, &sorted, &mut output)?;
+        assert_eq!(output, b"a\nb\nc\nc\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_numeric_crlf_lines() -> SortResult<()> {
+        // The `\r` of each line ending is dropped on the in-memory path and
+        // the merge path alike, so it cannot end up in a parsed number
+        let config = SortConfig::new().with_mode(SortMode::Numeric);
+        let sorted = sort_bytes(&config, b"10\r\n2\r\n-3\r\n1\r\n")?;
+        assert_eq!(sorted, b"-3\n1\n2\n10\n");
+
+        let temp_dir = TempDir::new()?;
+        let inputs = write_inputs(&temp_dir, &["-3\r\n2\r\n10\r\n", "1\r\n2\r\n"]);
+        let mut output = Vec::new();
+        merge_files(&config, &inputs, &mut output)?;
+        assert_eq!(output, b"-3\n1\n2\n2\n10\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_sorted_vecs() {
+        let lines = |items: &[&str]| -> Vec<Vec<u8>> {
+            items.iter().map(|item| item.as_bytes().to_vec()).collect()
+        };
+
+        let numeric = SortConfig::new().with_mode(SortMode::Numeric);
+        let inputs = vec![
+            lines(&["2", "10", "300"]),
+            lines(&["1", "10"]),
+            lines(&[]),
+            lines(&["9"]),
+        ];
+        assert_eq!(
+            merge_sorted_vecs(&numeric, inputs.clone()),
+            lines(&["1", "2", "9", "10", "10", "300"])
+        );
+        assert_eq!(
+            merge_sorted_vecs(&numeric.clone().with_unique(true), inputs),
+            lines(&["1", "2", "9", "10", "300"])
+        );
+
+        let reverse = SortConfig::new().with_reverse(true);
+        let inputs = vec![lines(&["pear", "fig", "apple"]), lines(&["plum", "fig"])];
+        assert_eq!(
+            merge_sorted_vecs(&reverse, inputs.clone()),
+            lines(&["plum", "pear", "fig", "fig", "apple"])
+        );
+        assert_eq!(
+            merge_sorted_vecs(&reverse.with_unique(true), inputs),
+            lines(&["plum", "pear", "fig", "apple"])
+        );
+    }
+
+    #[test]
+    fn test_merge_files_much_larger_than_read_chunks() -> SortResult<()> {
+        // Files of very different sizes, each many times the 64KB read chunk,
+        // with line lengths that do not divide it
+        let temp_dir = TempDir::new()?;
+        let contents: Vec<String> = [7u64, 30_011, 90_001]
+            .iter()
+            .enumerate()
+            .map(|(file, &count)| {
+                (0..count)
+                    .map(|n| {
+                        let pad = "x".repeat((n % 13) as usize);
+                        format!("{:012} {file} {pad}\n", n * (file as u64 + 2))
+                    })
+                    .collect()
+            })
+            .collect();
+        let inputs = write_inputs(
+            &temp_dir,
+            &contents.iter().map(String::as_str).collect::<Vec<_>>(),
+        );
+
+        let mut expected: Vec<&str> = contents.iter().flat_map(|c| c.lines()).collect();
+        expected.sort_unstable();
+        let expected: String = expected.iter().map(|line| format!("{line}\n")).collect();
+
+        let mut output = Vec::new();
+        merge_files(&SortConfig::new(), &inputs, &mut output)?;
+        assert!(output == expected.as_bytes(), "merged output is corrupted");
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_files_stable_keeps_file_then_line_order() -> SortResult<()> {
+        let temp_dir = TempDir::new()?;
+        let inputs = write_inputs(
+            &temp_dir,
+            &[
+                "a f0l0\na f0l1\nb f0l2\n",
+                "a f1l0\nb f1l1\nb f1l2\n",
+                "a f2l0\nb f2l1\n",
+            ],
+        );
+
+        let config = SortConfig::new()
+            .with_stable(true)
+            .add_key(crate::config::SortKey::parse("1,1")?);
+        let mut output = Vec::new();
+        merge_files(&config, &inputs, &mut output)?;
+
+        assert_eq!(
+            String::from_utf8_lossy(&output),
+            "a f0l0\na f0l1\na f1l0\na f2l0\nb f0l2\nb f1l1\nb f1l2\nb f2l1\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_bytes_newline_delimited() -> SortResult<()> {
+        let config = SortConfig::new();
+        assert_eq!(
+            sort_bytes(&config, b"pear\napple\nfig\n")?,
+            b"apple\nfig\npear\n"
+        );
+        // An unterminated last line stays unterminated under the default policy
+        assert_eq!(sort_bytes(&config, b"b\na")?, b"a\nb");
+        assert_eq!(sort_bytes(&config, b"")?, b"");
+
+        let numeric = SortConfig::new().with_mode(SortMode::Numeric);
+        assert_eq!(sort_bytes(&numeric, b"10\n9\n100\n")?, b"9\n10\n100\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_bytes_nul_delimited() -> SortResult<()> {
+        let config = SortConfig::new().with_zero_terminated(true);
+        // Newlines are ordinary bytes inside NUL-terminated records
+        assert_eq!(sort_bytes(&config, b"b\nline\0a\0c\0")?, b"a\0b\nline\0c\0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_sort_bytes_unique() -> SortResult<()> {
+        let config = SortConfig::new().with_unique(true);
+        assert_eq!(sort_bytes(&config, b"b\na\nb\na\n")?, b"a\nb\n");
+
+        let config = SortConfig::new()
+            .with_unique(true)
+            .with_stable(true)
+            .with_zero_terminated(true);
+        assert_eq!(sort_bytes(&config, b"y\0x\0y\0")?, b"x\0y\0");
+        Ok(())
+    }
+
+    #[test]
+    fn test_byte_order_holds_for_one_sort_only() -> SortResult<()> {
+        let input = b"b\nB\n\xc3\xa9\na\nA\n";
+        let byte_order = SortConfig::new().with_byte_order(true);
+        assert!(!byte_order.collates());
+        assert_eq!(sort_bytes(&byte_order, input)?, b"A\nB\na\nb\n\xc3\xa9\n");
+
+        // A later sort still collates when the environment names a locale
+        let config = SortConfig::new();
+        assert_eq!(config.collates(), locale::LocaleConfig::is_enabled());
+        let mut lines: Vec<zero_copy::Line> = zero_copy::parse_lines(input);
+        lines.sort_by(|a, b| a.compare_lexicographic(b));
+        let expected: Vec<u8> = lines
+            .iter()
+            .flat_map(|line| [unsafe { line.as_bytes() }, b"\n"].concat())
+            .collect();
+        assert_eq!(sort_bytes(&config, input)?, expected);
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_sort_async_over_in_memory_streams() -> SortResult<()> {
+        use tokio::io::AsyncWriteExt;
+
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        runtime.block_on(async {
+            let config = SortConfig::new().with_mode(SortMode::Numeric);
+            let mut output = Vec::new();
+            sort_async(&config, &b"10\n9\n100\n"[..], &mut output).await?;
+            assert_eq!(output, b"9\n10\n100\n");
+
+            // A pipe fed in pieces by another task is read to its end
+            let (mut writer, reader) = tokio::io::duplex(8);
+            let feeder = tokio::spawn(async move {
+                for piece in ["pear\nap", "ple\nfig", "\n"] {
+                    writer.write_all(piece.as_bytes()).await?;
+                }
+                writer.shutdown().await
+            });
+            let mut output = Vec::new();
+            sort_async(&SortConfig::new(), reader, &mut output).await?;
+            feeder
+                .await
+                .map_err(|e| SortError::internal(&e.to_string()))??;
+            assert_eq!(output, b"apple\nfig\npear\n");
+
+            // Sort errors come back from the blocking pool unchanged
+            let config = SortConfig::new().with_max_line_length(Some(2));
+            let err = sort_async(&config, &b"abc\n"[..], Vec::new())
+                .await
+                .unwrap_err();
+            assert!(matches!(err, SortError::LineTooLong { line: 1, limit: 2 }));
+
+            // Input past the -S budget (clamped to 1MiB) spills to a file
+            let temp_dir = TempDir::new()?;
+            let config = SortConfig::new()
+                .with_mode(SortMode::Numeric)
+                .with_buffer_size(Some(1))
+                .with_temp_dir(Some(temp_dir.path().to_string_lossy().to_string()));
+            let input: Vec<u8> = (0..300_000u32)
+                .rev()
+                .flat_map(|n| format!("{n}\n").into_bytes())
+                .collect();
+            assert!(input.len() > config::MIN_EXTERNAL_BUFFER_SIZE);
+            let mut output = Vec::new();
+            sort_async(&config, &input[..], &mut output).await?;
+            assert_eq!(output, sort_bytes(&config, &input)?);
+            assert_eq!(fs::read_dir(temp_dir.path())?.count(), 0);
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn test_strict_fields_rejects_ragged_lines() -> SortResult<()> {
+        let temp_dir = TempDir::new()?;
+        let inputs = write_inputs(&temp_dir, &["a 3 x\nb 1\nc\nd 2 y\n"]);
+        let output = temp_dir.path().join("out.txt");
+        let key = config::SortKey::parse("2,2n")?;
+        let config = SortConfig::new()
+            .add_key(key)
+            .with_output_file(Some(output.to_string_lossy().to_string()));
+
+        // Lenient: the missing field compares as empty
+        assert_eq!(sort(&config, &inputs)?, EXIT_SUCCESS);
+        assert_eq!(fs::read_to_string(&output)?, "c\nb 1\nd 2 y\na 3 x\n");
+
+        let strict = config.with_strict_fields(true);
+        let err = sort(&strict, &inputs).unwrap_err();
+        assert!(matches!(err, SortError::MissingField { line: 3, field: 2 }));
+        assert_eq!(err.exit_code(), SORT_FAILURE);
+        assert!(sort_bytes(&strict, b"a 1\nb 2\n").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_line_length_errors_or_truncates() -> SortResult<()> {
+        // The third line is over the limit; the first two agree up to it
+        let input = b"abcdz\nabcda\nabcdefgh-long\nabc\n";
+        let config = SortConfig::new().with_max_line_length(Some(4));
+
+        let err = sort_bytes(&config, input).unwrap_err();
+        assert!(matches!(err, SortError::LineTooLong { line: 1, limit: 4 }));
+        assert_eq!(err.exit_code(), SORT_FAILURE);
+        assert!(sort_bytes(&config, b"abcd\nabc\n").is_ok());
+
+        // Truncated, the long lines tie and are kept whole in the output
+        let truncate = config.with_long_lines(config::LongLines::Truncate);
+        assert_eq!(
+            sort_bytes(&truncate.clone().with_stable(true), input)?,
+            b"abc\nabcdz\nabcda\nabcdefgh-long\n"
+        );
+        assert_eq!(
+            sort_bytes(&truncate.with_unique(true), input)?,
+            b"abc\nabcdz\n"
+        );
+        Ok(())
+    }
 }
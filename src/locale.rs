@@ -58,7 +58,15 @@ impl LocaleConfig {
 
     /// Check if locale-aware comparison is enabled
     pub fn is_enabled() -> bool {
-        Self::get().enabled
+        Self::get().collates()
+    }
+
+    /// Whether comparisons under this configuration go through `strcoll`;
+    /// a sort with `--byte-order` also checks [`SortConfig::collates`]
+    ///
+    /// [`SortConfig::collates`]: crate::config::SortConfig::collates
+    pub fn collates(&self) -> bool {
+        self.enabled
     }
 }
 
@@ -116,7 +124,7 @@ pub fn strcoll_compare(a: &[u8], b: &[u8]) -> Ordering {
 }
 
 /// Case-insensitive locale-aware comparison using strcasecoll (if available)
-/// Falls back to lowercasing + strcoll if strcasecoll is not available
+/// Falls back to uppercasing + strcoll if strcasecoll is not available
 pub fn strcasecoll_compare(a: &[u8], b: &[u8]) -> Ordering {
     // Fast path for identical strings
     if a == b {
@@ -134,12 +142,12 @@ pub fn strcasecoll_compare(a: &[u8], b: &[u8]) -> Ordering {
         Err(_) => return case_insensitive_byte_compare(a, b),
     };
 
-    // Convert to lowercase for case-insensitive comparison
-    let a_lower = a_str.to_lowercase();
-    let b_lower = b_str.to_lowercase();
+    // Fold to uppercase for case-insensitive comparison, as GNU does
+    let a_upper = a_str.to_uppercase();
+    let b_upper = b_str.to_uppercase();
 
-    // Use strcoll on lowercased strings
-    strcoll_compare(a_lower.as_bytes(), b_lower.as_bytes())
+    // Use strcoll on uppercased strings
+    strcoll_compare(a_upper.as_bytes(), b_upper.as_bytes())
 }
 
 /// Fallback case-insensitive byte comparison
@@ -147,8 +155,8 @@ fn case_insensitive_byte_compare(a: &[u8], b: &[u8]) -> Ordering {
     let len = a.len().min(b.len());
 
     for i in 0..len {
-        let ca = a[i].to_ascii_lowercase();
-        let cb = b[i].to_ascii_lowercase();
+        let ca = a[i].to_ascii_uppercase();
+        let cb = b[i].to_ascii_uppercase();
         match ca.cmp(&cb) {
             Ordering::Equal => continue,
             other => return other,
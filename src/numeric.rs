@@ -0,0 +1,426 @@
+//! Numeric prefix parsing shared by every numeric comparison path
+//!
+//! GNU sort reads a `-n` key as optional blanks, an optional `-`, a run of
+//! digits and optionally a decimal point followed by more digits. Whatever
+//! follows that prefix is ignored, and a key without any digits (including an
+//! empty or blank key) is zero. `-g` instead reads the longest prefix that
+//! parses as a floating-point number, as `strtod` does.
+
+use std::cmp::Ordering;
+
+/// The parsed `-n` prefix of a byte string
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NumericPrefix<'a> {
+    /// Whether a `-` preceded a non-zero value
+    pub negative: bool,
+    /// Integer digits with leading zeros removed
+    pub integer: &'a [u8],
+    /// Fraction digits with trailing zeros removed
+    pub fraction: &'a [u8],
+}
+
+impl NumericPrefix<'_> {
+    /// Whether the prefix reads as zero (no digits, or only zeros)
+    pub fn is_zero(&self) -> bool {
+        self.integer.is_empty() && self.fraction.is_empty()
+    }
+
+    /// Compare two prefixes by numeric value, exactly and without overflow
+    pub fn compare(&self, other: &NumericPrefix) -> Ordering {
+        let sign = |n: &NumericPrefix| match (n.is_zero(), n.negative) {
+            (true, _) => 0,
+            (false, true) => -1,
+            (false, false) => 1,
+        };
+        match sign(self).cmp(&sign(other)) {
+            Ordering::Equal => {}
+            unequal => return unequal,
+        }
+
+        let magnitude = self
+            .integer
+            .len()
+            .cmp(&other.integer.len())
+            .then_with(|| self.integer.cmp(other.integer))
+            .then_with(|| self.fraction.cmp(other.fraction));
+        if self.negative {
+            magnitude.reverse()
+        } else {
+            magnitude
+        }
+    }
+
+    /// Approximate value of the prefix as an `f64`
+    pub fn to_f64(&self) -> f64 {
+        let mut text = String::with_capacity(self.integer.len() + self.fraction.len() + 3);
+        if self.negative {
+            text.push('-');
+        }
+        text.push('0');
+        text.extend(self.integer.iter().map(|&b| b as char));
+        text.push('.');
+        text.extend(self.fraction.iter().map(|&b| b as char));
+        text.parse().unwrap_or(0.0)
+    }
+}
+
+/// Blanks skipped before a number
+fn is_blank(byte: u8) -> bool {
+    byte == b' ' || byte == b'\t'
+}
+
+/// Number of leading ASCII digits in `bytes`
+fn digit_run(bytes: &[u8]) -> usize {
+    bytes.iter().take_while(|b| b.is_ascii_digit()).count()
+}
+
+/// Split `bytes` into the parts of its `-n` prefix; returns the parts and
+/// the prefix length, which is 0 when there are no digits
+fn split_prefix(bytes: &[u8]) -> (NumericPrefix<'_>, usize) {
+    let mut pos = bytes.iter().take_while(|&&b| is_blank(b)).count();
+    let negative = bytes.get(pos) == Some(&b'-');
+    if negative {
+        pos += 1;
+    }
+    split_digits(bytes, pos, negative)
+}
+
+/// The digits part of [`split_prefix`], from `pos` on, with the sign
+/// already read
+fn split_digits(bytes: &[u8], mut pos: usize, negative: bool) -> (NumericPrefix<'_>, usize) {
+    let integer_start = pos;
+    pos += digit_run(&bytes[pos..]);
+    let integer = &bytes[integer_start..pos];
+
+    let mut fraction: &[u8] = &[];
+    if bytes.get(pos) == Some(&b'.') {
+        let digits = digit_run(&bytes[pos + 1..]);
+        if digits > 0 || !integer.is_empty() {
+            fraction = &bytes[pos + 1..pos + 1 + digits];
+            pos += 1 + digits;
+        }
+    }
+
+    let len = if integer.is_empty() && fraction.is_empty() {
+        0
+    } else {
+        pos
+    };
+    let leading_zeros = integer.iter().take_while(|&&b| b == b'0').count();
+    let trailing_zeros = fraction.iter().rev().take_while(|&&b| b == b'0').count();
+    let prefix = NumericPrefix {
+        negative,
+        integer: &integer[leading_zeros..],
+        fraction: &fraction[..fraction.len() - trailing_zeros],
+    };
+    (prefix, len)
+}
+
+/// Length of the `-n` number at the start of `bytes`, counting any leading
+/// blanks; 0 when `bytes` does not start with a number
+pub fn numeric_prefix_len(bytes: &[u8]) -> usize {
+    split_prefix(bytes).1
+}
+
+/// Parse the `-n` prefix of `bytes`
+pub fn parse_numeric_prefix(bytes: &[u8]) -> NumericPrefix<'_> {
+    split_prefix(bytes).0
+}
+
+/// Parse the `-n` prefix of `-` followed by `bytes`, as `--accounting`
+/// reads `(100)`, without building that string
+pub fn parse_negated_numeric_prefix(bytes: &[u8]) -> NumericPrefix<'_> {
+    split_digits(bytes, 0, true).0
+}
+
+/// Compare two byte strings by the value of their `-n` prefixes
+pub fn compare_numeric(a: &[u8], b: &[u8]) -> Ordering {
+    parse_numeric_prefix(a).compare(&parse_numeric_prefix(b))
+}
+
+/// Value of the `-n` prefix of `bytes` as an `f64`; 0 when there is none
+pub fn numeric_value(bytes: &[u8]) -> f64 {
+    parse_numeric_prefix(bytes).to_f64()
+}
+
+/// Power of 1024 that the `-h` suffix byte `suffix` stands for: `k` or
+/// `K`, then `M`, `G`, `T`, `P`, `E`, `Z` and `Y`, matched exactly
+fn human_suffix_exponent(suffix: Option<&u8>) -> i32 {
+    match suffix {
+        Some(b'k' | b'K') => 1,
+        Some(b'M') => 2,
+        Some(b'G') => 3,
+        Some(b'T') => 4,
+        Some(b'P') => 5,
+        Some(b'E') => 6,
+        Some(b'Z') => 7,
+        Some(b'Y') => 8,
+        _ => 0,
+    }
+}
+
+/// Value of a `-h` number such as `2.5M` or `-1K`: the `-n` prefix scaled
+/// by the binary suffix directly after it (see [`compare_human_numeric`]
+/// for the suffixes). `None` when `bytes` does not start with a number.
+pub fn human_numeric_value(bytes: &[u8]) -> Option<f64> {
+    let len = numeric_prefix_len(bytes);
+    if len == 0 {
+        return None;
+    }
+    let exponent = human_suffix_exponent(bytes.get(len));
+    Some(numeric_value(&bytes[..len]) * 1024f64.powi(exponent))
+}
+
+/// Rank of the `-h` suffix of `bytes`, negated for negative numbers; 0 for
+/// no suffix, and for a number without a nonzero digit such as `0K`
+fn human_unit_order(bytes: &[u8]) -> i32 {
+    let len = numeric_prefix_len(bytes);
+    let number = &bytes[..len];
+    if !number.iter().any(|b| (b'1'..=b'9').contains(b)) {
+        return 0;
+    }
+    let order = human_suffix_exponent(bytes.get(len));
+    if number.contains(&b'-') {
+        -order
+    } else {
+        order
+    }
+}
+
+/// Compare two byte strings as `-h` does: by suffix first, `k`/`K` for
+/// 1024 up to `Y` for 1024^8, then by the `-n` value in front of it, so
+/// `2000K` sorts before `1M` as in GNU sort. Lowercase suffixes other than
+/// `k` are not units.
+pub fn compare_human_numeric(a: &[u8], b: &[u8]) -> Ordering {
+    human_unit_order(a)
+        .cmp(&human_unit_order(b))
+        .then_with(|| compare_numeric(a, b))
+}
+
+/// Parse `bytes` as a plain integer: an optional `-` and digits, nothing
+/// else, within `i64`. This is the fast path; anything it rejects still has
+/// a value through [`compare_numeric`].
+pub fn parse_integer(bytes: &[u8]) -> Option<i64> {
+    let (negative, digits) = match bytes {
+        [b'-', rest @ ..] => (true, rest),
+        rest => (false, rest),
+    };
+    if digits.is_empty() {
+        return None;
+    }
+
+    // Accumulate toward the sign so that i64::MIN is representable
+    let mut value: i64 = 0;
+    for &byte in digits {
+        if !byte.is_ascii_digit() {
+            return None;
+        }
+        let digit = (byte - b'0') as i64;
+        value = value.checked_mul(10)?;
+        value = if negative {
+            value.checked_sub(digit)?
+        } else {
+            value.checked_add(digit)?
+        };
+    }
+    Some(value)
+}
+
+/// Value of the `-g` prefix of `bytes`: the longest leading floating-point
+/// number, after whitespace, including `inf`, `infinity` and `nan`.
+/// Strings that do not start with a number read as negative infinity so
+/// they sort first.
+pub fn general_numeric_value(bytes: &[u8]) -> f64 {
+    let start = bytes
+        .iter()
+        .position(|b| !b.is_ascii_whitespace())
+        .unwrap_or(bytes.len());
+    let rest = &bytes[start..];
+    let len = general_prefix_len(rest);
+
+    std::str::from_utf8(&rest[..len])
+        .ok()
+        .and_then(|text| text.parse().ok())
+        .unwrap_or(f64::NEG_INFINITY)
+}
+
+/// [`general_numeric_value`] of `-` followed by `bytes`, without building
+/// that string: no blanks or second sign may follow the `-`
+pub fn negated_general_numeric_value(bytes: &[u8]) -> f64 {
+    if matches!(bytes.first(), Some(b'-' | b'+')) {
+        return f64::NEG_INFINITY;
+    }
+    let len = general_prefix_len(bytes);
+    std::str::from_utf8(&bytes[..len])
+        .ok()
+        .filter(|_| len > 0)
+        .and_then(|text| text.parse::<f64>().ok())
+        .map_or(f64::NEG_INFINITY, |value| -value)
+}
+
+/// Length of the floating-point number at the start of `bytes`
+fn general_prefix_len(bytes: &[u8]) -> usize {
+    let sign = usize::from(matches!(bytes.first(), Some(b'-' | b'+')));
+    let body = &bytes[sign..];
+
+    for word in [&b"infinity"[..], b"inf", b"nan"] {
+        if body.len() >= word.len() && body[..word.len()].eq_ignore_ascii_case(word) {
+            return sign + word.len();
+        }
+    }
+
+    let integer = digit_run(body);
+    let mut pos = integer;
+    let mut fraction = 0;
+    if body.get(pos) == Some(&b'.') {
+        fraction = digit_run(&body[pos + 1..]);
+        pos += 1 + fraction;
+    }
+    if integer + fraction == 0 {
+        return 0;
+    }
+
+    // An exponent only counts when it has digits
+    if matches!(body.get(pos), Some(b'e' | b'E')) {
+        let exp_sign = usize::from(matches!(body.get(pos + 1), Some(b'-' | b'+')));
+        let exp_digits = digit_run(&body[(pos + 1 + exp_sign).min(body.len())..]);
+        if exp_digits > 0 {
+            pos += 1 + exp_sign + exp_digits;
+        }
+    }
+    sign + pos
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_numeric_prefix_table() {
+        // (input, prefix length, negative, integer digits, fraction digits)
+        type Case = (&'static [u8], usize, bool, &'static [u8], &'static [u8]);
+        let cases: &[Case] = &[
+            (b"", 0, false, b"", b""),
+            (b"   ", 0, false, b"", b""),
+            (b"abc", 0, false, b"", b""),
+            (b"-", 0, true, b"", b""),
+            (b".", 0, false, b"", b""),
+            (b"-.", 0, true, b"", b""),
+            (b"+5", 0, false, b"", b""),
+            (b"0", 1, false, b"", b""),
+            (b"-0", 2, true, b"", b""),
+            (b"007", 3, false, b"7", b""),
+            (b"42", 2, false, b"42", b""),
+            (b"-42", 3, true, b"42", b""),
+            (b"  \t-42 apples", 6, true, b"42", b""),
+            (b"3.14", 4, false, b"3", b"14"),
+            (b"3.1400", 6, false, b"3", b"14"),
+            (b"5.", 2, false, b"5", b""),
+            (b".5", 2, false, b"", b"5"),
+            (b"-.5", 3, true, b"", b"5"),
+            (b"123abc", 3, false, b"123", b""),
+            (b"1.2.3", 3, false, b"1", b"2"),
+            (b"1e3", 1, false, b"1", b""),
+            (b"1,000", 1, false, b"1", b""),
+        ];
+
+        for &(input, len, negative, integer, fraction) in cases {
+            let text = String::from_utf8_lossy(input);
+            assert_eq!(numeric_prefix_len(input), len, "length of {text:?}");
+            assert_eq!(
+                parse_numeric_prefix(input),
+                NumericPrefix {
+                    negative,
+                    integer,
+                    fraction
+                },
+                "parts of {text:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_compare_numeric_table() {
+        use Ordering::*;
+
+        let cases: &[(&str, &str, Ordering)] = &[
+            ("", "0", Equal),
+            ("  ", "abc", Equal),
+            ("-0", "0", Equal),
+            ("-", "0", Equal),
+            ("007", "7", Equal),
+            ("7.0", "7", Equal),
+            ("123abc", "123", Equal),
+            ("", "-1", Greater),
+            ("", "1", Less),
+            ("-1", "1", Less),
+            ("-10", "-9", Less),
+            ("-0.5", "-0.25", Less),
+            ("0.5", "0.25", Greater),
+            ("1.05", "1.5", Less),
+            ("2", "10", Less),
+            ("  2", "10", Less),
+            ("99999999999999999999", "100000000000000000000", Less),
+            ("-99999999999999999999", "-100000000000000000000", Greater),
+            ("+5", "1", Less),
+        ];
+
+        for &(a, b, expected) in cases {
+            assert_eq!(
+                compare_numeric(a.as_bytes(), b.as_bytes()),
+                expected,
+                "{a:?} vs {b:?}"
+            );
+            assert_eq!(
+                compare_numeric(b.as_bytes(), a.as_bytes()),
+                expected.reverse(),
+                "{b:?} vs {a:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_integer_and_values() {
+        assert_eq!(parse_integer(b"123"), Some(123));
+        assert_eq!(parse_integer(b"-456"), Some(-456));
+        assert_eq!(parse_integer(b"-9223372036854775808"), Some(i64::MIN));
+        assert_eq!(parse_integer(b"9223372036854775808"), None);
+        assert_eq!(parse_integer(b"+789"), None);
+        assert_eq!(parse_integer(b""), None);
+        assert_eq!(parse_integer(b"-"), None);
+        assert_eq!(parse_integer(b" 1"), None);
+        assert_eq!(parse_integer(b"12.34"), None);
+
+        assert_eq!(numeric_value(b" -12.5kg"), -12.5);
+        assert_eq!(numeric_value(b"junk"), 0.0);
+
+        assert_eq!(human_numeric_value(b"2.5M"), Some(2.5 * 1024.0 * 1024.0));
+        assert_eq!(human_numeric_value(b" -1k"), Some(-1024.0));
+        assert_eq!(human_numeric_value(b"12"), Some(12.0));
+        assert_eq!(human_numeric_value(b"3X"), Some(3.0));
+        assert_eq!(human_numeric_value(b"-K"), None);
+        assert_eq!(human_numeric_value(b""), None);
+        // Only `k` is a unit in lowercase; `1e3` is just 1
+        assert_eq!(human_numeric_value(b"1e3"), Some(1.0));
+        assert_eq!(human_numeric_value(b"2m"), Some(2.0));
+
+        // Suffixes rank before values, as GNU sort orders them
+        let mut human: Vec<&[u8]> = vec![
+            b"1M", b"2000K", b"-1M", b"-2000K", b"0K", b"-0K", b"1e3", b"5", b"0.5k", b"1.5K",
+        ];
+        human.sort_by(|a, b| compare_human_numeric(a, b).then_with(|| a.cmp(b)));
+        let expected: Vec<&[u8]> = vec![
+            b"-1M", b"-2000K", b"-0K", b"0K", b"1e3", b"5", b"0.5k", b"1.5K", b"2000K", b"1M",
+        ];
+        assert_eq!(human, expected);
+
+        assert_eq!(general_numeric_value(b"1e3"), 1000.0);
+        assert_eq!(general_numeric_value(b" +2.5e-1x"), 0.25);
+        assert_eq!(general_numeric_value(b"1e"), 1.0);
+        assert_eq!(general_numeric_value(b"-Inf"), f64::NEG_INFINITY);
+        assert_eq!(general_numeric_value(b"infinity"), f64::INFINITY);
+        assert!(general_numeric_value(b"NaN").is_nan());
+        assert_eq!(general_numeric_value(b""), f64::NEG_INFINITY);
+        assert_eq!(general_numeric_value(b"abc"), f64::NEG_INFINITY);
+    }
+}
@@ -1,3 +1,5 @@
+use crate::adaptive_sort::{AdaptiveSort, SortAlgorithm};
+use crate::numeric::parse_integer;
 use crate::simd_compare::SIMDCompare;
 use crate::zero_copy::Line;
 use rayon::prelude::*;
@@ -5,6 +7,9 @@ use rayon::prelude::*;
 /// Achieves O(n) time complexity vs O(n log n) for comparison-based sorts
 use std::cmp::Ordering;
 
+/// Number of MSD radix buckets: one per byte value plus one for "line ended"
+const MSD_BUCKETS: usize = 257;
+
 /// Parallel radix sort for numeric data - can achieve 5-10x speedup
 pub struct RadixSort {
     /// Whether to use parallel processing
@@ -32,20 +37,40 @@ impl RadixSort {
             return;
         }
 
-        // Check if all lines are simple integers
-        if self.are_all_simple_integers(lines) {
-            if self.parallel && lines.len() > 10000 {
-                self.parallel_radix_sort_integers(lines);
+        // Check if all lines are simple integers; the sample can miss a
+        // few, in which case the radix sort declines and leaves the lines be
+        let sorted = self.are_all_simple_integers(lines)
+            && if self.parallel && lines.len() > 10000 {
+                self.parallel_radix_sort_integers(lines)
             } else {
-                self.sequential_radix_sort_integers(lines);
-            }
-        } else {
+                self.sequential_radix_sort_integers(lines)
+            };
+        if !sorted {
             // Fall back to comparison-based sort for complex numbers
             if self.parallel {
-                lines.par_sort_unstable_by(|a, b| a.compare_numeric(b));
+                lines.par_sort_unstable_by(numeric_then_bytes);
             } else {
-                lines.sort_unstable_by(|a, b| a.compare_numeric(b));
+                lines.sort_unstable_by(numeric_then_bytes);
+            }
+        }
+    }
+
+    /// Order each run of numerically equal lines bytewise, the whole-line
+    /// last resort `-n` applies without `-s`; the integer radix sort leaves
+    /// such runs, `007` and `7` say, in input order
+    pub fn order_equal_values_bytewise(&self, lines: &mut [Line]) {
+        let mut start = 0;
+        while start < lines.len() {
+            let mut end = start + 1;
+            while end < lines.len() && lines[start].compare_numeric(&lines[end]) == Ordering::Equal
+            {
+                end += 1;
+            }
+            if end - start > 1 {
+                lines[start..end]
+                    .sort_unstable_by(|a, b| unsafe { a.as_bytes().cmp(b.as_bytes()) });
             }
+            start = end;
         }
     }
 
@@ -53,7 +78,7 @@ impl RadixSort {
     fn sort_very_large_dataset(&self, lines: &mut [Line]) {
         if !self.parallel {
             // Fall back to sequential sort for very large single-threaded data
-            lines.sort_unstable_by(|a, b| a.compare_numeric(b));
+            lines.sort_unstable_by(numeric_then_bytes);
             return;
         }
 
@@ -63,10 +88,9 @@ impl RadixSort {
         // Sort chunks in parallel
         lines.par_chunks_mut(CHUNK_SIZE).for_each(|chunk| {
             // Use appropriate algorithm for each chunk
-            if self.are_all_simple_integers(chunk) {
-                self.sequential_radix_sort_integers(chunk);
-            } else {
-                chunk.sort_unstable_by(|a, b| a.compare_numeric(b));
+            if !(self.are_all_simple_integers(chunk) && self.sequential_radix_sort_integers(chunk))
+            {
+                chunk.sort_unstable_by(numeric_then_bytes);
             }
         });
 
@@ -124,7 +148,7 @@ impl RadixSort {
 
         // Merge the two halves
         while i < left.len() && j < right.len() {
-            if left[i].compare_numeric(&right[j]) != Ordering::Greater {
+            if numeric_then_bytes(&left[i], &right[j]) != Ordering::Greater {
                 temp.push(left[i]);
                 i += 1;
             } else {
@@ -177,83 +201,135 @@ impl RadixSort {
         SIMDCompare::is_all_digits_simd(&bytes[start..])
     }
 
-    /// Ultra-fast parallel radix sort for simple integers
-    fn parallel_radix_sort_integers(&self, lines: &mut [Line]) {
-        // Parse all integers in parallel
-        let mut values: Vec<(i64, usize)> = lines
+    /// Ultra-fast parallel radix sort for simple integers; returns false,
+    /// leaving `lines` as they were, if one of them is not a simple integer
+    fn parallel_radix_sort_integers(&self, lines: &mut [Line]) -> bool {
+        // Parse all integers in parallel, then reduce their range
+        let values: Option<Vec<(i64, usize)>> = lines
             .par_iter()
             .enumerate()
             .map(|(idx, line)| {
                 let value = unsafe {
                     let bytes = line.as_bytes();
-                    self.parse_integer_fast(bytes)
+                    self.integer_value(bytes)
                 };
-                (value, idx)
+                value.map(|value| (value, idx))
             })
             .collect();
+        let Some(mut values) = values else {
+            return false;
+        };
+        let range = values.par_iter().map(|&(value, _)| (value, value)).reduce(
+            || (i64::MAX, i64::MIN),
+            |(min_a, max_a), (min_b, max_b)| (min_a.min(min_b), max_a.max(max_b)),
+        );
 
-        // Parallel radix sort on the integers
-        self.parallel_radix_sort_pairs(&mut values);
+        self.sort_integer_pairs(&mut values, range);
 
         // Reconstruct the lines array based on sorted indices
         let original_lines: Vec<Line> = lines.to_vec();
         for (i, &(_, original_idx)) in values.iter().enumerate() {
             lines[i] = original_lines[original_idx];
         }
+        true
     }
 
-    /// Sequential radix sort for simple integers
-    fn sequential_radix_sort_integers(&self, lines: &mut [Line]) {
-        // Parse all integers
-        let mut values: Vec<(i64, usize)> = lines
+    /// Sequential radix sort for simple integers; returns false, leaving
+    /// `lines` as they were, if one of them is not a simple integer
+    fn sequential_radix_sort_integers(&self, lines: &mut [Line]) -> bool {
+        // Parse all integers, tracking their range in the same pass
+        let (mut min, mut max) = (i64::MAX, i64::MIN);
+        let values: Option<Vec<(i64, usize)>> = lines
             .iter()
             .enumerate()
             .map(|(idx, line)| {
                 let value = unsafe {
                     let bytes = line.as_bytes();
-                    self.parse_integer_fast(bytes)
-                };
-                (value, idx)
+                    self.integer_value(bytes)
+                }?;
+                min = min.min(value);
+                max = max.max(value);
+                Some((value, idx))
             })
             .collect();
+        let Some(mut values) = values else {
+            return false;
+        };
 
-        // Sequential radix sort
-        self.sequential_radix_sort_pairs(&mut values);
+        self.sort_integer_pairs(&mut values, (min, max));
 
         // Reconstruct lines
         let original_lines: Vec<Line> = lines.to_vec();
         for (i, &(_, original_idx)) in values.iter().enumerate() {
             lines[i] = original_lines[original_idx];
         }
+        true
     }
 
-    /// Fast integer parsing optimized for speed
-    fn parse_integer_fast(&self, bytes: &[u8]) -> i64 {
-        if bytes.is_empty() {
-            return 0;
-        }
-
-        let mut result: i64 = 0;
-        let mut start = 0;
-        let negative = if bytes[0] == b'-' {
-            start = 1;
-            true
-        } else if bytes[0] == b'+' {
-            start = 1;
-            false
+    /// Sort lines of simple integers by value, descending under `reverse`,
+    /// keeping equal values in input order as `-s` and `-u` need; returns
+    /// false, leaving `lines` as they were, if one of them is not a simple
+    /// integer
+    pub fn stable_sort_integer_lines(&self, lines: &mut [Line], reverse: bool) -> bool {
+        let parse = |line: &Line| self.integer_value(unsafe { line.as_bytes() });
+        let values: Option<Vec<i64>> = if self.parallel {
+            lines.par_iter().map(parse).collect()
         } else {
-            false
+            lines.iter().map(parse).collect()
         };
+        let Some(values) = values else {
+            return false;
+        };
+        let (Some(&min), Some(&max)) = (values.iter().min(), values.iter().max()) else {
+            return true;
+        };
+
+        // Distances from the smallest value (the largest when descending)
+        // order as the values do, and fit u64 however wide the range
+        let mut pairs: Vec<(u64, usize)> = values
+            .iter()
+            .enumerate()
+            .map(|(idx, &value)| {
+                let key = if reverse {
+                    (max as u64).wrapping_sub(value as u64)
+                } else {
+                    (value as u64).wrapping_sub(min as u64)
+                };
+                (key, idx)
+            })
+            .collect();
+        let span = (max as u64).wrapping_sub(min as u64);
+        lsd_radix_sort_pairs(&mut pairs, span);
+
+        let original_lines: Vec<Line> = lines.to_vec();
+        for (i, &(_, original_idx)) in pairs.iter().enumerate() {
+            lines[i] = original_lines[original_idx];
+        }
+        true
+    }
 
-        // Unrolled loop for better performance
-        for &byte in &bytes[start..] {
-            result = result * 10 + (byte - b'0') as i64;
+    /// Sort parsed `(value, index)` pairs whose values lie in `min..=max`,
+    /// picking counting or radix sort from the value range
+    fn sort_integer_pairs(&self, values: &mut [(i64, usize)], (min, max): (i64, i64)) {
+        if values.is_empty() {
+            return;
         }
 
-        if negative {
-            -result
+        match AdaptiveSort::select_integer_algorithm(values.len(), min, max) {
+            SortAlgorithm::CountingSort => AdaptiveSort::counting_sort(values, min, max),
+            _ if self.parallel => self.parallel_radix_sort_pairs(values),
+            _ => self.sequential_radix_sort_pairs(values),
+        }
+    }
+
+    /// Value of a line for the integer radix sorts: an empty line is 0, as
+    /// `-n` reads it, and anything else must be a plain integer to
+    /// [`parse_integer`]; `None` leaves the line to the comparison sort
+    fn integer_value(&self, bytes: &[u8]) -> Option<i64> {
+        if bytes.is_empty() {
+            Some(0)
         } else {
-            result
+            parse_integer(bytes)
         }
     }
 
@@ -265,7 +341,7 @@ impl RadixSort {
         const MAX_BITS: usize = 64;
 
         // Handle negative numbers by splitting and sorting separately
-        let (mut negatives, mut positives): (Vec<_>, Vec<_>) = values
+        let (negatives, mut positives): (Vec<_>, Vec<_>) = values
             .par_iter()
             .cloned()
             .partition(|(value, _)| *value < 0);
@@ -275,6 +351,12 @@ impl RadixSort {
             self.radix_sort_positive_parallel(&mut positives);
         }
 
+        // i64::MIN cannot be negated; it is the smallest value, so those
+        // entries simply go first
+        let (minimums, mut negatives): (Vec<_>, Vec<_>) = negatives
+            .into_iter()
+            .partition(|(value, _)| *value == i64::MIN);
+
         // Sort negatives by absolute value, then reverse
         if !negatives.is_empty() {
             // Convert to positive values for sorting
@@ -290,9 +372,10 @@ impl RadixSort {
         }
 
         // Combine results: negatives first, then positives
-        for (idx, item) in negatives
+        for (idx, item) in minimums
             .into_iter()
-            .chain(positives.into_iter())
+            .chain(negatives)
+            .chain(positives)
             .enumerate()
         {
             values[idx] = item;
@@ -352,11 +435,95 @@ impl RadixSort {
         }
     }
 
+    /// MSD (most significant byte first) radix sort over line bytes, giving
+    /// the same order as a plain byte-wise comparison
+    pub fn sort_lexicographic_lines(&self, lines: &mut [Line]) {
+        let mut scratch = lines.to_vec();
+
+        if !self.parallel {
+            Self::msd_radix_sort(lines, &mut scratch, 0);
+            return;
+        }
+
+        // Distribute on the first byte, then sort the buckets in parallel
+        let bounds = Self::msd_distribute(lines, &mut scratch, 0);
+        let mut buckets = Vec::with_capacity(MSD_BUCKETS);
+        let (mut rest, mut rest_scratch) = (lines, scratch.as_mut_slice());
+        for window in bounds.windows(2) {
+            let len = window[1] - window[0];
+            let (bucket, tail) = rest.split_at_mut(len);
+            let (bucket_scratch, scratch_tail) = rest_scratch.split_at_mut(len);
+            buckets.push((bucket, bucket_scratch));
+            rest = tail;
+            rest_scratch = scratch_tail;
+        }
+
+        // Bucket 0 holds lines that ended before this byte; they are all equal
+        buckets
+            .into_par_iter()
+            .skip(1)
+            .for_each(|(bucket, bucket_scratch)| Self::msd_radix_sort(bucket, bucket_scratch, 1));
+    }
+
+    /// Sort `lines`, all of which share their first `depth` bytes
+    fn msd_radix_sort(lines: &mut [Line], scratch: &mut [Line], depth: usize) {
+        // Small or deeply shared buckets are cheaper to finish by comparison
+        const COMPARISON_THRESHOLD: usize = 64;
+        const MAX_DEPTH: usize = 32;
+
+        if lines.len() <= 1 {
+            return;
+        }
+        if lines.len() <= COMPARISON_THRESHOLD || depth >= MAX_DEPTH {
+            lines.sort_unstable_by(|a, b| unsafe {
+                a.as_bytes()[depth..].cmp(&b.as_bytes()[depth..])
+            });
+            return;
+        }
+
+        let bounds = Self::msd_distribute(lines, scratch, depth);
+        for window in bounds.windows(2).skip(1) {
+            let range = window[0]..window[1];
+            Self::msd_radix_sort(&mut lines[range.clone()], &mut scratch[range], depth + 1);
+        }
+    }
+
+    /// Stable counting pass on the byte at `depth`: bucket 0 is for lines no
+    /// longer than `depth`, bucket `b + 1` for byte `b`. Returns bucket bounds.
+    fn msd_distribute(lines: &mut [Line], scratch: &mut [Line], depth: usize) -> Vec<usize> {
+        let bucket_of =
+            |line: &Line| unsafe { line.as_bytes().get(depth).map_or(0, |&b| b as usize + 1) };
+
+        let mut counts = [0usize; MSD_BUCKETS];
+        for line in lines.iter() {
+            counts[bucket_of(line)] += 1;
+        }
+
+        let mut bounds = Vec::with_capacity(MSD_BUCKETS + 1);
+        let mut positions = [0usize; MSD_BUCKETS];
+        let mut total = 0;
+        bounds.push(0);
+        for (bucket, &count) in counts.iter().enumerate() {
+            positions[bucket] = total;
+            total += count;
+            bounds.push(total);
+        }
+
+        for line in lines.iter() {
+            let bucket = bucket_of(line);
+            scratch[positions[bucket]] = *line;
+            positions[bucket] += 1;
+        }
+        lines.copy_from_slice(scratch);
+
+        bounds
+    }
+
     /// Insertion sort for small arrays
     fn insertion_sort(&self, lines: &mut [Line]) {
         for i in 1..lines.len() {
             let mut j = i;
-            while j > 0 && lines[j].compare_numeric(&lines[j - 1]) == Ordering::Less {
+            while j > 0 && numeric_then_bytes(&lines[j], &lines[j - 1]) == Ordering::Less {
                 lines.swap(j, j - 1);
                 j -= 1;
             }
@@ -364,6 +531,44 @@ impl RadixSort {
     }
 }
 
+/// Stable LSD radix sort of `(key, index)` pairs by key, a byte per pass,
+/// taking only as many passes as keys up to `max_key` need
+fn lsd_radix_sort_pairs(pairs: &mut Vec<(u64, usize)>, max_key: u64) {
+    let passes = (64 - max_key.leading_zeros() as usize + 7) / 8;
+    let mut scratch = vec![(0u64, 0usize); pairs.len()];
+
+    for pass in 0..passes {
+        let shift = pass * 8;
+        let digit = |key: u64| ((key >> shift) & 0xff) as usize;
+
+        let mut positions = [0usize; 256];
+        for &(key, _) in pairs.iter() {
+            positions[digit(key)] += 1;
+        }
+        let mut total = 0;
+        for position in positions.iter_mut() {
+            let count = *position;
+            *position = total;
+            total += count;
+        }
+
+        // Scattering in order keeps equal digits in their current order
+        for &(key, idx) in pairs.iter() {
+            let position = &mut positions[digit(key)];
+            scratch[*position] = (key, idx);
+            *position += 1;
+        }
+        std::mem::swap(pairs, &mut scratch);
+    }
+}
+
+/// `-n` order for lines the integer radix sort cannot take: numeric value,
+/// then the whole line bytewise, so that `N/A` follows the zeros as in GNU sort
+fn numeric_then_bytes(a: &Line, b: &Line) -> Ordering {
+    a.compare_numeric(b)
+        .then_with(|| unsafe { a.as_bytes().cmp(b.as_bytes()) })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -420,4 +625,245 @@ mod tests {
             assert_eq!(lines[3].as_bytes(), b"456");
         }
     }
+
+    #[test]
+    fn test_i64_min_sorts_first() {
+        let data: Vec<String> = (0..20_000)
+            .map(|i| match i % 5 {
+                0 => i64::MIN.to_string(),
+                1 => (i64::MIN + 1).to_string(),
+                2 => format!("-{i}"),
+                3 => i64::MAX.to_string(),
+                _ => i.to_string(),
+            })
+            .collect();
+        let mut expected: Vec<i64> = data
+            .iter()
+            .map(|s| s.parse().expect("valid integer"))
+            .collect();
+        expected.sort_unstable();
+
+        for parallel in [false, true] {
+            let mut lines: Vec<Line> = data.iter().map(|s| Line::new(s.as_bytes())).collect();
+            RadixSort::new(parallel).sort_numeric_lines(&mut lines);
+            let sorted: Vec<i64> = lines
+                .iter()
+                .map(|line| unsafe { std::str::from_utf8(line.as_bytes()).expect("valid UTF-8") })
+                .map(|s| s.parse().expect("valid integer"))
+                .collect();
+            assert_eq!(sorted, expected, "parallel={parallel}");
+        }
+    }
+
+    #[test]
+    fn test_fraction_just_past_the_sample() {
+        // Integers whose line 101 is `3.14`, just past the lines sampled
+        let mut texts: Vec<String> = (0..5_000i64)
+            .map(|i| ((i * 37) % 101).to_string())
+            .collect();
+        texts[100] = "3.14".to_string();
+        let lines: Vec<Line> = texts.iter().map(|t| Line::new(t.as_bytes())).collect();
+
+        for parallel in [false, true] {
+            let mut sorted = lines.clone();
+            RadixSort::new(parallel).sort_numeric_lines(&mut sorted);
+            let position = |text: &[u8]| {
+                sorted
+                    .iter()
+                    .position(|line| unsafe { line.as_bytes() } == text)
+                    .unwrap()
+            };
+            // Between the last 3 and the first 4
+            assert_eq!(position(b"3.14"), position(b"4") - 1, "parallel={parallel}");
+            assert!(sorted
+                .windows(2)
+                .all(|pair| numeric_then_bytes(&pair[0], &pair[1]) != Ordering::Greater));
+        }
+    }
+
+    #[test]
+    fn test_integers_past_i64_fall_back() {
+        // 25-digit values, well after the sample, would wrap in an i64
+        let texts: Vec<String> = (0..20_000i64)
+            .map(|i| match i % 1009 {
+                700 => "9999999999999999999999999".to_string(),
+                701 => "-9999999999999999999999999".to_string(),
+                702 => "1000000000000000000000000".to_string(),
+                _ => ((i * 7919) % 2001 - 1000).to_string(),
+            })
+            .collect();
+        let lines: Vec<Line> = texts.iter().map(|t| Line::new(t.as_bytes())).collect();
+
+        let mut expected = lines.clone();
+        expected.sort_by(numeric_then_bytes);
+        assert_eq!(
+            unsafe { expected[0].as_bytes() },
+            b"-9999999999999999999999999"
+        );
+        assert_eq!(
+            unsafe { expected[expected.len() - 1].as_bytes() },
+            b"9999999999999999999999999"
+        );
+
+        for parallel in [false, true] {
+            let mut sorted = lines.clone();
+            RadixSort::new(parallel).sort_numeric_lines(&mut sorted);
+            assert!(
+                sorted
+                    .iter()
+                    .zip(&expected)
+                    .all(|(a, b)| unsafe { a.as_bytes() == b.as_bytes() }),
+                "parallel={parallel}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_stray_terminators_do_not_corrupt_values() {
+        // A terminator left inside a line is not a digit: such lines take
+        // the comparison sort, which reads the number before it
+        let texts: [&[u8]; 4] = [b"10\r", b"2", b"-3\n", b"1"];
+        for parallel in [false, true] {
+            let mut lines: Vec<Line> = texts.iter().map(|t| Line::new(t)).collect();
+            RadixSort::new(parallel).sort_numeric_lines(&mut lines);
+            let sorted: Vec<Vec<u8>> = lines
+                .iter()
+                .map(|line| unsafe { line.as_bytes() }.to_vec())
+                .collect();
+            assert_eq!(
+                sorted,
+                [&b"-3\n"[..], b"1", b"2", b"10\r"],
+                "parallel={parallel}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_non_numeric_lines_past_the_sample() {
+        // Integers with a few `N/A` lines well after the first 100
+        let texts: Vec<String> = (0..20_000i64)
+            .map(|i| {
+                if i % 997 == 500 {
+                    "N/A".to_string()
+                } else {
+                    ((i * 7919) % 2001 - 1000).to_string()
+                }
+            })
+            .collect();
+        let lines: Vec<Line> = texts.iter().map(|t| Line::new(t.as_bytes())).collect();
+
+        // `N/A` has value 0 and sorts after the zeros
+        let mut expected = lines.clone();
+        expected.sort_by(numeric_then_bytes);
+        let n_a = expected
+            .iter()
+            .position(|line| unsafe { line.as_bytes() } == b"N/A")
+            .unwrap();
+        assert_eq!(unsafe { expected[n_a - 1].as_bytes() }, b"0");
+
+        for parallel in [false, true] {
+            let mut sorted = lines.clone();
+            RadixSort::new(parallel).sort_numeric_lines(&mut sorted);
+            assert!(
+                sorted
+                    .iter()
+                    .zip(&expected)
+                    .all(|(a, b)| unsafe { a.as_bytes() == b.as_bytes() }),
+                "parallel={parallel}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_msd_radix_matches_comparison_sort() {
+        // 1M random strings over a small alphabet, so many share long prefixes
+        let mut state = 42u64;
+        let mut next = move || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        let mut data = Vec::new();
+        for _ in 0..1_000_000 {
+            let len = next() % 24;
+            data.extend((0..len).map(|_| b"abc\x01\xff"[(next() % 5) as usize]));
+            data.push(b'\n');
+        }
+        let lines = crate::zero_copy::parse_lines(&data);
+
+        let mut expected = lines.clone();
+        expected.sort_unstable_by(|a, b| unsafe { a.as_bytes().cmp(b.as_bytes()) });
+
+        for parallel in [false, true] {
+            let mut sorted = lines.clone();
+            RadixSort::new(parallel).sort_lexicographic_lines(&mut sorted);
+            assert!(
+                sorted
+                    .iter()
+                    .zip(&expected)
+                    .all(|(a, b)| unsafe { a.as_bytes() == b.as_bytes() }),
+                "MSD radix order differs from comparison sort (parallel: {parallel})"
+            );
+        }
+    }
+
+    #[test]
+    fn test_small_range_integers_sort_stably() {
+        // Values in 0..1000 take the counting sort path
+        let texts: Vec<String> = (0..50_000u64)
+            .map(|i| ((i * 7919) % 1000).to_string())
+            .collect();
+        let lines: Vec<Line> = texts.iter().map(|t| Line::new(t.as_bytes())).collect();
+
+        let mut expected: Vec<usize> = (0..texts.len()).collect();
+        expected.sort_by_key(|&i| texts[i].parse::<i64>().unwrap());
+
+        for parallel in [false, true] {
+            let mut sorted = lines.clone();
+            RadixSort::new(parallel).sort_numeric_lines(&mut sorted);
+            // Same lines in the same order, including among equal values
+            assert!(sorted.iter().zip(&expected).all(|(line, &i)| std::ptr::eq(
+                unsafe { line.as_bytes() }.as_ptr(),
+                texts[i].as_ptr()
+            )));
+        }
+    }
+
+    #[test]
+    fn test_stable_integer_sort_keeps_equal_values_in_input_order() {
+        // Equal values written differently, across the whole i64 range
+        let texts: Vec<String> = (0..5_000i64)
+            .map(|i| match i % 5 {
+                0 => format!("{}", i % 17 - 8),
+                1 => format!("0{}", i % 17),
+                2 => i64::MIN.to_string(),
+                3 => i64::MAX.to_string(),
+                _ => "-0".to_string(),
+            })
+            .collect();
+        let lines: Vec<Line> = texts.iter().map(|t| Line::new(t.as_bytes())).collect();
+        let value = |i: usize| texts[i].parse::<i64>().unwrap();
+
+        for reverse in [false, true] {
+            let mut expected: Vec<usize> = (0..texts.len()).collect();
+            if reverse {
+                expected.sort_by_key(|&i| std::cmp::Reverse(value(i)));
+            } else {
+                expected.sort_by_key(|&i| value(i));
+            }
+            for parallel in [false, true] {
+                let mut sorted = lines.clone();
+                assert!(RadixSort::new(parallel).stable_sort_integer_lines(&mut sorted, reverse));
+                assert!(sorted.iter().zip(&expected).all(|(line, &i)| std::ptr::eq(
+                    unsafe { line.as_bytes() }.as_ptr(),
+                    texts[i].as_ptr()
+                )));
+            }
+        }
+
+        // `+1` is not a number to -n, so the comparison sort takes it
+        let mut signed = [Line::new(b"2"), Line::new(b"+1")];
+        assert!(!RadixSort::new(false).stable_sort_integer_lines(&mut signed, false));
+    }
 }
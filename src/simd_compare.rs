@@ -50,8 +50,8 @@ impl SIMDCompare {
 
         // Fallback to byte-by-byte comparison
         for i in 0..min_len {
-            let a_char = a[i].to_ascii_lowercase();
-            let b_char = b[i].to_ascii_lowercase();
+            let a_char = a[i].to_ascii_uppercase();
+            let b_char = b[i].to_ascii_uppercase();
             match a_char.cmp(&b_char) {
                 Ordering::Equal => continue,
                 other => return other,
@@ -212,8 +212,8 @@ impl SIMDCompare {
 
         unsafe {
             // Broadcast constants for case conversion
-            let upper_a = _mm256_set1_epi8(b'A' as i8);
-            let upper_z = _mm256_set1_epi8(b'Z' as i8);
+            let lower_a = _mm256_set1_epi8(b'a' as i8);
+            let lower_z = _mm256_set1_epi8(b'z' as i8);
             let case_diff = _mm256_set1_epi8(32);
 
             for i in 0..chunks {
@@ -223,18 +223,18 @@ impl SIMDCompare {
                 let mut va = _mm256_loadu_si256(a.as_ptr().add(offset) as *const __m256i);
                 let mut vb = _mm256_loadu_si256(b.as_ptr().add(offset) as *const __m256i);
 
-                // Convert to lowercase using SIMD
-                let a_is_upper = _mm256_and_si256(
-                    _mm256_cmpgt_epi8(va, _mm256_sub_epi8(upper_a, _mm256_set1_epi8(1))),
-                    _mm256_cmpgt_epi8(_mm256_add_epi8(upper_z, _mm256_set1_epi8(1)), va),
+                // Convert to uppercase using SIMD, as GNU's toupper folding does
+                let a_is_lower = _mm256_and_si256(
+                    _mm256_cmpgt_epi8(va, _mm256_sub_epi8(lower_a, _mm256_set1_epi8(1))),
+                    _mm256_cmpgt_epi8(_mm256_add_epi8(lower_z, _mm256_set1_epi8(1)), va),
                 );
-                let b_is_upper = _mm256_and_si256(
-                    _mm256_cmpgt_epi8(vb, _mm256_sub_epi8(upper_a, _mm256_set1_epi8(1))),
-                    _mm256_cmpgt_epi8(_mm256_add_epi8(upper_z, _mm256_set1_epi8(1)), vb),
+                let b_is_lower = _mm256_and_si256(
+                    _mm256_cmpgt_epi8(vb, _mm256_sub_epi8(lower_a, _mm256_set1_epi8(1))),
+                    _mm256_cmpgt_epi8(_mm256_add_epi8(lower_z, _mm256_set1_epi8(1)), vb),
                 );
 
-                va = _mm256_add_epi8(va, _mm256_and_si256(a_is_upper, case_diff));
-                vb = _mm256_add_epi8(vb, _mm256_and_si256(b_is_upper, case_diff));
+                va = _mm256_sub_epi8(va, _mm256_and_si256(a_is_lower, case_diff));
+                vb = _mm256_sub_epi8(vb, _mm256_and_si256(b_is_lower, case_diff));
 
                 // Compare converted vectors
                 let cmp = _mm256_cmpeq_epi8(va, vb);
@@ -245,8 +245,8 @@ impl SIMDCompare {
                     let diff_pos = (!mask).trailing_zeros() as usize;
                     let abs_pos = offset + diff_pos;
                     return a[abs_pos]
-                        .to_ascii_lowercase()
-                        .cmp(&b[abs_pos].to_ascii_lowercase());
+                        .to_ascii_uppercase()
+                        .cmp(&b[abs_pos].to_ascii_uppercase());
                 }
             }
         }
@@ -254,8 +254,8 @@ impl SIMDCompare {
         // Compare remaining bytes
         let remaining_start = chunks * chunk_size;
         for i in remaining_start..min_len {
-            let a_char = a[i].to_ascii_lowercase();
-            let b_char = b[i].to_ascii_lowercase();
+            let a_char = a[i].to_ascii_uppercase();
+            let b_char = b[i].to_ascii_uppercase();
             match a_char.cmp(&b_char) {
                 Ordering::Equal => continue,
                 other => return other,
@@ -316,6 +316,52 @@ impl SIMDCompare {
         let remaining_start = chunks * chunk_size;
         bytes[remaining_start..].iter().all(|&b| b.is_ascii_digit())
     }
+
+    /// Count occurrences of `needle`, e.g. line terminators to pre-size a
+    /// line index
+    #[inline]
+    pub fn count_byte_simd(bytes: &[u8], needle: u8) -> usize {
+        #[cfg(target_arch = "x86_64")]
+        {
+            if is_x86_feature_detected!("avx2") && bytes.len() >= 32 {
+                // SAFETY: AVX2 support was just detected
+                return unsafe { Self::count_byte_avx2(bytes, needle) };
+            }
+        }
+
+        // Fallback
+        bytes.iter().filter(|&&b| b == needle).count()
+    }
+
+    /// AVX2-accelerated byte counting; compiled for AVX2 so the intrinsics
+    /// inline into the loop
+    #[cfg(target_arch = "x86_64")]
+    #[target_feature(enable = "avx2")]
+    unsafe fn count_byte_avx2(bytes: &[u8], needle: u8) -> usize {
+        use std::arch::x86_64::*;
+
+        let chunk_size = 32;
+        let chunks = bytes.len() / chunk_size;
+        let mut count = 0;
+
+        let target = _mm256_set1_epi8(needle as i8);
+        for i in 0..chunks {
+            let offset = i * chunk_size;
+            let v = _mm256_loadu_si256(bytes.as_ptr().add(offset) as *const __m256i);
+
+            // One mask bit per matching byte
+            let mask = _mm256_movemask_epi8(_mm256_cmpeq_epi8(v, target)) as u32;
+            count += mask.count_ones() as usize;
+        }
+
+        // Count remaining bytes
+        let remaining_start = chunks * chunk_size;
+        count
+            + bytes[remaining_start..]
+                .iter()
+                .filter(|&&b| b == needle)
+                .count()
+    }
 }
 
 #[cfg(test)]
@@ -342,6 +388,60 @@ mod tests {
         assert_eq!(result, Ordering::Equal);
     }
 
+    #[test]
+    fn test_case_insensitive_folds_only_letters() {
+        // Bytes at the edges of both letter ranges, and some outside ASCII
+        let edges = [
+            b'@', b'A', b'Z', b'[', b'_', b'`', b'a', b'z', b'{', b'|', 0x7f, 0x80, 0xc1, 0xff,
+        ];
+        let scalar = |a: &[u8], b: &[u8]| {
+            let fold = |s: &[u8]| s.iter().map(u8::to_ascii_uppercase).collect::<Vec<u8>>();
+            fold(a).cmp(&fold(b))
+        };
+
+        for &x in &edges {
+            for &y in &edges {
+                // Differences inside the first vector, past it, and in the
+                // scalar tail, on inputs short enough for the scalar path too
+                for (len, pos) in [(8, 3), (40, 5), (40, 31), (70, 33), (70, 66)] {
+                    let mut a = vec![b'm'; len];
+                    let mut b = a.clone();
+                    a[pos] = x;
+                    b[pos] = y;
+                    let expected = scalar(&a, &b);
+                    assert_eq!(
+                        SIMDCompare::compare_case_insensitive_simd(&a, &b),
+                        expected,
+                        "{x:#x} vs {y:#x} at {pos} of {len}"
+                    );
+                    #[cfg(target_arch = "x86_64")]
+                    if is_x86_feature_detected!("avx2") {
+                        assert_eq!(
+                            SIMDCompare::compare_case_insensitive_avx2(&a, &b),
+                            expected,
+                            "{x:#x} vs {y:#x} at {pos} of {len}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_simd_byte_count() {
+        // Lengths on both sides of the 32-byte vector width
+        for len in [0, 1, 31, 32, 33, 64, 100, 1000] {
+            let bytes: Vec<u8> = (0..len)
+                .map(|i| if i % 3 == 0 { b'\n' } else { b'x' })
+                .collect();
+            for needle in [b'\n', b'x', 0] {
+                let expected = bytes.iter().filter(|&&b| b == needle).count();
+                assert_eq!(SIMDCompare::count_byte_simd(&bytes, needle), expected);
+            }
+        }
+        assert_eq!(SIMDCompare::count_byte_simd(&[0xff; 40], 0xff), 40);
+    }
+
     #[test]
     fn test_simd_digit_detection() {
         assert!(SIMDCompare::is_all_digits_simd(b"123456789"));
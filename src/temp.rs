@@ -0,0 +1,478 @@
+//! Temporary files and directories used while sorting
+//!
+//! Temporaries go in the `-T` directory, else `$TMPDIR`, else the system
+//! default; when a directory cannot be written the next one is tried.
+//! Creation failures name the directories and the settings that chose them,
+//! as GNU sort's "cannot create temporary file in DIR" does.
+//!
+//! Every live temporary is recorded, so that once [`remove_on_interrupt`]
+//! is installed (with the `signal-cleanup` feature) a signal that kills the
+//! process does not leave them behind.
+
+use std::ffi::OsString;
+use std::fmt;
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tempfile::{NamedTempFile, TempDir};
+
+/// Temporaries that exist right now; each leaves the list when dropped
+static CREATED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Remember `path` for removal on interrupt
+fn record(path: &Path) {
+    CREATED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .push(path.to_path_buf());
+}
+
+/// Stop tracking `path`, which its owner is about to remove
+fn forget(path: &Path) {
+    let mut created = CREATED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    if let Some(index) = created.iter().rposition(|recorded| recorded == path) {
+        created.swap_remove(index);
+    }
+}
+
+/// A temporary directory or file that stays recorded for removal on
+/// interrupt for as long as it lives; it derefs to the wrapped
+/// [`TempDir`] or [`NamedTempFile`]
+#[derive(Debug)]
+pub struct Temporary<T> {
+    inner: T,
+    path: PathBuf,
+}
+
+impl<T> Temporary<T> {
+    fn recorded(inner: T, path: &Path) -> Self {
+        record(path);
+        Self {
+            inner,
+            path: path.to_path_buf(),
+        }
+    }
+}
+
+impl<T> Deref for Temporary<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<T> DerefMut for Temporary<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.inner
+    }
+}
+
+impl<T> Drop for Temporary<T> {
+    fn drop(&mut self) {
+        forget(&self.path);
+    }
+}
+
+/// Whether `path` is currently recorded
+#[cfg(test)]
+fn is_recorded(path: &Path) -> bool {
+    CREATED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .iter()
+        .any(|recorded| recorded == path)
+}
+
+/// Remove every recorded temporary that still exists
+#[cfg(all(unix, feature = "signal-cleanup"))]
+fn remove_created() {
+    let created = CREATED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    for path in created.iter() {
+        if path.is_dir() {
+            let _ = std::fs::remove_dir_all(path);
+        } else {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// On SIGHUP, SIGINT, SIGQUIT and SIGTERM, remove the temporaries before
+/// the signal ends the process, as GNU sort does; signals that were being
+/// ignored stay ignored. Installed once, by the `sort` binary; a no-op
+/// without the `signal-cleanup` feature, off Unix or if the handler cannot
+/// be set up.
+pub fn remove_on_interrupt() {
+    #[cfg(all(unix, feature = "signal-cleanup"))]
+    {
+        static INSTALL: std::sync::Once = std::sync::Once::new();
+        INSTALL.call_once(|| {
+            let _ = interrupt::install();
+        });
+    }
+}
+
+/// The signal handler only writes the signal number to a pipe; a thread
+/// reading the other end does the removal, which is not safe to do inside
+/// a handler, then re-raises the signal with its default action
+#[cfg(all(unix, feature = "signal-cleanup"))]
+mod interrupt {
+    use std::io;
+    use std::sync::atomic::{AtomicI32, Ordering};
+
+    const SIGNALS: [libc::c_int; 4] = [libc::SIGHUP, libc::SIGINT, libc::SIGQUIT, libc::SIGTERM];
+
+    /// Write end of the pipe to the cleanup thread
+    static PIPE: AtomicI32 = AtomicI32::new(-1);
+
+    extern "C" fn on_signal(signal: libc::c_int) {
+        let byte = signal as u8;
+        unsafe {
+            libc::write(
+                PIPE.load(Ordering::Relaxed),
+                &byte as *const u8 as *const libc::c_void,
+                1,
+            );
+        }
+    }
+
+    pub(super) fn install() -> io::Result<()> {
+        let mut fds = [0; 2];
+        if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let [read_end, write_end] = fds;
+        PIPE.store(write_end, Ordering::Relaxed);
+
+        std::thread::Builder::new()
+            .name("sort-cleanup".to_string())
+            .spawn(move || {
+                let mut byte = 0u8;
+                let read =
+                    unsafe { libc::read(read_end, &mut byte as *mut u8 as *mut libc::c_void, 1) };
+                if read == 1 {
+                    super::remove_created();
+                    let signal = libc::c_int::from(byte);
+                    unsafe {
+                        libc::signal(signal, libc::SIG_DFL);
+                        libc::raise(signal);
+                    }
+                }
+            })?;
+
+        let handler = on_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        for signal in SIGNALS {
+            unsafe {
+                if libc::signal(signal, handler) == libc::SIG_IGN {
+                    libc::signal(signal, libc::SIG_IGN);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Setting that chose the temporary directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TempDirSource {
+    /// `-T` / `--temporary-directory`
+    Option,
+    /// The `TMPDIR` environment variable
+    Environment,
+    /// The system default
+    Default,
+}
+
+impl fmt::Display for TempDirSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TempDirSource::Option => write!(f, "-T"),
+            TempDirSource::Environment => write!(f, "TMPDIR"),
+            TempDirSource::Default => write!(f, "the system default"),
+        }
+    }
+}
+
+/// Directories for temporaries in the order they are tried, given the `-T`
+/// setting if any; an empty `TMPDIR` counts as unset
+pub fn temp_locations(configured: Option<&str>) -> Vec<(PathBuf, TempDirSource)> {
+    candidate_locations(configured, std::env::var_os("TMPDIR"), default_temp_dir())
+}
+
+/// The system default directory; `std::env::temp_dir` would read `TMPDIR`
+fn default_temp_dir() -> PathBuf {
+    if cfg!(unix) {
+        PathBuf::from("/tmp")
+    } else {
+        std::env::temp_dir()
+    }
+}
+
+/// `-T`, then `TMPDIR`, then `default`, each directory listed once
+fn candidate_locations(
+    configured: Option<&str>,
+    tmpdir: Option<OsString>,
+    default: PathBuf,
+) -> Vec<(PathBuf, TempDirSource)> {
+    let mut locations: Vec<(PathBuf, TempDirSource)> = Vec::new();
+    let candidates = [
+        configured.map(|dir| (PathBuf::from(dir), TempDirSource::Option)),
+        tmpdir
+            .filter(|dir| !dir.is_empty())
+            .map(|dir| (PathBuf::from(dir), TempDirSource::Environment)),
+        Some((default, TempDirSource::Default)),
+    ];
+    for (dir, source) in candidates.into_iter().flatten() {
+        if !locations.iter().any(|(listed, _)| *listed == dir) {
+            locations.push((dir, source));
+        }
+    }
+    locations
+}
+
+/// Create a temporary directory in the first configured location that allows it
+pub fn create_temp_dir(configured: Option<&str>) -> io::Result<Temporary<TempDir>> {
+    let created = create_in_first(&temp_locations(configured), |dir| tempfile::tempdir_in(dir))?;
+    let path = created.path().to_path_buf();
+    Ok(Temporary::recorded(created, &path))
+}
+
+/// Create a temporary file in the first configured location that allows it
+pub fn create_temp_file(configured: Option<&str>) -> io::Result<Temporary<NamedTempFile>> {
+    let created = create_in_first(&temp_locations(configured), |dir| {
+        NamedTempFile::new_in(dir)
+    })?;
+    let path = created.path().to_path_buf();
+    Ok(Temporary::recorded(created, &path))
+}
+
+/// Run `create` on each location until one succeeds; if none does, the
+/// error lists every directory tried with its own failure
+fn create_in_first<T>(
+    locations: &[(PathBuf, TempDirSource)],
+    create: impl Fn(&Path) -> io::Result<T>,
+) -> io::Result<T> {
+    let mut failures = Vec::new();
+    for (dir, source) in locations {
+        match create(dir) {
+            Ok(created) => return Ok(created),
+            Err(err) => failures.push((err, dir, *source)),
+        }
+    }
+
+    match failures.len() {
+        0 => Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no directory for temporary files",
+        )),
+        1 => {
+            let (err, dir, source) = failures.remove(0);
+            Err(creation_error(err, dir, Some(source)))
+        }
+        _ => {
+            let kind = failures[failures.len() - 1].0.kind();
+            let tried: Vec<String> = failures
+                .iter()
+                .map(|(err, dir, source)| format!("'{}' (set by {source}): {err}", dir.display()))
+                .collect();
+            Err(io::Error::new(
+                kind,
+                format!(
+                    "cannot create temporary file in any of {}",
+                    tried.join("; ")
+                ),
+            ))
+        }
+    }
+}
+
+/// Create a temporary file inside `dir`, a directory from [`create_temp_dir`]
+/// whose record covers the file too
+pub fn create_temp_file_in(dir: &Path) -> io::Result<NamedTempFile> {
+    NamedTempFile::new_in(dir).map_err(|e| creation_error(e, dir, None))
+}
+
+/// Wrap a creation failure with the directory and where it came from,
+/// keeping the original error kind
+fn creation_error(err: io::Error, dir: &Path, source: Option<TempDirSource>) -> io::Error {
+    let origin = match source {
+        Some(source) => format!(" (set by {source})"),
+        None => String::new(),
+    };
+    io::Error::new(
+        err.kind(),
+        format!(
+            "cannot create temporary file in '{}'{origin}: {err}",
+            dir.display()
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A path below a regular file, which cannot be written even by root
+    fn unwritable_dir(scratch: &Path, name: &str) -> PathBuf {
+        let blocker = scratch.join(name);
+        std::fs::write(&blocker, b"").unwrap();
+        blocker.join("tmp")
+    }
+
+    #[test]
+    fn test_unwritable_temp_dir_is_named() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = unwritable_dir(scratch.path(), "file");
+        let locations = [(dir.clone(), TempDirSource::Option)];
+
+        let err = create_in_first(&locations, |dir| tempfile::tempdir_in(dir))
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string().split(": ").next().unwrap(),
+            format!(
+                "cannot create temporary file in '{}' (set by -T)",
+                dir.display()
+            )
+        );
+        assert!(temp_locations(None)
+            .iter()
+            .all(|(_, source)| *source != TempDirSource::Option));
+    }
+
+    #[test]
+    fn test_unwritable_temp_dirs_fall_back_in_order() {
+        let scratch = tempfile::tempdir().unwrap();
+        let option = unwritable_dir(scratch.path(), "option");
+        let env = unwritable_dir(scratch.path(), "env");
+        let option_str = option.to_str().unwrap();
+
+        // An unwritable -T falls back to TMPDIR, and an empty TMPDIR is skipped
+        let locations =
+            candidate_locations(Some(option_str), Some(scratch.path().into()), env.clone());
+        let created = create_in_first(&locations, |dir| NamedTempFile::new_in(dir)).unwrap();
+        assert_eq!(created.path().parent(), Some(scratch.path()));
+        assert_eq!(
+            candidate_locations(None, Some(OsString::new()), env.clone()),
+            [(env.clone(), TempDirSource::Default)]
+        );
+
+        // With every directory unwritable the error lists each one
+        let default = unwritable_dir(scratch.path(), "default");
+        let locations = candidate_locations(Some(option_str), Some(env.clone().into()), default);
+        let message = create_in_first(&locations, |dir| tempfile::tempdir_in(dir))
+            .err()
+            .unwrap()
+            .to_string();
+        assert!(message.starts_with("cannot create temporary file in any of "));
+        assert!(message.contains(&format!("'{}' (set by -T): ", option.display())));
+        assert!(message.contains(&format!("'{}' (set by TMPDIR): ", env.display())));
+        assert!(message.contains("(set by the system default): "));
+    }
+
+    #[test]
+    fn test_dropped_temporaries_are_forgotten() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = create_temp_dir(scratch.path().to_str()).unwrap();
+        let file = create_temp_file(scratch.path().to_str()).unwrap();
+        let (dir_path, file_path) = (dir.path().to_path_buf(), file.path().to_path_buf());
+        assert!(is_recorded(&dir_path) && is_recorded(&file_path));
+
+        // Files in a temporary directory are covered by its record
+        let chunk = create_temp_file_in(dir.path()).unwrap();
+        assert!(!is_recorded(chunk.path()));
+
+        drop(chunk);
+        drop(dir);
+        drop(file);
+        assert!(!is_recorded(&dir_path) && !is_recorded(&file_path));
+    }
+
+    /// Temporary directory the interrupted sort of
+    /// [`test_interrupt_removes_temporaries`] runs with, set only in the
+    /// child process that test starts
+    #[cfg(all(unix, feature = "signal-cleanup"))]
+    const INTERRUPT_DIR_VAR: &str = "GNU_SORT_TEST_INTERRUPT_DIR";
+
+    /// Runs a real multi-file sort in a child copy of the test binary, one
+    /// of its inputs a stdin pipe that is held open so that the sort is
+    /// still copying it, and interrupts it once its temporaries exist
+    #[cfg(all(unix, feature = "signal-cleanup"))]
+    #[test]
+    fn test_interrupt_removes_temporaries() {
+        use std::io::Write;
+        use std::os::unix::process::ExitStatusExt;
+        use std::time::{Duration, Instant};
+
+        // In the child: sort a file and stdin, with temporaries in the
+        // given directory, until the parent's signal ends the process
+        if let Some(dir) = std::env::var_os(INTERRUPT_DIR_VAR) {
+            let dir = PathBuf::from(dir);
+            let input = dir.with_extension("input");
+            std::fs::write(&input, b"b\na\n").unwrap();
+            let args = [
+                "sort",
+                "-T",
+                dir.to_str().unwrap(),
+                input.to_str().unwrap(),
+                "-",
+            ];
+            let (config, files) = crate::config::SortConfig::from_args(&args).unwrap();
+            remove_on_interrupt();
+            let _ = crate::sort(&config, &files);
+            panic!("sort finished without being interrupted");
+        }
+
+        let scratch = tempfile::tempdir().unwrap();
+        let temp_dir = scratch.path().join("tmp");
+        std::fs::create_dir(&temp_dir).unwrap();
+        let mut child = std::process::Command::new(std::env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "temp::tests::test_interrupt_removes_temporaries",
+                "--nocapture",
+            ])
+            .env(INTERRUPT_DIR_VAR, &temp_dir)
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        let mut stdin = child.stdin.take().unwrap();
+        stdin.write_all(b"d\nc\n").unwrap();
+
+        // Wait for the sort's temporary directory and its copy of stdin
+        let count = || walk_count(&temp_dir);
+        let deadline = Instant::now() + Duration::from_secs(30);
+        while count() < 2 {
+            assert!(
+                Instant::now() < deadline,
+                "sort never created its temporaries"
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+
+        unsafe { libc::kill(child.id() as libc::pid_t, libc::SIGINT) };
+        let status = child.wait().unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGINT));
+        assert_eq!(count(), 0);
+        drop(stdin);
+    }
+
+    /// Files and directories below `dir`
+    #[cfg(all(unix, feature = "signal-cleanup"))]
+    fn walk_count(dir: &Path) -> usize {
+        std::fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| 1 + walk_count(&entry.path()))
+                    .sum()
+            })
+            .unwrap_or(0)
+    }
+}
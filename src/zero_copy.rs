@@ -52,12 +52,21 @@ impl Line {
 
         let bytes = unsafe { self.as_bytes() };
 
-        // If no separator specified, use whitespace
-        if separator.is_none() {
-            return self.extract_field_by_whitespace(bytes, field_num);
+        // If no separator specified, fields are separated by runs of blanks
+        let Some(separator) = separator else {
+            return self.extract_field_by_blanks(field_num);
+        };
+
+        // A multibyte character separates fields by its UTF-8 encoding
+        if !separator.is_ascii() {
+            let mut encoded = [0; 4];
+            return self.extract_field_by_string(
+                field_num,
+                separator.encode_utf8(&mut encoded).as_bytes(),
+            );
         }
 
-        let sep_byte = separator.unwrap() as u8;
+        let sep_byte = separator as u8;
         let mut field_count = 1;
         let mut field_start = 0;
 
@@ -79,79 +88,94 @@ impl Line {
         None
     }
 
-    /// Extract field by whitespace (default behavior when no separator is specified)
-    /// Fields include leading whitespace from previous field separator (GNU sort behavior)
-    fn extract_field_by_whitespace<'a>(
-        &self,
-        bytes: &'a [u8],
-        field_num: usize,
-    ) -> Option<&'a [u8]> {
-        if field_num == 1 {
-            // Special case: field 1 starts at beginning of line
-            // Skip leading whitespace to find start of field 1
-            let mut field_start = 0;
-            for (i, &byte) in bytes.iter().enumerate() {
-                if byte != b' ' && byte != b'\t' {
-                    field_start = i;
-                    break;
-                }
-            }
-
-            // Find the end of field 1 (first whitespace or end of line)
-            for (i, &byte) in bytes[field_start..].iter().enumerate() {
-                if byte == b' ' || byte == b'\t' {
-                    return Some(&bytes[field_start..field_start + i]);
-                }
-            }
-            return Some(&bytes[field_start..]); // Entire remaining line is field 1
+    /// Extract a field delimited by a multi-byte separator string
+    /// Fields are 1-indexed; a trailing separator ends the line with an empty field
+    pub fn extract_field_by_string(&self, field_num: usize, separator: &[u8]) -> Option<&[u8]> {
+        if field_num == 0 || separator.is_empty() {
+            return None;
         }
 
-        // For fields > 1, use a different approach
-        // First, skip initial whitespace and find all field boundaries
-        let mut field_boundaries = Vec::new();
-        let mut in_field = false;
+        let bytes = unsafe { self.as_bytes() };
         let mut field_start = 0;
 
-        for (i, &byte) in bytes.iter().enumerate() {
-            let is_whitespace = byte == b' ' || byte == b'\t';
-
-            if !is_whitespace && !in_field {
-                // Starting a new field
-                field_start = i;
-                in_field = true;
-            } else if is_whitespace && in_field {
-                // Ending a field
-                field_boundaries.push(field_start..i);
-                in_field = false;
-            }
+        for _ in 1..field_num {
+            let offset = bytes[field_start..]
+                .windows(separator.len())
+                .position(|window| window == separator)?;
+            field_start += offset + separator.len();
         }
 
-        // Handle case where line ends with a field (no trailing whitespace)
-        if in_field {
-            field_boundaries.push(field_start..bytes.len());
-        }
+        let field_end = bytes[field_start..]
+            .windows(separator.len())
+            .position(|window| window == separator)
+            .map_or(bytes.len(), |offset| field_start + offset);
+
+        Some(&bytes[field_start..field_end])
+    }
 
-        if field_num > field_boundaries.len() {
+    /// Extract a blank-separated field as GNU sort does: each field is a run
+    /// of blanks followed by a run of non-blanks, so the first field keeps any
+    /// blanks that start the line
+    pub fn extract_field_by_blanks(&self, field_num: usize) -> Option<&[u8]> {
+        if field_num == 0 {
             return None;
         }
 
-        let target_field = &field_boundaries[field_num - 1];
-
-        // For field 1, return just the field content
-        if field_num == 1 {
-            return Some(&bytes[target_field.clone()]);
+        let bytes = unsafe { self.as_bytes() };
+        let is_blank = |b: u8| b == b' ' || b == b'\t';
+        let mut start = 0;
+        for field in 1..=field_num {
+            let mut end = start;
+            while end < bytes.len() && is_blank(bytes[end]) {
+                end += 1;
+            }
+            while end < bytes.len() && !is_blank(bytes[end]) {
+                end += 1;
+            }
+            if field == field_num {
+                return (start < bytes.len() || field == 1).then(|| &bytes[start..end]);
+            }
+            start = end;
         }
+        None
+    }
 
-        // For fields > 1, include the whitespace before the field
-        // Find where the previous field ended
-        let prev_field_end = if field_num > 1 {
-            field_boundaries[field_num - 2].end
-        } else {
-            0
-        };
+    /// Extract the region `key` selects, splitting fields as `config` says:
+    /// on the separator string, on `separator`, or on blanks
+    pub fn extract_key_with_config(
+        &self,
+        key: &crate::config::SortKey,
+        separator: Option<char>,
+        config: &crate::config::SortConfig,
+    ) -> Option<&[u8]> {
+        self.extract_key_by(key, config.utf8_positions, |field| {
+            self.field_with_config(field, separator, config)
+        })
+    }
+
+    /// Field `field_num` (1-based), split as [`Self::extract_key_with_config`] does
+    fn field_with_config(
+        &self,
+        field_num: usize,
+        separator: Option<char>,
+        config: &crate::config::SortConfig,
+    ) -> Option<&[u8]> {
+        match config.field_separator_string.as_deref() {
+            Some(sep) => self.extract_field_by_string(field_num, sep.as_bytes()),
+            None => self.extract_field(field_num, separator),
+        }
+    }
 
-        // The field includes whitespace from previous field end to current field end
-        Some(&bytes[prev_field_end..target_field.end])
+    /// First field referenced by `keys` that this line does not have
+    pub fn missing_key_field(
+        &self,
+        keys: &[crate::config::SortKey],
+        separator: Option<char>,
+        config: &crate::config::SortConfig,
+    ) -> Option<usize> {
+        keys.iter()
+            .flat_map(|key| std::iter::once(key.start_field).chain(key.end_field))
+            .find(|&field| self.field_with_config(field, separator, config).is_none())
     }
 
     /// Extract a key region from the line based on SortKey specification
@@ -160,158 +184,113 @@ impl Line {
         key: &crate::config::SortKey,
         separator: Option<char>,
     ) -> Option<&[u8]> {
-        // Extract the starting field
-        let start_field_data = self.extract_field(key.start_field, separator)?;
-
-        // If no end field specified, use just the start field
-        if key.end_field.is_none() {
-            // Apply character positions if specified
-            if let Some(start_char) = key.start_char {
-                if start_char > 0 && start_char <= start_field_data.len() {
-                    return Some(&start_field_data[start_char - 1..]);
-                }
-            }
-            return Some(start_field_data);
-        }
+        self.extract_key_by(key, false, |field| self.extract_field(field, separator))
+    }
+
+    /// Extract a key region with fields split on a multi-byte separator string
+    pub fn extract_key_with_string(
+        &self,
+        key: &crate::config::SortKey,
+        separator: &[u8],
+    ) -> Option<&[u8]> {
+        self.extract_key_by(key, false, |field| {
+            self.extract_field_by_string(field, separator)
+        })
+    }
 
-        // Complex case: range of fields
-        // For now, just extract from start field to end field
-        // This is a simplified implementation
+    /// Extract a key region, locating fields with `field_at`; character
+    /// positions count UTF-8 characters when `utf8` is set, else bytes
+    fn extract_key_by<'a>(
+        &'a self,
+        key: &crate::config::SortKey,
+        utf8: bool,
+        field_at: impl Fn(usize) -> Option<&'a [u8]>,
+    ) -> Option<&'a [u8]> {
         let bytes = unsafe { self.as_bytes() };
 
         // Find start position
-        let start_pos = if let Some(field_data) = self.extract_field(key.start_field, separator) {
-            let offset = field_data.as_ptr() as usize - bytes.as_ptr() as usize;
-            if let Some(start_char) = key.start_char {
-                if start_char > 0 && start_char <= field_data.len() {
-                    offset + start_char - 1
-                } else {
-                    offset
-                }
-            } else {
-                offset
-            }
-        } else {
-            return None;
+        let start_field_data = field_at(key.start_field)?;
+        let start_pos = Self::position_in_field(
+            bytes,
+            start_field_data,
+            key.start_skip_blanks,
+            key.start_char.map_or(0, |c| c - 1),
+            utf8,
+        );
+
+        // Find end position; without an end field the key runs to the end of the line
+        let end_pos = match key.end_field {
+            None => bytes.len(),
+            Some(end_field) => match field_at(end_field) {
+                Some(field_data) => match key.end_char {
+                    Some(end_char) => Self::position_in_field(
+                        bytes,
+                        field_data,
+                        key.end_skip_blanks,
+                        end_char,
+                        utf8,
+                    ),
+                    None => Self::field_end(bytes, field_data),
+                },
+                None => bytes.len(),
+            },
         };
 
-        // Find end position
-        let end_pos = if let Some(end_field) = key.end_field {
-            if let Some(field_data) = self.extract_field(end_field, separator) {
-                let offset = field_data.as_ptr() as usize - bytes.as_ptr() as usize;
-                let field_end = offset + field_data.len();
-                if let Some(end_char) = key.end_char {
-                    if end_char > 0 && end_char <= field_data.len() {
-                        offset + end_char
-                    } else {
-                        field_end
-                    }
-                } else {
-                    field_end
-                }
-            } else {
-                bytes.len()
-            }
+        Some(&bytes[start_pos..end_pos.max(start_pos)])
+    }
+
+    /// Offset into `bytes` that lies `chars` bytes past the start of `field`,
+    /// optionally after skipping the field's leading blanks; like GNU sort,
+    /// the position may run past the field and is only clamped to the line
+    fn position_in_field(
+        bytes: &[u8],
+        field: &[u8],
+        skip_blanks: bool,
+        chars: usize,
+        utf8: bool,
+    ) -> usize {
+        let offset = field.as_ptr() as usize - bytes.as_ptr() as usize;
+        let rest = &bytes[offset..];
+        let blanks = if skip_blanks {
+            rest.iter()
+                .position(|&b| b != b' ' && b != b'\t')
+                .unwrap_or(rest.len())
         } else {
-            bytes.len()
+            0
         };
-
-        if start_pos < end_pos && start_pos < bytes.len() {
-            Some(&bytes[start_pos..end_pos.min(bytes.len())])
+        let chars = if utf8 {
+            utf8_char_offset(&rest[blanks..], chars)
         } else {
-            None
-        }
+            chars
+        };
+        offset + (blanks + chars).min(rest.len())
+    }
+
+    /// Offset into `bytes` just past the end of `field`
+    fn field_end(bytes: &[u8], field: &[u8]) -> usize {
+        field.as_ptr() as usize - bytes.as_ptr() as usize + field.len()
     }
 
-    /// Fast numeric parsing for simple integers (optimized path)
+    /// Fast numeric parsing for simple integers (optimized path); see
+    /// [`crate::numeric::parse_integer`]
     pub fn parse_int(&self) -> Option<i64> {
         // SAFETY: as_bytes() is safe here because Line was created from valid memory
         // that remains valid throughout the sorting operation
-        let bytes = unsafe { self.as_bytes() };
-        if bytes.is_empty() {
-            return Some(0);
-        }
-
-        let mut start = 0;
-        let negative = if bytes[0] == b'-' {
-            start = 1;
-            true
-        } else {
-            false
-        };
-
-        if start >= bytes.len() {
-            return None;
-        }
-
-        let mut result: i64 = 0;
-        for &byte in &bytes[start..] {
-            if !byte.is_ascii_digit() {
-                return None;
-            }
-            result = result.checked_mul(10)?;
-            result = result.checked_add((byte - b'0') as i64)?;
-        }
-
-        Some(if negative { -result } else { result })
+        crate::numeric::parse_integer(unsafe { self.as_bytes() })
     }
 
     /// Parse as general numeric (supports scientific notation, inf, nan)
     pub fn parse_general_numeric(&self) -> f64 {
-        let bytes = unsafe { self.as_bytes() };
-        if let Ok(s) = std::str::from_utf8(bytes) {
-            let trimmed = s.trim();
-
-            // Handle special cases
-            if trimmed.is_empty() {
-                return 0.0;
-            }
-
-            // Parse as float (handles scientific notation automatically)
-            match trimmed.parse::<f64>() {
-                Ok(val) => val,
-                Err(_) => {
-                    // Check for special strings
-                    let lower = trimmed.to_lowercase();
-                    if lower == "inf" || lower == "+inf" || lower == "infinity" {
-                        f64::INFINITY
-                    } else if lower == "-inf" || lower == "-infinity" {
-                        f64::NEG_INFINITY
-                    } else if lower == "nan" {
-                        f64::NAN
-                    } else {
-                        // Non-numeric strings sort to beginning (like GNU sort)
-                        f64::NEG_INFINITY
-                    }
-                }
-            }
-        } else {
-            f64::NEG_INFINITY
-        }
+        crate::numeric::general_numeric_value(unsafe { self.as_bytes() })
     }
 
     /// Compare as general numeric values (scientific notation support)
     pub fn compare_general_numeric(&self, other: &Line) -> Ordering {
-        let a = self.parse_general_numeric();
-        let b = other.parse_general_numeric();
-
-        // Handle NaN specially (NaN sorts last in GNU sort)
-        match (a.is_nan(), b.is_nan()) {
-            (true, true) => unsafe { self.as_bytes().cmp(other.as_bytes()) }, // Lexicographic tie-breaker
-            (true, false) => Ordering::Greater,
-            (false, true) => Ordering::Less,
-            (false, false) => {
-                // Use total_cmp for consistent ordering including -0.0 vs 0.0
-                match a.total_cmp(&b) {
-                    Ordering::Equal => {
-                        // When numeric values are equal, use lexicographic comparison as tie-breaker
-                        // This matches GNU sort behavior
-                        unsafe { self.as_bytes().cmp(other.as_bytes()) }
-                    }
-                    other => other,
-                }
-            }
-        }
+        compare_general_values(
+            self.parse_general_numeric(),
+            other.parse_general_numeric(),
+            || unsafe { self.as_bytes().cmp(other.as_bytes()) },
+        )
     }
 
     /// Compare lines using field-based sorting with multiple keys
@@ -322,92 +301,243 @@ impl Line {
         separator: Option<char>,
         config: &crate::config::SortConfig,
     ) -> Ordering {
+        if let Some(limit) = config.comparison_limit() {
+            if self.len() > limit || other.len() > limit {
+                return self.truncated(limit).compare_with_keys(
+                    &other.truncated(limit),
+                    keys,
+                    separator,
+                    config,
+                );
+            }
+        }
+
+        #[cfg(feature = "comparison-count")]
+        config
+            .comparisons
+            .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+
+        // Byte-identical lines agree on every key and on the last resort, so
+        // skip extracting keys; under -s the caller's input order decides.
+        // `--debug` still traces each key comparison.
+        if !config.debug && self.len == other.len {
+            let (a, b) = unsafe { (self.as_bytes(), other.as_bytes()) };
+            if SIMDCompare::compare_bytes_simd(a, b) == Ordering::Equal {
+                return Ordering::Equal;
+            }
+        }
+
         if keys.is_empty() {
             // No keys specified, compare entire lines based on global options
-            return self.compare_with_config(other, config);
+            let cmp = match &config.ignore_chars {
+                Some(chars) => {
+                    let (a, b) = unsafe { (self.as_bytes(), other.as_bytes()) };
+                    let (a, b) = (without_chars(a, chars), without_chars(b, chars));
+                    Line::new(&a).compare_with_config(&Line::new(&b), config)
+                }
+                None => self.compare_with_config(other, config),
+            };
+            if cmp != Ordering::Equal {
+                return cmp;
+            }
+            return self.last_resort_compare(other, config);
         }
 
         // Compare using each key in order
         for key in keys {
-            let self_field = self.extract_key(key, separator);
-            let other_field = other.extract_key(key, separator);
-
-            let cmp = match (self_field, other_field) {
-                (Some(a), Some(b)) => {
-                    // Create temporary Line structs for the extracted fields
-                    let a_line = Line::new(a);
-                    let b_line = Line::new(b);
-
-                    // Compare based on key options
-                    let result = if key.options.general_numeric {
-                        a_line.compare_general_numeric(&b_line)
-                    } else if key.options.numeric {
-                        a_line.compare_numeric(&b_line)
-                    } else if key.options.month {
-                        a_line.compare_month(&b_line)
-                    } else if key.options.version {
-                        a_line.compare_version(&b_line)
-                    } else if key.options.human_numeric {
-                        a_line.compare_human_numeric(&b_line)
-                    } else if key.options.dictionary_order && key.options.ignore_case {
-                        a_line.compare_dictionary_order_ignore_case(&b_line)
-                    } else if key.options.dictionary_order {
-                        a_line.compare_dictionary_order(&b_line)
-                    } else if key.options.ignore_case {
-                        a_line.compare_ignore_case(&b_line)
-                    } else if key.options.ignore_leading_blanks {
-                        a_line.compare_lexicographic_with_blanks(&b_line, true)
-                    } else {
-                        a_line.compare_lexicographic(&b_line)
-                    };
-
-                    // Apply reverse if specified for this key
-                    let final_result = if key.options.reverse {
-                        result.reverse()
-                    } else {
-                        result
-                    };
-
-                    // Debug output if enabled (GNU sort compatible)
-                    if config.debug {
-                        let self_bytes = unsafe { self.as_bytes() };
-                        let other_bytes = unsafe { other.as_bytes() };
-                        let self_str = String::from_utf8_lossy(self_bytes);
-                        let other_str = String::from_utf8_lossy(other_bytes);
-                        let a_str = String::from_utf8_lossy(a);
-                        let b_str = String::from_utf8_lossy(b);
-
-                        // Convert Ordering to GNU sort style number
-                        let cmp_val = match final_result {
-                            Ordering::Greater => 1,
-                            Ordering::Less => -1,
-                            Ordering::Equal => 0,
-                        };
-
-                        eprintln!("; k1=<{a_str}>; k2=<{b_str}>; s1=<{self_str}>, s2=<{other_str}>; cmp1={cmp_val}");
-                    }
-
-                    final_result
+            let self_field = self.extract_key_with_config(key, separator, config);
+            let other_field = other.extract_key_with_config(key, separator, config);
+
+            // A key that lies past the end of the line is empty; lines whose
+            // keys are all empty fall through to the last-resort comparison
+            let a = self_field.unwrap_or_default();
+            let b = other_field.unwrap_or_default();
+
+            // `i` drops control characters from text keys before comparing
+            let printable;
+            let (a, b) = if key.options.ignore_nonprinting
+                && key.options.mode() == crate::config::SortMode::Lexicographic
+            {
+                printable = (printable_bytes(a), printable_bytes(b));
+                (&printable.0[..], &printable.1[..])
+            } else {
+                (a, b)
+            };
+            let kept;
+            let (a, b) = match &config.ignore_chars {
+                Some(chars) => {
+                    kept = (without_chars(a, chars), without_chars(b, chars));
+                    (&kept.0[..], &kept.1[..])
                 }
-                (None, Some(_)) => Ordering::Less,
-                (Some(_), None) => Ordering::Greater,
-                (None, None) => Ordering::Equal,
+                None => (a, b),
+            };
+            // Create temporary Line structs for the extracted fields
+            let a_line = Line::new(a);
+            let b_line = Line::new(b);
+
+            // Compare based on key options
+            let result = if key.options.random {
+                a_line.compare_random(&b_line, config.effective_random_seed())
+            } else if key.options.length {
+                key_length(a, config.utf8_positions).cmp(&key_length(b, config.utf8_positions))
+            } else if (key.options.general_numeric || key.options.numeric) && config.accounting {
+                a_line.compare_accounting(&b_line, key.options.general_numeric)
+            } else if key.options.general_numeric {
+                a_line.compare_general_numeric(&b_line)
+            } else if key.options.numeric {
+                a_line.compare_numeric(&b_line)
+            } else if key.options.month {
+                a_line.compare_month(&b_line)
+            } else if key.options.version {
+                a_line.compare_version(&b_line)
+            } else if key.options.human_numeric {
+                a_line.compare_human_numeric(&b_line)
+            } else {
+                a_line.compare_text(
+                    &b_line,
+                    key.options.ignore_case,
+                    key.options.dictionary_order,
+                    false,
+                    config.collates(),
+                )
             };
 
-            if cmp != Ordering::Equal {
-                return cmp;
+            // Apply reverse if specified for this key; keys without
+            // options of their own inherit the global -r
+            let reverse = key.options.reverse || (key.options.is_empty() && config.reverse);
+            let final_result = if reverse { result.reverse() } else { result };
+
+            // Debug output if enabled (GNU sort compatible)
+            if config.debug {
+                let self_bytes = unsafe { self.as_bytes() };
+                let other_bytes = unsafe { other.as_bytes() };
+                let self_str = String::from_utf8_lossy(self_bytes);
+                let other_str = String::from_utf8_lossy(other_bytes);
+                let a_str = String::from_utf8_lossy(a);
+                let b_str = String::from_utf8_lossy(b);
+
+                // Convert Ordering to GNU sort style number
+                let cmp_val = match final_result {
+                    Ordering::Greater => 1,
+                    Ordering::Less => -1,
+                    Ordering::Equal => 0,
+                };
+
+                eprintln!("; k1=<{a_str}>; k2=<{b_str}>; s1=<{self_str}>, s2=<{other_str}>; cmp1={cmp_val}");
             }
+
+            if final_result != Ordering::Equal {
+                return final_result;
+            }
+        }
+
+        // All keys compared equal
+        self.last_resort_compare(other, config)
+    }
+
+    /// `--debug` underlines for this output line, as GNU sort prints them:
+    /// one per key (the line itself without keys) marking the characters it
+    /// compares, then one for the whole-line last resort where that applies.
+    /// Blanks skipped by `b` and characters dropped by `d` or `i` are left
+    /// unmarked, so each line shows exactly what is compared.
+    pub fn debug_underlines(&self, config: &crate::config::SortConfig) -> Vec<String> {
+        use crate::config::{SortKeyOptions, SortMode};
+
+        let bytes = unsafe { self.as_bytes() };
+        let mut underlines = Vec::new();
+        if config.keys.is_empty() {
+            let start = if config.ignore_leading_blanks {
+                bytes
+                    .iter()
+                    .position(|&b| b != b' ' && b != b'\t')
+                    .unwrap_or(bytes.len())
+            } else {
+                0
+            };
+            let options = SortKeyOptions {
+                dictionary_order: config.dictionary_order && config.mode == SortMode::Lexicographic,
+                ..Default::default()
+            };
+            underlines.push(underline(
+                bytes,
+                Some(start..bytes.len()),
+                &options,
+                config.utf8_positions,
+            ));
+        }
+        for key in &config.keys {
+            let span = self
+                .extract_key_with_config(key, config.field_separator, config)
+                .map(|field| {
+                    let start = field.as_ptr() as usize - bytes.as_ptr() as usize;
+                    start..start + field.len()
+                });
+            // `d` and `i` only filter text comparisons
+            let options = if key.options.mode() == SortMode::Lexicographic {
+                SortKeyOptions {
+                    dictionary_order: key.options.dictionary_order,
+                    ignore_nonprinting: key.options.ignore_nonprinting,
+                    ..Default::default()
+                }
+            } else {
+                SortKeyOptions::default()
+            };
+            underlines.push(underline(bytes, span, &options, config.utf8_positions));
         }
 
-        // All keys compared equal, use stable sort order (original line order)
-        if config.stable {
+        // Without keys or options the line is its own key; -s and -u drop
+        // the last resort
+        let whole_line_options = config.mode != SortMode::Lexicographic
+            || config.dictionary_order
+            || config.ignore_case
+            || config.ignore_leading_blanks;
+        if !config.stable && !config.unique && (!config.keys.is_empty() || whole_line_options) {
+            let options = SortKeyOptions::default();
+            underlines.push(underline(
+                bytes,
+                Some(0..bytes.len()),
+                &options,
+                config.utf8_positions,
+            ));
+        }
+        underlines
+    }
+
+    /// Tie-breaker for lines whose keys compare equal: keep input order under
+    /// `-s` and `-u`, otherwise compare the entire lines byte by byte,
+    /// honoring `-r`
+    pub(crate) fn last_resort_compare(
+        &self,
+        other: &Line,
+        config: &crate::config::SortConfig,
+    ) -> Ordering {
+        let compare = || self.compare_text(other, false, false, false, config.collates());
+        if config.stable || config.unique {
             Ordering::Equal
+        } else if config.reverse {
+            compare().reverse()
         } else {
-            // Use entire line as tie-breaker
-            self.compare_lexicographic(other)
+            compare()
         }
     }
 
+    /// Random order keyed by `seed`: lines are ordered by a seeded hash of
+    /// their bytes, so identical lines stay together and the order is
+    /// reproducible for a given seed
+    pub fn compare_random(&self, other: &Line, seed: u64) -> Ordering {
+        use std::hash::{Hash, Hasher};
+
+        let hash = |bytes: &[u8]| {
+            let mut hasher = crate::hash_sort::RandomHasher::default();
+            seed.hash(&mut hasher);
+            bytes.hash(&mut hasher);
+            hasher.finish()
+        };
+
+        let (a, b) = unsafe { (self.as_bytes(), other.as_bytes()) };
+        hash(a).cmp(&hash(b)).then_with(|| a.cmp(b))
+    }
+
     /// Compare lines based on global configuration (when no keys are specified)
     pub fn compare_with_config(
         &self,
@@ -415,33 +545,39 @@ impl Line {
         config: &crate::config::SortConfig,
     ) -> Ordering {
         let cmp = match config.mode {
+            crate::config::SortMode::GeneralNumeric if config.accounting => {
+                self.compare_accounting(other, true)
+            }
+            crate::config::SortMode::Numeric if config.accounting => {
+                self.compare_accounting(other, false)
+            }
             crate::config::SortMode::GeneralNumeric => self.compare_general_numeric(other),
             crate::config::SortMode::Numeric => self.compare_numeric(other),
             crate::config::SortMode::Month => self.compare_month(other),
             crate::config::SortMode::Version => self.compare_version(other),
             crate::config::SortMode::HumanNumeric => self.compare_human_numeric(other),
             crate::config::SortMode::Lexicographic => {
-                if config.dictionary_order && config.ignore_case {
-                    self.compare_dictionary_order_ignore_case(other)
-                } else if config.dictionary_order {
-                    self.compare_dictionary_order(other)
-                } else if config.ignore_case {
-                    self.compare_ignore_case(other)
-                } else if config.ignore_leading_blanks {
-                    self.compare_lexicographic_with_blanks(other, true)
-                } else {
-                    self.compare_lexicographic(other)
-                }
+                // -b applies only without -d and -f
+                let blanks =
+                    config.ignore_leading_blanks && !config.dictionary_order && !config.ignore_case;
+                self.compare_text(
+                    other,
+                    config.ignore_case,
+                    config.dictionary_order,
+                    blanks,
+                    config.collates(),
+                )
             }
             _ => {
                 // For other modes, also check dictionary_order flag
-                if config.dictionary_order {
-                    self.compare_dictionary_order(other)
-                } else if config.ignore_leading_blanks {
-                    self.compare_lexicographic_with_blanks(other, true)
-                } else {
-                    self.compare_lexicographic(other)
-                }
+                let blanks = config.ignore_leading_blanks && !config.dictionary_order;
+                self.compare_text(
+                    other,
+                    false,
+                    config.dictionary_order,
+                    blanks,
+                    config.collates(),
+                )
             }
         };
 
@@ -452,6 +588,27 @@ impl Line {
         }
     }
 
+    /// Numeric (`general` for `-g`) comparison reading a parenthesized value
+    /// such as `(100)` as negative, as in accounting notation (`--accounting`)
+    pub fn compare_accounting(&self, other: &Line, general: bool) -> Ordering {
+        let (a, b) = unsafe {
+            (
+                accounting_parts(self.as_bytes()),
+                accounting_parts(other.as_bytes()),
+            )
+        };
+        if general {
+            // Ties compare the text as rewritten, `(100)` as `-100`
+            compare_general_values(
+                accounting_general_value(a),
+                accounting_general_value(b),
+                || accounting_text(a).cmp(accounting_text(b)),
+            )
+        } else {
+            accounting_prefix(a).compare(&accounting_prefix(b))
+        }
+    }
+
     /// Fast comparison for numeric values (GNU sort style - no string conversion)
     pub fn compare_numeric(&self, other: &Line) -> Ordering {
         // Try fast path for simple integers
@@ -459,156 +616,7 @@ impl Line {
             return a.cmp(&b);
         }
 
-        // GNU sort style: compare as strings with numeric logic
-        self.compare_numeric_string_style(other)
-    }
-
-    /// GNU sort-style numeric string comparison (key optimization!)
-    fn compare_numeric_string_style(&self, other: &Line) -> Ordering {
-        let a_bytes = unsafe { self.as_bytes() };
-        let b_bytes = unsafe { other.as_bytes() };
-
-        // Skip leading whitespace
-        let a_start = self.skip_leading_space(a_bytes);
-        let b_start = self.skip_leading_space(b_bytes);
-
-        if a_start >= a_bytes.len() && b_start >= b_bytes.len() {
-            return Ordering::Equal;
-        }
-        if a_start >= a_bytes.len() {
-            return Ordering::Less;
-        }
-        if b_start >= b_bytes.len() {
-            return Ordering::Greater;
-        }
-
-        let a_rest = &a_bytes[a_start..];
-        let b_rest = &b_bytes[b_start..];
-
-        // Check signs
-        let (a_negative, a_num_start) = self.parse_sign(a_rest);
-        let (b_negative, b_num_start) = self.parse_sign(b_rest);
-
-        match (a_negative, b_negative) {
-            (true, false) => Ordering::Less,
-            (false, true) => Ordering::Greater,
-            _ => {
-                // Same sign - compare magnitudes
-                let a_digits = &a_rest[a_num_start..];
-                let b_digits = &b_rest[b_num_start..];
-
-                // Skip leading zeros (GNU sort behavior)
-                let a_no_zeros = self.skip_leading_zeros(a_digits);
-                let b_no_zeros = self.skip_leading_zeros(b_digits);
-
-                // Compare by digit count first (major optimization!)
-                let a_digit_count = self.count_leading_digits(&a_digits[a_no_zeros..]);
-                let b_digit_count = self.count_leading_digits(&b_digits[b_no_zeros..]);
-
-                let magnitude_cmp = match a_digit_count.cmp(&b_digit_count) {
-                    Ordering::Equal => {
-                        // Same digit count - lexicographic comparison
-                        a_digits[a_no_zeros..a_no_zeros + a_digit_count]
-                            .cmp(&b_digits[b_no_zeros..b_no_zeros + b_digit_count])
-                    }
-                    other => other,
-                };
-
-                if a_negative {
-                    magnitude_cmp.reverse()
-                } else {
-                    magnitude_cmp
-                }
-            }
-        }
-    }
-
-    fn skip_leading_space(&self, bytes: &[u8]) -> usize {
-        bytes
-            .iter()
-            .position(|&b| b != b' ' && b != b'\t')
-            .unwrap_or(bytes.len())
-    }
-
-    fn parse_sign(&self, bytes: &[u8]) -> (bool, usize) {
-        if bytes.is_empty() {
-            return (false, 0);
-        }
-        match bytes[0] {
-            b'-' => (true, 1),
-            b'+' => (false, 1),
-            _ => (false, 0),
-        }
-    }
-
-    fn skip_leading_zeros(&self, bytes: &[u8]) -> usize {
-        bytes.iter().position(|&b| b != b'0').unwrap_or(bytes.len())
-    }
-
-    fn count_leading_digits(&self, bytes: &[u8]) -> usize {
-        bytes.iter().take_while(|&&b| b.is_ascii_digit()).count()
-    }
-
-    /// Byte-level numeric comparison for complex numbers
-    #[allow(dead_code)]
-    fn compare_numeric_bytes(&self, other: &Line) -> Ordering {
-        let a_bytes = unsafe { self.as_bytes() };
-        let b_bytes = unsafe { other.as_bytes() };
-
-        // Skip leading whitespace
-        let a_trimmed = a_bytes
-            .iter()
-            .skip_while(|&&b| b == b' ' || b == b'\t')
-            .collect::<Vec<_>>();
-        let b_trimmed = b_bytes
-            .iter()
-            .skip_while(|&&b| b == b' ' || b == b'\t')
-            .collect::<Vec<_>>();
-
-        if a_trimmed.is_empty() && b_trimmed.is_empty() {
-            return Ordering::Equal;
-        }
-        if a_trimmed.is_empty() {
-            return Ordering::Less;
-        }
-        if b_trimmed.is_empty() {
-            return Ordering::Greater;
-        }
-
-        // Compare signs
-        let a_negative = *a_trimmed[0] == b'-';
-        let b_negative = *b_trimmed[0] == b'-';
-
-        match (a_negative, b_negative) {
-            (true, false) => Ordering::Less,
-            (false, true) => Ordering::Greater,
-            _ => {
-                // Same sign, compare magnitudes
-                let a_digits: Vec<u8> = a_trimmed
-                    .iter()
-                    .skip_while(|&&&b| b == b'-' || b == b'+')
-                    .filter(|&&&b| b.is_ascii_digit())
-                    .map(|&&b| b)
-                    .collect();
-                let b_digits: Vec<u8> = b_trimmed
-                    .iter()
-                    .skip_while(|&&&b| b == b'-' || b == b'+')
-                    .filter(|&&&b| b.is_ascii_digit())
-                    .map(|&&b| b)
-                    .collect();
-
-                let magnitude_cmp = match a_digits.len().cmp(&b_digits.len()) {
-                    Ordering::Equal => a_digits.cmp(&b_digits),
-                    other => other,
-                };
-
-                if a_negative {
-                    magnitude_cmp.reverse()
-                } else {
-                    magnitude_cmp
-                }
-            }
-        }
+        crate::numeric::compare_numeric(unsafe { self.as_bytes() }, unsafe { other.as_bytes() })
     }
 
     /// Get the length of the line
@@ -616,6 +624,11 @@ impl Line {
         self.len as usize
     }
 
+    /// The first `limit` bytes of the line, or all of it if shorter
+    pub fn truncated(&self, limit: usize) -> Line {
+        Line::new(&unsafe { self.as_bytes() }[..self.len().min(limit)])
+    }
+
     /// Check if the line is empty
     pub fn is_empty(&self) -> bool {
         self.len == 0
@@ -623,30 +636,24 @@ impl Line {
 
     /// Locale-aware case-insensitive comparison
     pub fn compare_ignore_case(&self, other: &Line) -> Ordering {
-        let a_bytes = unsafe { self.as_bytes() };
-        let b_bytes = unsafe { other.as_bytes() };
-
-        // Use locale-aware comparison if enabled
-        if locale::LocaleConfig::is_enabled() {
-            locale::smart_compare(a_bytes, b_bytes, true)
-        } else {
-            // Use SIMD for performance boost when locale is not enabled
-            SIMDCompare::compare_case_insensitive_simd(a_bytes, b_bytes)
-        }
+        self.compare_text(
+            other,
+            true,
+            false,
+            false,
+            locale::LocaleConfig::is_enabled(),
+        )
     }
 
     /// Locale-aware lexicographic comparison
     pub fn compare_lexicographic(&self, other: &Line) -> Ordering {
-        let a_bytes = unsafe { self.as_bytes() };
-        let b_bytes = unsafe { other.as_bytes() };
-
-        // Use locale-aware comparison if enabled
-        if locale::LocaleConfig::is_enabled() {
-            locale::smart_compare(a_bytes, b_bytes, false)
-        } else {
-            // Use SIMD for maximum performance when locale is not enabled
-            SIMDCompare::compare_bytes_simd(a_bytes, b_bytes)
-        }
+        self.compare_text(
+            other,
+            false,
+            false,
+            false,
+            locale::LocaleConfig::is_enabled(),
+        )
     }
 
     /// Lexicographic comparison with option to ignore leading blanks
@@ -654,6 +661,45 @@ impl Line {
         &self,
         other: &Line,
         ignore_leading_blanks: bool,
+    ) -> Ordering {
+        self.compare_text(
+            other,
+            false,
+            false,
+            ignore_leading_blanks,
+            locale::LocaleConfig::is_enabled(),
+        )
+    }
+
+    /// Dictionary order comparison (only alphanumeric characters and blanks)
+    pub fn compare_dictionary_order(&self, other: &Line) -> Ordering {
+        self.compare_text(
+            other,
+            false,
+            true,
+            false,
+            locale::LocaleConfig::is_enabled(),
+        )
+    }
+
+    /// Dictionary order with case-insensitive comparison
+    pub fn compare_dictionary_order_ignore_case(&self, other: &Line) -> Ordering {
+        self.compare_text(other, true, true, false, locale::LocaleConfig::is_enabled())
+    }
+
+    /// Text comparison behind the methods above, collating with `strcoll`
+    /// only when `collate` is set; callers with a [`SortConfig`] pass
+    /// [`SortConfig::collates`] so that `--byte-order` holds per sort
+    ///
+    /// [`SortConfig`]: crate::config::SortConfig
+    /// [`SortConfig::collates`]: crate::config::SortConfig::collates
+    pub(crate) fn compare_text(
+        &self,
+        other: &Line,
+        ignore_case: bool,
+        dictionary_order: bool,
+        ignore_leading_blanks: bool,
+        collate: bool,
     ) -> Ordering {
         let mut a_bytes = unsafe { self.as_bytes() };
         let mut b_bytes = unsafe { other.as_bytes() };
@@ -672,46 +718,26 @@ impl Line {
             b_bytes = &b_bytes[b_start..];
         }
 
-        // Use locale-aware comparison if enabled
-        if locale::LocaleConfig::is_enabled() {
-            locale::smart_compare(a_bytes, b_bytes, false)
-        } else {
-            // Use SIMD for maximum performance when locale is not enabled
-            SIMDCompare::compare_bytes_simd(a_bytes, b_bytes)
+        let filtered;
+        if dictionary_order {
+            filtered = (
+                self.filter_dictionary_order(a_bytes),
+                self.filter_dictionary_order(b_bytes),
+            );
+            a_bytes = &filtered.0;
+            b_bytes = &filtered.1;
         }
-    }
-
-    /// Dictionary order comparison (only alphanumeric characters and blanks)
-    pub fn compare_dictionary_order(&self, other: &Line) -> Ordering {
-        let a_bytes = unsafe { self.as_bytes() };
-        let b_bytes = unsafe { other.as_bytes() };
 
-        let a_filtered = self.filter_dictionary_order(a_bytes);
-        let b_filtered = self.filter_dictionary_order(b_bytes);
-
-        // Use locale-aware comparison if enabled
-        if locale::LocaleConfig::is_enabled() {
-            locale::smart_compare(&a_filtered, &b_filtered, false)
+        if collate && ignore_case {
+            locale::strcasecoll_compare(a_bytes, b_bytes)
+        } else if collate {
+            locale::strcoll_compare(a_bytes, b_bytes)
+        } else if ignore_case {
+            // Use SIMD for performance boost when locale is not enabled
+            SIMDCompare::compare_case_insensitive_simd(a_bytes, b_bytes)
         } else {
             // Use SIMD for maximum performance when locale is not enabled
-            SIMDCompare::compare_bytes_simd(&a_filtered, &b_filtered)
-        }
-    }
-
-    /// Dictionary order with case-insensitive comparison
-    pub fn compare_dictionary_order_ignore_case(&self, other: &Line) -> Ordering {
-        let a_bytes = unsafe { self.as_bytes() };
-        let b_bytes = unsafe { other.as_bytes() };
-
-        let a_filtered = self.filter_dictionary_order(a_bytes);
-        let b_filtered = self.filter_dictionary_order(b_bytes);
-
-        // Use locale-aware comparison if enabled
-        if locale::LocaleConfig::is_enabled() {
-            locale::smart_compare(&a_filtered, &b_filtered, true)
-        } else {
-            // Use SIMD for performance boost when locale is not enabled
-            SIMDCompare::compare_case_insensitive_simd(&a_filtered, &b_filtered)
+            SIMDCompare::compare_bytes_simd(a_bytes, b_bytes)
         }
     }
 
@@ -734,215 +760,417 @@ impl Line {
         }
     }
 
-    /// Month-aware comparison (GNU sort compatible)
+    /// Month comparison (`-M`): after leading blanks, the first three letters
+    /// name the month, `JAN` to `DEC` in any case and always in English. Text
+    /// that names no month is `0` and sorts before January; equal months are
+    /// left to the next key or the last resort.
     pub fn compare_month(&self, other: &Line) -> Ordering {
-        let a_bytes = unsafe { self.as_bytes() };
-        let b_bytes = unsafe { other.as_bytes() };
-
-        fn month_value(bytes: &[u8]) -> u8 {
-            // Convert to uppercase for case-insensitive comparison
-            let upper_bytes: Vec<u8> = bytes.iter().map(|b| b.to_ascii_uppercase()).collect();
-
-            // Try to match month abbreviations (GNU sort standard)
-            match upper_bytes.as_slice() {
-                b"JAN" | b"JANUARY" => 1,
-                b"FEB" | b"FEBRUARY" => 2,
-                b"MAR" | b"MARCH" => 3,
-                b"APR" | b"APRIL" => 4,
-                b"MAY" => 5,
-                b"JUN" | b"JUNE" => 6,
-                b"JUL" | b"JULY" => 7,
-                b"AUG" | b"AUGUST" => 8,
-                b"SEP" | b"SEPTEMBER" => 9,
-                b"OCT" | b"OCTOBER" => 10,
-                b"NOV" | b"NOVEMBER" => 11,
-                b"DEC" | b"DECEMBER" => 12,
-                _ => 0, // Unknown month, will be compared lexicographically
-            }
-        }
-
-        let a_month = month_value(a_bytes);
-        let b_month = month_value(b_bytes);
-
-        match (a_month, b_month) {
-            // Both are recognized months - compare by month order
-            (a, b) if a > 0 && b > 0 => a.cmp(&b),
-            // Only a is a month - non-months come before months (GNU sort behavior)
-            (a, 0) if a > 0 => Ordering::Greater,
-            // Only b is a month - non-months come before months (GNU sort behavior)
-            (0, b) if b > 0 => Ordering::Less,
-            // Neither is a month - fall back to lexicographic comparison
-            (0, 0) => self.compare_lexicographic(other),
-            // Catch-all for any other cases (should not occur, but satisfies compiler)
-            _ => self.compare_lexicographic(other),
-        }
+        let a = month_number(unsafe { self.as_bytes() });
+        let b = month_number(unsafe { other.as_bytes() });
+        a.cmp(&b)
     }
 
-    /// Version-aware comparison (GNU sort -V compatible)
+    /// Version comparison (`-V`), GNU's `filevercmp`: digit runs compare by
+    /// value and the text between them byte by byte, letters before other
+    /// characters and `~` before anything, even the end of the text, so
+    /// `1.0~rc1` sorts before `1.0`. File suffixes such as `.tar.gz` are
+    /// only compared when the rest is equal.
     pub fn compare_version(&self, other: &Line) -> Ordering {
-        let a_bytes = unsafe { self.as_bytes() };
-        let b_bytes = unsafe { other.as_bytes() };
+        filevercmp(unsafe { self.as_bytes() }, unsafe { other.as_bytes() })
+    }
 
-        // Convert to strings for version parsing
-        let a_str = String::from_utf8_lossy(a_bytes);
-        let b_str = String::from_utf8_lossy(b_bytes);
+    /// Human numeric comparison (`-h`): by binary suffix (K, M, G, T, P, E,
+    /// Z, Y), then by the `-n` number in front of it, so `900 < 1K < 1M`.
+    /// Lines without a number count as zero, as with `-n`.
+    pub fn compare_human_numeric(&self, other: &Line) -> Ordering {
+        crate::numeric::compare_human_numeric(unsafe { self.as_bytes() }, unsafe {
+            other.as_bytes()
+        })
+    }
+}
 
-        Self::compare_version_strings(&a_str, &b_str)
+/// Memory-mapped file with parsed lines
+pub struct MappedFile {
+    mmap: Mmap, // Keeps the lines valid
+    lines: Vec<Line>,
+}
+
+impl MappedFile {
+    /// Create a new SimpleMappedFile from a file path
+    pub fn new(path: &Path) -> io::Result<Self> {
+        Self::with_terminator(path, b'\n')
     }
 
-    /// Compare two version strings (like "1.2.3" vs "1.10.1")
-    fn compare_version_strings(a: &str, b: &str) -> Ordering {
-        // Split by non-alphanumeric characters and compare each component
-        let a_parts = Self::version_tokenize(a);
-        let b_parts = Self::version_tokenize(b);
+    /// Map a file whose lines end with `terminator` (`b'\0'` for `-z`)
+    pub fn with_terminator(path: &Path, terminator: u8) -> io::Result<Self> {
+        let file = File::open(path)?;
+        let mmap = unsafe { Mmap::map(&file)? };
 
-        for (a_part, b_part) in a_parts.iter().zip(b_parts.iter()) {
-            match Self::compare_version_component(a_part, b_part) {
-                Ordering::Equal => continue,
-                other => return other,
-            }
-        }
+        // Parse lines while keeping references to the mmap
+        let lines = parse_lines_with_terminator(&mmap, terminator);
 
-        // If all compared parts are equal, longer version wins
-        a_parts.len().cmp(&b_parts.len())
+        Ok(Self { mmap, lines })
     }
 
-    /// Tokenize version string into alphanumeric components
-    fn version_tokenize(s: &str) -> Vec<String> {
-        let mut tokens = Vec::new();
-        let mut current = String::new();
-        let mut in_alpha = false;
+    /// Get the lines in this file
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
 
-        for ch in s.chars() {
-            let is_alpha = ch.is_alphabetic();
-            let is_digit = ch.is_ascii_digit();
+    /// Byte offset within the file of a line taken from this mapping, e.g.
+    /// after sorting; `None` for lines that point elsewhere
+    pub fn offset_of(&self, line: &Line) -> Option<usize> {
+        let base = self.mmap.as_ptr() as usize;
+        let start = line.start as usize;
+        (start >= base && start + line.len() <= base + self.mmap.len()).then(|| start - base)
+    }
 
-            if is_alpha || is_digit {
-                if in_alpha != is_alpha && !current.is_empty() {
-                    tokens.push(current);
-                    current = String::new();
-                }
-                current.push(ch);
-                in_alpha = is_alpha;
-            } else if !current.is_empty() {
-                tokens.push(current);
-                current = String::new();
-            }
-        }
+    /// `(offset, len)` of every line in file order, terminators excluded
+    pub fn line_spans(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.lines.iter().map(|line| {
+            let offset = line.start as usize - self.mmap.as_ptr() as usize;
+            (offset, line.len())
+        })
+    }
+}
 
-        if !current.is_empty() {
-            tokens.push(current);
-        }
+/// Split an accounting negative such as `(100)` into `true` and the
+/// number inside; anything else is `false` and the bytes unchanged
+fn accounting_parts(bytes: &[u8]) -> (bool, &[u8]) {
+    let is_blank = |b: &u8| *b == b' ' || *b == b'\t';
+    let start = bytes
+        .iter()
+        .position(|b| !is_blank(b))
+        .unwrap_or(bytes.len());
+    let end = bytes
+        .iter()
+        .rposition(|b| !is_blank(b))
+        .map_or(start, |i| i + 1);
 
-        tokens
+    match &bytes[start..end] {
+        [b'(', inner @ .., b')'] => (true, inner),
+        _ => (false, bytes),
     }
+}
 
-    /// Compare individual version components (numeric or alphabetic)
-    fn compare_version_component(a: &str, b: &str) -> Ordering {
-        // Check if both are numeric
-        if let (Ok(a_num), Ok(b_num)) = (a.parse::<u64>(), b.parse::<u64>()) {
-            return a_num.cmp(&b_num);
-        }
+/// The `-n` prefix of [`accounting_parts`], read as negative when negated
+fn accounting_prefix((negated, body): (bool, &[u8])) -> crate::numeric::NumericPrefix<'_> {
+    if negated {
+        crate::numeric::parse_negated_numeric_prefix(body)
+    } else {
+        crate::numeric::parse_numeric_prefix(body)
+    }
+}
 
-        // Check if one is numeric and other is not (numeric comes first)
-        match (
-            a.chars().all(|c| c.is_ascii_digit()),
-            b.chars().all(|c| c.is_ascii_digit()),
-        ) {
-            (true, false) => Ordering::Less,
-            (false, true) => Ordering::Greater,
-            _ => a.cmp(b), // Both non-numeric, lexicographic comparison
-        }
+/// The bytes of [`accounting_parts`] as `-100` for `(100)`
+fn accounting_text((negated, body): (bool, &[u8])) -> impl Iterator<Item = &u8> {
+    let sign: &'static [u8] = if negated { b"-" } else { b"" };
+    sign.iter().chain(body)
+}
+
+/// The `-g` value of [`accounting_parts`], negated when negated
+fn accounting_general_value((negated, body): (bool, &[u8])) -> f64 {
+    if negated {
+        crate::numeric::negated_general_numeric_value(body)
+    } else {
+        crate::numeric::general_numeric_value(body)
     }
+}
 
-    /// Human numeric comparison (GNU sort -h compatible)
-    pub fn compare_human_numeric(&self, other: &Line) -> Ordering {
-        let a_bytes = unsafe { self.as_bytes() };
-        let b_bytes = unsafe { other.as_bytes() };
-
-        let a_string = String::from_utf8_lossy(a_bytes);
-        let b_string = String::from_utf8_lossy(b_bytes);
-        let a_str = a_string.trim();
-        let b_str = b_string.trim();
-
-        let a_val = Self::parse_human_numeric(a_str);
-        let b_val = Self::parse_human_numeric(b_str);
-
-        match (a_val, b_val) {
-            (Some(a), Some(b)) => {
-                match a.partial_cmp(&b) {
-                    Some(ord) => ord,
-                    None => a_str.cmp(b_str), // Handle NaN case
+/// `-g` order of two values: NaN after everything else, then by value,
+/// `tie` deciding between NaNs and equal values
+fn compare_general_values(a: f64, b: f64, tie: impl FnOnce() -> Ordering) -> Ordering {
+    match (a.is_nan(), b.is_nan()) {
+        (true, true) => tie(),
+        (true, false) => Ordering::Greater,
+        (false, true) => Ordering::Less,
+        // total_cmp keeps -0.0 before 0.0
+        (false, false) => a.total_cmp(&b).then_with(tie),
+    }
+}
+
+/// Byte offset of the character `chars` characters into `bytes`, or the
+/// length of `bytes` when it is shorter; every byte that does not continue
+/// a UTF-8 sequence starts a character, so invalid input degrades to bytes
+fn utf8_char_offset(bytes: &[u8], chars: usize) -> usize {
+    bytes
+        .iter()
+        .enumerate()
+        .filter(|&(_, &b)| b & 0xc0 != 0x80)
+        .nth(chars)
+        .map_or(bytes.len(), |(index, _)| index)
+}
+
+/// One `--debug` underline for `line`: `_` under each character of `span`
+/// that `options` compare and spaces elsewhere, or GNU's "no match" marker
+/// for a missing or empty key. Columns are UTF-8 characters when `utf8` is
+/// set, else bytes; tabs take one column, as the debug output shows them
+/// as `>`.
+fn underline(
+    line: &[u8],
+    span: Option<std::ops::Range<usize>>,
+    options: &crate::config::SortKeyOptions,
+    utf8: bool,
+) -> String {
+    // The same characters the comparisons leave out
+    let compared = |c: char| {
+        !(options.dictionary_order && !(c.is_alphanumeric() || c == ' ' || c == '\t'))
+            && !(options.ignore_nonprinting && c.is_ascii_control())
+    };
+    let units: Vec<(usize, char, usize)> = match std::str::from_utf8(line) {
+        Ok(text) => text
+            .char_indices()
+            .map(|(offset, c)| (offset, c, if utf8 { 1 } else { c.len_utf8() }))
+            .collect(),
+        Err(_) => line
+            .iter()
+            .enumerate()
+            .map(|(offset, &b)| (offset, if b.is_ascii() { b as char } else { '\u{80}' }, 1))
+            .collect(),
+    };
+
+    let (start, end, matched) = match span {
+        Some(span) if !span.is_empty() => (span.start, span.end, true),
+        Some(span) => (span.start, span.start, false),
+        None => (line.len(), line.len(), false),
+    };
+    let mut text = String::new();
+    for &(offset, c, width) in units.iter().take_while(|&&(offset, ..)| offset < end) {
+        let mark = if offset >= start && compared(c) {
+            '_'
+        } else {
+            ' '
+        };
+        text.extend(std::iter::repeat(mark).take(width));
+    }
+    if matched {
+        text.truncate(text.trim_end().len());
+    } else {
+        text.push_str("^ no match for key");
+    }
+    text
+}
+
+/// GNU's `filevercmp`: `.` and `..` first, then other names starting with
+/// `.`, then the rest ordered by [`version_runs_cmp`], suffixes last
+fn filevercmp(a: &[u8], b: &[u8]) -> Ordering {
+    match (a.is_empty(), b.is_empty()) {
+        (true, true) => return Ordering::Equal,
+        (true, false) => return Ordering::Less,
+        (false, true) => return Ordering::Greater,
+        (false, false) => {}
+    }
+    match (a[0] == b'.', b[0] == b'.') {
+        (true, true) => {
+            for special in [&b"."[..], b".."] {
+                match (a == special, b == special) {
+                    (true, true) => return Ordering::Equal,
+                    (true, false) => return Ordering::Less,
+                    (false, true) => return Ordering::Greater,
+                    (false, false) => {}
                 }
             }
-            (Some(_), None) => Ordering::Less, // Numbers before non-numbers
-            (None, Some(_)) => Ordering::Greater, // Numbers before non-numbers
-            (None, None) => a_str.cmp(b_str),  // Both non-numeric
         }
+        (true, false) => return Ordering::Less,
+        (false, true) => return Ordering::Greater,
+        (false, false) => {}
     }
 
-    /// Parse human-readable numeric value (like "1K", "2.5M", "1G")
-    fn parse_human_numeric(s: &str) -> Option<f64> {
-        if s.is_empty() {
-            return None;
+    let (a_prefix, b_prefix) = (file_prefix_len(a), file_prefix_len(b));
+    let cmp = version_runs_cmp(&a[..a_prefix], &b[..b_prefix]);
+    if cmp != Ordering::Equal || (a_prefix == a.len() && b_prefix == b.len()) {
+        return cmp;
+    }
+    version_runs_cmp(a, b)
+}
+
+/// Length of `s` without its file suffix, the longest match of
+/// `(\.[A-Za-z~][A-Za-z0-9~]*)*$` that does not take the first byte
+fn file_prefix_len(s: &[u8]) -> usize {
+    let mut prefix_len = 0;
+    let mut i = 0;
+    while i < s.len() {
+        i += 1;
+        prefix_len = i;
+        while i + 1 < s.len()
+            && s[i] == b'.'
+            && (s[i + 1].is_ascii_alphabetic() || s[i + 1] == b'~')
+        {
+            i += 2;
+            while i < s.len() && (s[i].is_ascii_alphanumeric() || s[i] == b'~') {
+                i += 1;
+            }
         }
+    }
+    prefix_len
+}
 
-        let s = s.trim();
-        let last_char = s.chars().last()?;
+/// Rank of the byte at `pos` among non-digits: `~` lowest, below the end
+/// of the text, then digits, letters, and everything else
+fn version_char_order(s: &[u8], pos: usize) -> i32 {
+    match s.get(pos) {
+        None => -1,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(c) if c.is_ascii_alphabetic() => i32::from(*c),
+        Some(b'~') => -2,
+        Some(c) => i32::from(*c) + 256,
+    }
+}
 
-        let multiplier = match last_char.to_ascii_uppercase() {
-            'K' => 1024.0,
-            'M' => 1024.0 * 1024.0,
-            'G' => 1024.0 * 1024.0 * 1024.0,
-            'T' => 1024.0 * 1024.0 * 1024.0 * 1024.0,
-            'P' => 1024.0 * 1024.0 * 1024.0 * 1024.0 * 1024.0,
-            _ => {
-                // No suffix, parse as regular number
-                return s.parse::<f64>().ok();
+/// Compare alternating non-digit and digit runs: non-digits byte by byte
+/// by [`version_char_order`], digit runs by value
+fn version_runs_cmp(a: &[u8], b: &[u8]) -> Ordering {
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !a[i].is_ascii_digit()) || (j < b.len() && !b[j].is_ascii_digit()) {
+            let (a_order, b_order) = (version_char_order(a, i), version_char_order(b, j));
+            if a_order != b_order {
+                return a_order.cmp(&b_order);
             }
-        };
-
-        // Parse the numeric part (without the suffix)
-        let numeric_part = s[..s.len() - 1].trim();
-        let value = numeric_part.parse::<f64>().ok()?;
+            i += 1;
+            j += 1;
+        }
 
-        Some(value * multiplier)
+        while i < a.len() && a[i] == b'0' {
+            i += 1;
+        }
+        while j < b.len() && b[j] == b'0' {
+            j += 1;
+        }
+        // Of two digit runs of the same length, the first differing digit decides
+        let mut first_diff = Ordering::Equal;
+        while i < a.len() && j < b.len() && a[i].is_ascii_digit() && b[j].is_ascii_digit() {
+            if first_diff == Ordering::Equal {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if i < a.len() && a[i].is_ascii_digit() {
+            return Ordering::Greater;
+        }
+        if j < b.len() && b[j].is_ascii_digit() {
+            return Ordering::Less;
+        }
+        if first_diff != Ordering::Equal {
+            return first_diff;
+        }
     }
+    Ordering::Equal
 }
 
-/// Memory-mapped file with parsed lines
-pub struct MappedFile {
-    _mmap: Mmap, // Keep mmap alive
-    lines: Vec<Line>,
+/// Month `1..=12` named by the first three letters of `bytes` after any
+/// leading blanks, or `0` when they name no month
+fn month_number(bytes: &[u8]) -> u8 {
+    const MONTHS: [&[u8; 3]; 12] = [
+        b"JAN", b"FEB", b"MAR", b"APR", b"MAY", b"JUN", b"JUL", b"AUG", b"SEP", b"OCT", b"NOV",
+        b"DEC",
+    ];
+    let start = bytes
+        .iter()
+        .position(|&b| b != b' ' && b != b'\t')
+        .unwrap_or(bytes.len());
+    let Some(name) = bytes.get(start..start + 3) else {
+        return 0;
+    };
+    MONTHS
+        .iter()
+        .position(|month| name.eq_ignore_ascii_case(&month[..]))
+        .map_or(0, |index| index as u8 + 1)
 }
 
-impl MappedFile {
-    /// Create a new SimpleMappedFile from a file path
-    pub fn new(path: &Path) -> io::Result<Self> {
-        let file = File::open(path)?;
-        let mmap = unsafe { Mmap::map(&file)? };
+/// `bytes` without ASCII control characters, for the `i` key option; bytes
+/// from 0x80 up are kept so that UTF-8 text survives
+fn printable_bytes(bytes: &[u8]) -> Vec<u8> {
+    bytes
+        .iter()
+        .copied()
+        .filter(|b| !b.is_ascii_control())
+        .collect()
+}
 
-        // Parse lines while keeping references to the mmap
-        let lines = parse_lines(&mmap);
+/// Length of a key for the `L` key option: characters when `utf8` is set,
+/// counting each byte that does not continue a UTF-8 sequence, else bytes
+fn key_length(bytes: &[u8], utf8: bool) -> usize {
+    if utf8 {
+        bytes.iter().filter(|&&b| b & 0xC0 != 0x80).count()
+    } else {
+        bytes.len()
+    }
+}
 
-        Ok(Self { _mmap: mmap, lines })
+/// `bytes` without any character of `chars` (`--ignore-chars`); text that
+/// is not UTF-8 only loses the ASCII ones
+fn without_chars(bytes: &[u8], chars: &str) -> Vec<u8> {
+    match std::str::from_utf8(bytes) {
+        Ok(text) => text
+            .chars()
+            .filter(|c| !chars.contains(*c))
+            .collect::<String>()
+            .into_bytes(),
+        Err(_) => bytes
+            .iter()
+            .copied()
+            .filter(|&b| !(b.is_ascii() && chars.as_bytes().contains(&b)))
+            .collect(),
     }
+}
 
-    /// Get the lines in this file
-    pub fn lines(&self) -> &[Line] {
-        &self.lines
+/// Fail on the first line that is longer than `--max-line-length` allows
+/// or, under `--strict-fields`, lacks a field the keys reference;
+/// `first_line` is the 1-based number of the first line
+pub fn check_input_lines(
+    lines: impl IntoIterator<Item = Line>,
+    first_line: usize,
+    config: &crate::config::SortConfig,
+) -> io::Result<()> {
+    let max_len = config
+        .max_line_length
+        .filter(|_| config.long_lines == crate::config::LongLines::Error);
+    let strict = config.strict_fields && !config.keys.is_empty();
+    if max_len.is_none() && !strict {
+        return Ok(());
     }
+    for (index, line) in lines.into_iter().enumerate() {
+        let error = match max_len {
+            Some(limit) if line.len() > limit => {
+                crate::error::SortError::line_too_long(first_line + index, limit)
+            }
+            _ if strict => {
+                match line.missing_key_field(&config.keys, config.field_separator, config) {
+                    Some(field) => {
+                        crate::error::SortError::missing_field(first_line + index, field)
+                    }
+                    None => continue,
+                }
+            }
+            _ => continue,
+        };
+        return Err(io::Error::new(io::ErrorKind::InvalidData, error));
+    }
+    Ok(())
 }
 
 /// Fast line parsing that creates Line structs pointing into the mmap'd data
-fn parse_lines(data: &[u8]) -> Vec<Line> {
-    let mut lines = Vec::new();
+pub fn parse_lines(data: &[u8]) -> Vec<Line> {
+    parse_lines_with_terminator(data, b'\n')
+}
+
+/// Split `data` into lines ending with `terminator`; a `\r` before a newline
+/// terminator is dropped as part of a Windows line ending
+pub fn parse_lines_with_terminator(data: &[u8], terminator: u8) -> Vec<Line> {
+    let strip_cr = terminator == b'\n';
+    // Size the index exactly: one line per terminator, plus an unterminated last line
+    let unterminated = data.last().is_some_and(|&b| b != terminator);
+    let mut lines = Vec::with_capacity(
+        SIMDCompare::count_byte_simd(data, terminator) + usize::from(unterminated),
+    );
     let mut start = 0;
 
     for (i, &byte) in data.iter().enumerate() {
-        if byte == b'\n' {
+        if byte == terminator {
             // Handle both Unix (\n) and Windows (\r\n) line endings
-            let end = if i > 0 && data[i - 1] == b'\r' {
+            let end = if strip_cr && i > 0 && data[i - 1] == b'\r' {
                 i - 1
             } else {
                 i
@@ -957,7 +1185,7 @@ fn parse_lines(data: &[u8]) -> Vec<Line> {
     if start < data.len() {
         let mut end = data.len();
         // Strip trailing \r if present
-        if end > start && data[end - 1] == b'\r' {
+        if strip_cr && end > start && data[end - 1] == b'\r' {
             end -= 1;
         }
         let line_data = &data[start..end];
@@ -970,225 +1198,80 @@ fn parse_lines(data: &[u8]) -> Vec<Line> {
 /// Zero-copy line reader for streaming large files
 pub struct ZeroCopyReader {
     reader: BufReader<File>,
-    buffer: Vec<u8>,
+    terminator: u8,
+}
+
+/// Lines read by [`ZeroCopyReader::read_chunk`], together with the buffer
+/// they point into
+pub struct LineChunk {
+    /// Not modified once the lines are built, so they stay valid wherever
+    /// the chunk is moved
+    _buffer: Vec<u8>,
     lines: Vec<Line>,
 }
 
+impl LineChunk {
+    /// The lines of the chunk, valid for as long as the chunk lives
+    pub fn lines(&self) -> &[Line] {
+        &self.lines
+    }
+}
+
 impl ZeroCopyReader {
     pub fn new(file: File) -> Self {
+        Self::with_terminator(file, b'\n')
+    }
+
+    /// Read lines ending with `terminator` (`b'\0'` for `-z`)
+    pub fn with_terminator(file: File, terminator: u8) -> Self {
         Self {
             reader: BufReader::new(file),
-            buffer: Vec::with_capacity(64 * 1024), // 64KB buffer
-            lines: Vec::new(),
+            terminator,
         }
     }
 
-    /// Read next chunk of lines, reusing the internal buffer
-    pub fn read_chunk(&mut self) -> io::Result<&[Line]> {
-        self.buffer.clear();
-        self.lines.clear();
-
-        let mut total_read = 0;
+    /// Read the next chunk of lines, about 64KB, into a buffer of its own.
+    /// Lines are only built once the buffer is complete, as growing it may
+    /// move its contents; chunks from several reads can be held at once.
+    pub fn read_chunk(&mut self) -> io::Result<LineChunk> {
         const CHUNK_SIZE: usize = 64 * 1024;
 
-        // Read up to CHUNK_SIZE bytes
-        while total_read < CHUNK_SIZE {
-            let mut line_buf = Vec::new();
-            let bytes_read = self.reader.read_until(b'\n', &mut line_buf)?;
+        let mut buffer = Vec::with_capacity(CHUNK_SIZE);
+        let mut spans = Vec::new();
 
-            if bytes_read == 0 {
+        // Read up to CHUNK_SIZE bytes
+        while buffer.len() < CHUNK_SIZE {
+            let start = buffer.len();
+            if self.reader.read_until(self.terminator, &mut buffer)? == 0 {
                 break; // EOF
             }
 
-            let start_idx = self.buffer.len();
-            self.buffer.extend_from_slice(&line_buf);
-
-            // Remove trailing newline if present
-            let end_idx = if line_buf.ends_with(b"\n") {
-                self.buffer.len() - 1
-            } else {
-                self.buffer.len()
-            };
-
-            let line_data = &self.buffer[start_idx..end_idx];
-            self.lines.push(Line::new(line_data));
-
-            total_read += bytes_read;
+            // Remove trailing terminator if present, and with a newline
+            // terminator the `\r` of a Windows line ending, as `parse_lines` does
+            let mut end = buffer.len();
+            if buffer[end - 1] == self.terminator {
+                end -= 1;
+            }
+            if self.terminator == b'\n' && end > start && buffer[end - 1] == b'\r' {
+                end -= 1;
+            }
+            spans.push((start, end));
         }
 
-        Ok(&self.lines)
+        let lines = spans
+            .into_iter()
+            .map(|(start, end)| Line::new(&buffer[start..end]))
+            .collect();
+        Ok(LineChunk {
+            _buffer: buffer,
+            lines,
+        })
     }
 }
 
 /// Optimized numeric comparison for Line structs
 pub fn compare_numeric_lines(a: &Line, b: &Line) -> Ordering {
-    unsafe {
-        let a_bytes = a.as_bytes();
-        let b_bytes = b.as_bytes();
-
-        // Fast path for simple integer comparison
-        if let (Some(a_num), Some(b_num)) = (parse_int(a_bytes), parse_int(b_bytes)) {
-            return a_num.cmp(&b_num);
-        }
-
-        // Fall back to lexicographic comparison for complex numbers
-        compare_numeric_bytes(a_bytes, b_bytes)
-    }
-}
-
-/// Fast integer parsing for simple cases (digits only, no signs/decimals)
-fn parse_int(bytes: &[u8]) -> Option<i64> {
-    if bytes.is_empty() {
-        return Some(0);
-    }
-
-    let mut result: i64 = 0;
-    let mut negative = false;
-    let mut start = 0;
-
-    // Handle leading sign
-    if bytes[0] == b'-' {
-        negative = true;
-        start = 1;
-    } else if bytes[0] == b'+' {
-        start = 1;
-    }
-
-    // Parse digits
-    for &byte in &bytes[start..] {
-        if !byte.is_ascii_digit() {
-            return None; // Not a simple integer
-        }
-
-        result = result.checked_mul(10)?;
-        result = result.checked_add((byte - b'0') as i64)?;
-    }
-
-    if negative {
-        result = -result;
-    }
-
-    Some(result)
-}
-
-/// Numeric comparison for complex numbers (with decimals, scientific notation, etc.)
-fn compare_numeric_bytes(a: &[u8], b: &[u8]) -> Ordering {
-    // Skip leading whitespace
-    let a = skip_whitespace(a);
-    let b = skip_whitespace(b);
-
-    // Handle empty strings
-    match (a.is_empty(), b.is_empty()) {
-        (true, true) => return Ordering::Equal,
-        (true, false) => return Ordering::Less,
-        (false, true) => return Ordering::Greater,
-        (false, false) => {
-            // Continue with comparison
-        }
-    }
-
-    // Extract signs
-    let (a_negative, a_digits) = extract_sign(a);
-    let (b_negative, b_digits) = extract_sign(b);
-
-    // Compare signs
-    match (a_negative, b_negative) {
-        (false, true) => return Ordering::Greater,
-        (true, false) => return Ordering::Less,
-        _ => {}
-    }
-
-    // Both have same sign, compare magnitudes
-    let magnitude_cmp = compare_magnitude(a_digits, b_digits);
-
-    if a_negative {
-        magnitude_cmp.reverse()
-    } else {
-        magnitude_cmp
-    }
-}
-
-fn skip_whitespace(bytes: &[u8]) -> &[u8] {
-    let start = bytes
-        .iter()
-        .position(|&b| !b.is_ascii_whitespace())
-        .unwrap_or(bytes.len());
-    &bytes[start..]
-}
-
-fn extract_sign(bytes: &[u8]) -> (bool, &[u8]) {
-    if bytes.starts_with(b"-") {
-        (true, &bytes[1..])
-    } else if bytes.starts_with(b"+") {
-        (false, &bytes[1..])
-    } else {
-        (false, bytes)
-    }
-}
-
-fn compare_magnitude(a: &[u8], b: &[u8]) -> Ordering {
-    // Find decimal points
-    let a_dot = a.iter().position(|&b| b == b'.');
-    let b_dot = b.iter().position(|&b| b == b'.');
-
-    let (a_int, a_frac) = match a_dot {
-        Some(pos) => (&a[..pos], &a[pos + 1..]),
-        None => (a, &[][..]),
-    };
-
-    let (b_int, b_frac) = match b_dot {
-        Some(pos) => (&b[..pos], &b[pos + 1..]),
-        None => (b, &[][..]),
-    };
-
-    // Compare integer parts
-    let int_cmp = compare_integer_parts(a_int, b_int);
-    if int_cmp != Ordering::Equal {
-        return int_cmp;
-    }
-
-    // Compare fractional parts
-    compare_fractional_parts(a_frac, b_frac)
-}
-
-fn compare_integer_parts(a: &[u8], b: &[u8]) -> Ordering {
-    // Remove leading zeros
-    let a = skip_leading_zeros(a);
-    let b = skip_leading_zeros(b);
-
-    // Compare lengths first
-    let len_cmp = a.len().cmp(&b.len());
-    if len_cmp != Ordering::Equal {
-        return len_cmp;
-    }
-
-    // Same length, compare digit by digit
-    a.cmp(b)
-}
-
-fn compare_fractional_parts(a: &[u8], b: &[u8]) -> Ordering {
-    let max_len = a.len().max(b.len());
-
-    for i in 0..max_len {
-        let a_digit = a.get(i).copied().unwrap_or(b'0');
-        let b_digit = b.get(i).copied().unwrap_or(b'0');
-
-        let cmp = a_digit.cmp(&b_digit);
-        if cmp != Ordering::Equal {
-            return cmp;
-        }
-    }
-
-    Ordering::Equal
-}
-
-fn skip_leading_zeros(bytes: &[u8]) -> &[u8] {
-    let start = bytes.iter().position(|&b| b != b'0').unwrap_or(bytes.len());
-    if start == bytes.len() {
-        b"0" // All zeros, return single zero
-    } else {
-        &bytes[start..]
-    }
+    a.compare_numeric(b)
 }
 
 /// Fast case-insensitive comparison with locale support
@@ -1201,8 +1284,8 @@ pub fn compare_case_insensitive(a: &[u8], b: &[u8]) -> Ordering {
         let min_len = a.len().min(b.len());
 
         for i in 0..min_len {
-            let a_char = a[i].to_ascii_lowercase();
-            let b_char = b[i].to_ascii_lowercase();
+            let a_char = a[i].to_ascii_uppercase();
+            let b_char = b[i].to_ascii_uppercase();
 
             match a_char.cmp(&b_char) {
                 Ordering::Equal => continue,
@@ -1242,13 +1325,57 @@ mod tests {
 
     #[test]
     fn test_simple_int_parsing() {
-        assert_eq!(parse_int(b"123"), Some(123));
-        assert_eq!(parse_int(b"-456"), Some(-456));
-        assert_eq!(parse_int(b"+789"), Some(789));
-        assert_eq!(parse_int(b"0"), Some(0));
-        assert_eq!(parse_int(b""), Some(0));
-        assert_eq!(parse_int(b"12.34"), None); // Not simple
-        assert_eq!(parse_int(b"abc"), None); // Not numeric
+        let parse = |bytes: &[u8]| Line::new(bytes).parse_int();
+        assert_eq!(parse(b"123"), Some(123));
+        assert_eq!(parse(b"-456"), Some(-456));
+        assert_eq!(parse(b"0"), Some(0));
+        // Anything but bare digits leaves the fast path; -n still reads it
+        assert_eq!(parse(b"+789"), None);
+        assert_eq!(parse(b""), None);
+        assert_eq!(parse(b"12.34"), None); // Not simple
+        assert_eq!(parse(b"abc"), None); // Not numeric
+        assert_eq!(
+            Line::new(b"").compare_numeric(&Line::new(b"0")),
+            Ordering::Equal
+        );
+        assert_eq!(
+            Line::new(b"12.34").compare_numeric(&Line::new(b"12.4")),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_compare_month() {
+        let month = |a: &[u8], b: &[u8]| Line::new(a).compare_month(&Line::new(b));
+        assert_eq!(month(b"JAN", b"FEB"), Ordering::Less);
+        assert_eq!(month(b"  dec", b"Nov"), Ordering::Greater);
+        assert_eq!(month(b"January", b"jan 5"), Ordering::Equal);
+        // Anything that names no month comes before January
+        assert_eq!(month(b"xyz", b"JAN"), Ordering::Less);
+        assert_eq!(month(b"", b"ja"), Ordering::Equal);
+
+        let config = crate::config::SortConfig::default().with_mode(crate::config::SortMode::Month);
+        let sorted = crate::sort_bytes(&config, b"MAR\nJAN\nFEB\n  apr\nxyz\n").unwrap();
+        assert_eq!(sorted, b"xyz\nJAN\nFEB\nMAR\n  apr\n");
+    }
+
+    #[test]
+    fn test_compare_version() {
+        let version = |a: &[u8], b: &[u8]| Line::new(a).compare_version(&Line::new(b));
+        assert_eq!(version(b"1.0.9", b"1.0.10"), Ordering::Less);
+        assert_eq!(version(b"foo-1.2", b"foo-1.11"), Ordering::Less);
+        assert_eq!(version(b"1.007", b"1.7"), Ordering::Equal);
+        // A tilde sorts before everything, even the end of the version
+        assert_eq!(version(b"1.0~rc1", b"1.0"), Ordering::Less);
+        assert_eq!(version(b"1.0~rc1", b"1.0~rc2"), Ordering::Less);
+        assert_eq!(version(b"1.0a", b"1.0"), Ordering::Greater);
+        // Suffixes only break ties
+        assert_eq!(version(b"pkg-1.2.tar.gz", b"pkg-1.10.tar"), Ordering::Less);
+
+        let config =
+            crate::config::SortConfig::default().with_mode(crate::config::SortMode::Version);
+        let sorted = crate::sort_bytes(&config, b"1.10\n1.0~beta\n1.2\n1.0\n").unwrap();
+        assert_eq!(sorted, b"1.0~beta\n1.0\n1.2\n1.10\n");
     }
 
     #[test]
@@ -1291,4 +1418,706 @@ mod tests {
             assert_eq!(single_lines[0].as_bytes(), b"single_line");
         }
     }
+
+    #[test]
+    fn test_parse_lines_empty_lines() {
+        // As GNU sort reads them: every terminator ends a line, empty or
+        // not, and nothing after the last one means no further line
+        let cases: [(&[u8], &[&[u8]]); 7] = [
+            (b"", &[]),
+            (b"\n", &[b""]),
+            (b"\n\n", &[b"", b""]),
+            (b"\n\n\n", &[b"", b"", b""]),
+            (b"a\n\n", &[b"a", b""]),
+            (b"\n\na\n", &[b"", b"", b"a"]),
+            (b"\r\n\r\n", &[b"", b""]),
+        ];
+        let owned = |lines: Vec<Line>| -> Vec<Vec<u8>> {
+            lines
+                .iter()
+                .map(|line| unsafe { line.as_bytes() }.to_vec())
+                .collect()
+        };
+        for (data, expected) in cases {
+            assert_eq!(owned(parse_lines(data)), expected, "{data:?}");
+        }
+
+        assert_eq!(
+            owned(parse_lines_with_terminator(b"\0\0a\0", 0)),
+            [&b""[..], b"", b"a"]
+        );
+    }
+
+    #[test]
+    fn test_parse_lines_is_sized_exactly() {
+        let long: Vec<u8> = (0..10_000u32)
+            .flat_map(|i| format!("{i}\r\n").into_bytes())
+            .collect();
+        let inputs: [(&[u8], u8); 7] = [
+            (b"", b'\n'),
+            (b"\n", b'\n'),
+            (b"a\nb", b'\n'),
+            (b"a\nb\n\n", b'\n'),
+            (b"a\0b\0", 0),
+            (b"a\nb\0c", 0),
+            (&long, b'\n'),
+        ];
+        for (data, terminator) in inputs {
+            let lines = parse_lines_with_terminator(data, terminator);
+            assert_eq!(lines.capacity(), lines.len(), "{data:?}");
+            let expected = data.split(|&b| b == terminator).count()
+                - usize::from(data.last().map_or(true, |&b| b == terminator));
+            assert_eq!(lines.len(), expected, "{data:?}");
+        }
+    }
+
+    #[test]
+    fn test_mapped_file_line_offsets() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("input.txt");
+        let data = b"pear\r\napple\n\nfig";
+        std::fs::write(&path, data)?;
+
+        let mapped = MappedFile::new(&path)?;
+        let spans: Vec<(usize, usize)> = mapped.line_spans().collect();
+        assert_eq!(spans, [(0, 4), (6, 5), (12, 0), (13, 3)]);
+        for ((offset, len), line) in spans.iter().zip(mapped.lines()) {
+            assert_eq!(&data[*offset..offset + len], unsafe { line.as_bytes() });
+        }
+
+        // Sorted copies still map back to their source positions
+        let mut sorted = mapped.lines().to_vec();
+        sorted.sort_by(|a, b| a.compare_lexicographic(b));
+        let offsets: Vec<Option<usize>> = sorted.iter().map(|l| mapped.offset_of(l)).collect();
+        assert_eq!(offsets, [Some(12), Some(6), Some(13), Some(0)]);
+        assert_eq!(mapped.offset_of(&Line::new(b"elsewhere")), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_multibyte_separator_splits_on_whole_character() {
+        use crate::config::{SortConfig, SortKey};
+
+        // '§' is 0xC2 0xA7; splitting on either byte alone would make five fields
+        let line = Line::new("a§b§c".as_bytes());
+        let fields: Vec<Option<&[u8]>> =
+            (1..=4).map(|n| line.extract_field(n, Some('§'))).collect();
+        assert_eq!(
+            fields,
+            [Some(&b"a"[..]), Some(&b"b"[..]), Some(&b"c"[..]), None]
+        );
+        // A stray lead byte is not a separator
+        let stray = Line::new(b"a\xC2b\xA7c");
+        assert_eq!(stray.extract_field(1, Some('§')), Some(&b"a\xC2b\xA7c"[..]));
+
+        let config = SortConfig::new();
+        let key = SortKey::parse("3,3").expect("Failed to parse key");
+        assert_eq!(
+            line.extract_key_with_config(&key, Some('§'), &config),
+            Some(&b"c"[..])
+        );
+    }
+
+    #[test]
+    fn test_dictionary_order() {
+        use crate::config::{SortConfig, SortKey};
+
+        let cmp = |a: &str, b: &str| {
+            Line::new(a.as_bytes()).compare_dictionary_order(&Line::new(b.as_bytes()))
+        };
+        assert_eq!(cmp("a-b", "ab"), Ordering::Equal);
+        assert_eq!(cmp("a.c", "a-d"), Ordering::Less);
+        assert_eq!(cmp("a b", "ab"), Ordering::Less);
+        assert_eq!(
+            Line::new(b"A-b").compare_dictionary_order_ignore_case(&Line::new(b"ab")),
+            Ordering::Equal
+        );
+
+        // Equal under -d, the lines still order by their raw bytes unless -s
+        let config = SortConfig {
+            dictionary_order: true,
+            ..SortConfig::default()
+        };
+        let (a, b) = (Line::new(b"a-b"), Line::new(b"ab"));
+        assert_eq!(a.compare_with_keys(&b, &[], None, &config), Ordering::Less);
+        let stable = config.clone().with_stable(true);
+        assert_eq!(a.compare_with_keys(&b, &[], None, &stable), Ordering::Equal);
+
+        // The same through a key with the d and f options
+        let keys = [SortKey::parse("1df").expect("Failed to parse key")];
+        let (a, b) = (Line::new(b"A-b"), Line::new(b"a.b"));
+        assert_eq!(
+            a.compare_with_keys(&b, &keys, None, &stable),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_ignore_leading_blanks_in_comparisons() {
+        use crate::config::{SortConfig, SortKey};
+
+        let (padded, plain) = (Line::new(b"  apple"), Line::new(b"apple"));
+        let blanks = SortConfig {
+            ignore_leading_blanks: true,
+            stable: true,
+            ..SortConfig::default()
+        };
+        assert_eq!(
+            padded.compare_with_keys(&plain, &[], None, &blanks),
+            Ordering::Equal
+        );
+        let stable = SortConfig::default().with_stable(true);
+        assert_eq!(
+            padded.compare_with_keys(&plain, &[], None, &stable),
+            Ordering::Less
+        );
+
+        // Per key, a field's blanks are skipped only where `b` is given
+        let key = |spec: &str| [SortKey::parse(spec).expect("Failed to parse key")];
+        let (a, b) = (Line::new(b"x   b"), Line::new(b"x a"));
+        assert_eq!(
+            a.compare_with_keys(&b, &key("2"), None, &stable),
+            Ordering::Less
+        );
+        assert_eq!(
+            a.compare_with_keys(&b, &key("2b"), None, &stable),
+            Ordering::Greater
+        );
+        assert_eq!(
+            Line::new(b"x  apple").compare_with_keys(
+                &Line::new(b"x apple"),
+                &key("2b,2"),
+                None,
+                &stable
+            ),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_field_separator_string() {
+        use crate::config::{SortConfig, SortKey};
+
+        let line = Line::new(b"a||b c||||d");
+        assert_eq!(line.extract_field_by_string(1, b"||"), Some(&b"a"[..]));
+        assert_eq!(line.extract_field_by_string(2, b"||"), Some(&b"b c"[..]));
+        assert_eq!(line.extract_field_by_string(3, b"||"), Some(&b""[..]));
+        assert_eq!(line.extract_field_by_string(4, b"||"), Some(&b"d"[..]));
+        assert_eq!(line.extract_field_by_string(5, b"||"), None);
+        // A single '|' is not a separator
+        assert_eq!(
+            Line::new(b"x|y||z").extract_field_by_string(1, b"||"),
+            Some(&b"x|y"[..])
+        );
+
+        let key = SortKey::parse("2,2").expect("Failed to parse key");
+        assert_eq!(line.extract_key_with_string(&key, b"||"), Some(&b"b c"[..]));
+        let key = SortKey::parse("2.3,4").expect("Failed to parse key");
+        assert_eq!(
+            line.extract_key_with_string(&key, b"||"),
+            Some(&b"c||||d"[..])
+        );
+
+        let config = SortConfig::new().with_field_separator_string(Some("||".to_string()));
+        let keys = [SortKey::parse("2,2").expect("Failed to parse key")];
+        let mut lines = [
+            Line::new(b"1||pear||x"),
+            Line::new(b"2||apple||y"),
+            Line::new(b"3||fig"),
+        ];
+        lines.sort_by(|a, b| a.compare_with_keys(b, &keys, None, &config));
+        let sorted: Vec<&[u8]> = lines.iter().map(|l| unsafe { l.as_bytes() }).collect();
+        assert_eq!(
+            sorted,
+            [&b"2||apple||y"[..], &b"3||fig"[..], &b"1||pear||x"[..]]
+        );
+    }
+
+    #[test]
+    fn test_accounting_negatives() {
+        use crate::config::{SortConfig, SortKey, SortMode};
+
+        let input: [&[u8]; 6] = [b"(100)", b"-50", b"0", b"50", b"(100)", b" (7.5)"];
+        let expected: [&[u8]; 6] = [b"(100)", b"(100)", b"-50", b" (7.5)", b"0", b"50"];
+
+        for mode in [SortMode::Numeric, SortMode::GeneralNumeric] {
+            let config = SortConfig::default().with_mode(mode).with_accounting(true);
+            let mut lines: Vec<Line> = input.iter().map(|bytes| Line::new(bytes)).collect();
+            lines.sort_by(|a, b| a.compare_with_keys(b, &[], None, &config));
+            let sorted: Vec<&[u8]> = lines.iter().map(|l| unsafe { l.as_bytes() }).collect();
+            assert_eq!(sorted, expected, "{mode:?}");
+        }
+
+        // Same through a numeric key
+        let config = SortConfig::default().with_accounting(true);
+        let keys = [SortKey::parse("2n").expect("Failed to parse key")];
+        let (a, b) = (Line::new(b"x (100)"), Line::new(b"y -50"));
+        assert_eq!(
+            a.compare_with_keys(&b, &keys, None, &config),
+            Ordering::Less
+        );
+
+        // Comparing in place agrees with rewriting `(x)` as `-x` first
+        let values: [&[u8]; 16] = [
+            b"(100)", b"-100", b"( 5)", b"(+5)", b"(-5)", b"(.5)", b"(5.)", b"()", b"(abc)",
+            b"(nan)", b"(inf)", b"(1e3)", b"-1e3", b"(0)", b"0", b" (7.5) ",
+        ];
+        let rewritten = |bytes: &[u8]| match accounting_parts(bytes) {
+            (true, inner) => [&b"-"[..], inner].concat(),
+            (false, bytes) => bytes.to_vec(),
+        };
+        for a in values {
+            for b in values {
+                let (ra, rb) = (rewritten(a), rewritten(b));
+                let (ra, rb) = (Line::new(&ra), Line::new(&rb));
+                let (a, b) = (Line::new(a), Line::new(b));
+                assert_eq!(a.compare_accounting(&b, false), ra.compare_numeric(&rb));
+                assert_eq!(
+                    a.compare_accounting(&b, true),
+                    ra.compare_general_numeric(&rb)
+                );
+            }
+        }
+
+        // Without the flag parentheses are not a number
+        let config = SortConfig::default().with_mode(SortMode::Numeric);
+        assert_eq!(
+            Line::new(b"(100)").compare_with_config(&Line::new(b"-50"), &config),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_numeric_ignores_exponent() {
+        // -n reads "1e3" as the number 1 followed by the non-numeric "e3"
+        let exp = Line::new(b"1e3");
+        assert_eq!(exp.compare_numeric(&Line::new(b"1")), Ordering::Equal);
+        assert_eq!(exp.compare_numeric(&Line::new(b"2")), Ordering::Less);
+        assert_eq!(exp.compare_numeric(&Line::new(b"0")), Ordering::Greater);
+        assert_eq!(
+            Line::new(b"-1e3").compare_numeric(&Line::new(b"-1")),
+            Ordering::Equal
+        );
+    }
+
+    #[test]
+    fn test_empty_numeric_compares_as_zero() {
+        // GNU reads an empty or blank -n value as 0
+        for blank in [&b""[..], b"  "] {
+            let blank = Line::new(blank);
+            assert_eq!(blank.compare_numeric(&Line::new(b"0")), Ordering::Equal);
+            assert_eq!(blank.compare_numeric(&Line::new(b"-1")), Ordering::Greater);
+            assert_eq!(blank.compare_numeric(&Line::new(b"1")), Ordering::Less);
+        }
+
+        // Whole-line and keyed sorts agree, ties broken bytewise
+        let whole = crate::SortConfig::new().with_mode(crate::SortMode::Numeric);
+        let keyed =
+            crate::SortConfig::new().add_key(crate::config::SortKey::parse("1,1n").unwrap());
+        for config in [whole, keyed] {
+            let sorted = crate::sort_bytes(&config, b"1\n\n0\n-1\n").unwrap();
+            assert_eq!(sorted, b"-1\n\n0\n1\n");
+        }
+
+        // So does the radix sorter, past its insertion-sort cutoff
+        let values: [&[u8]; 4] = [b"1", b"", b"0", b"-1"];
+        let mut lines: Vec<Line> = (0..1200).map(|i| Line::new(values[i % 4])).collect();
+        crate::radix_sort::RadixSort::new(false).sort_numeric_lines(&mut lines);
+        assert!(lines
+            .windows(2)
+            .all(|pair| pair[0].compare_numeric(&pair[1]) != Ordering::Greater));
+    }
+
+    #[test]
+    fn test_human_numeric_orders_negatives_below_positives() {
+        let sorted: [&[u8]; 9] = [
+            b"-2G", b"-1.5M", b"-1K", b"-512", b"0", b"512", b"1K", b"1.5M", b"2G",
+        ];
+        let mut lines: Vec<Line> = sorted.iter().rev().map(|b| Line::new(b)).collect();
+        lines.sort_by(|a, b| a.compare_human_numeric(b));
+        let result: Vec<&[u8]> = lines.iter().map(|l| unsafe { l.as_bytes() }).collect();
+        assert_eq!(result, sorted);
+
+        // The suffix decides before the value does, as in GNU sort
+        assert_eq!(
+            Line::new(b"-1k").compare_human_numeric(&Line::new(b"-1024")),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_human_numeric_scales_by_suffix() {
+        let human = |a: &[u8], b: &[u8]| Line::new(a).compare_human_numeric(&Line::new(b));
+        assert_eq!(human(b"1K", b"1M"), Ordering::Less);
+        assert_eq!(human(b"1M", b"1G"), Ordering::Less);
+        assert_eq!(human(b"900", b"1K"), Ordering::Less);
+        assert_eq!(human(b"2K", b"1G"), Ordering::Less);
+        // Lines without a number count as zero
+        assert_eq!(human(b"abc", b"0"), Ordering::Equal);
+        assert_eq!(human(b"", b"-1K"), Ordering::Greater);
+
+        let config =
+            crate::config::SortConfig::default().with_mode(crate::config::SortMode::HumanNumeric);
+        let sorted = crate::sort_bytes(&config, b"1G\n2K\n900\n1M\n").unwrap();
+        assert_eq!(sorted, b"900\n2K\n1M\n1G\n");
+    }
+
+    #[test]
+    fn test_general_numeric_reads_exponent() {
+        // -g parses "1e3" as 1000
+        let exp = Line::new(b"1e3");
+        assert_eq!(exp.parse_general_numeric(), 1000.0);
+        assert_eq!(
+            exp.compare_general_numeric(&Line::new(b"999")),
+            Ordering::Greater
+        );
+        assert_eq!(
+            exp.compare_general_numeric(&Line::new(b"1001")),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_key_blanks_on_start_or_end() {
+        use crate::config::SortKey;
+
+        let line = Line::new(b"a:  xy:  pq");
+        let key = |spec: &str| SortKey::parse(spec).expect("Failed to parse key");
+        let extract = |spec: &str| line.extract_key(&key(spec), Some(':')).map(|k| k.to_vec());
+
+        // `b` on the start skips the start field's blanks only
+        assert_eq!(extract("2b,3.1"), Some(b"xy: ".to_vec()));
+        // `b` on the end skips the end field's blanks only
+        assert_eq!(extract("2,3.1b"), Some(b"  xy:  p".to_vec()));
+        assert_eq!(extract("2,3.1"), Some(b"  xy: ".to_vec()));
+        assert_eq!(extract("2b,3"), Some(b"xy:  pq".to_vec()));
+        assert_eq!(extract("2,3b"), Some(b"  xy:  pq".to_vec()));
+
+        // Ordering follows: with start blanks kept, " b" sorts before "a"
+        let config = crate::config::SortConfig::default();
+        let padded = Line::new(b"x: b");
+        let plain = Line::new(b"x:a");
+        assert_eq!(
+            padded.compare_with_keys(&plain, &[key("2b,2")], Some(':'), &config),
+            Ordering::Greater
+        );
+        assert_eq!(
+            padded.compare_with_keys(&plain, &[key("2,2b")], Some(':'), &config),
+            Ordering::Less
+        );
+    }
+
+    #[test]
+    fn test_key_without_end_field_runs_to_end_of_line() {
+        use crate::config::{SortConfig, SortKey};
+
+        let key = |spec: &str| SortKey::parse(spec).expect("Failed to parse key");
+        let line = Line::new(b"a x 2");
+        assert_eq!(line.extract_key(&key("2"), None), Some(&b" x 2"[..]));
+        assert_eq!(line.extract_key(&key("2.2"), Some(' ')), Some(&b" 2"[..]));
+
+        // The key " x 2" against " x 1" is decided past field 2
+        let stable = SortConfig::default().add_key(key("2")).with_stable(true);
+        let sorted = crate::sort_bytes(&stable, b"a x 2\nb x 1\n").unwrap();
+        assert_eq!(sorted, b"b x 1\na x 2\n");
+
+        // An empty third field still leaves the rest of the line as the key
+        let spaced = SortConfig::default()
+            .add_key(key("3"))
+            .with_field_separator(Some(' '))
+            .with_stable(true);
+        let sorted = crate::sort_bytes(&spaced, b"a b  z\nc d  y\ne f\n").unwrap();
+        assert_eq!(sorted, b"e f\nc d  y\na b  z\n");
+    }
+
+    #[test]
+    fn test_single_character_key() {
+        use crate::config::{SortConfig, SortKey};
+
+        let key = SortKey::parse("1.3,1.3").expect("Failed to parse key");
+        for (sep, line, short) in [(None, "abz1 q", "ab q"), (Some(':'), "abz1:q", "ab:q")] {
+            let key_of = |text: &str| {
+                Line::new(text.as_bytes())
+                    .extract_key(&key, sep)
+                    .map(<[u8]>::to_vec)
+            };
+            assert_eq!(key_of(line), Some(b"z".to_vec()));
+            // Past a short field the position runs on into the separator, as in GNU sort
+            assert_eq!(key_of(short), Some(short.as_bytes()[2..3].to_vec()));
+        }
+        assert_eq!(Line::new(b"ab").extract_key(&key, None), Some(&b""[..]));
+
+        // Only the third character matters; the rest of the line is the tie-break
+        let config = SortConfig::default();
+        let keys = [key.clone()];
+        let mut lines = [
+            Line::new(b"AAz1"),
+            Line::new(b"ZZa9"),
+            Line::new(b"MMc2"),
+            Line::new(b"BBa3"),
+            Line::new(b"XY"),
+        ];
+        lines.sort_by(|a, b| a.compare_with_keys(b, &keys, None, &config));
+        let sorted: Vec<&[u8]> = lines.iter().map(|l| unsafe { l.as_bytes() }).collect();
+        assert_eq!(sorted, [&b"XY"[..], b"BBa3", b"ZZa9", b"MMc2", b"AAz1"]);
+    }
+
+    #[test]
+    fn test_identical_lines_compare_equal_under_any_keys() {
+        use crate::config::{SortConfig, SortKey};
+
+        // Separate buffers, so only the bytes agree
+        let first = b"beta 10 K2 1.2.3 a\x01b".to_vec();
+        let second = first.clone();
+        let (a, b) = (Line::new(&first), Line::new(&second));
+        for spec in [
+            "1,1", "2,2nr", "2,2g", "3,3h", "4,4V", "5,5dfi", "1.2,2R", "3,3M",
+        ] {
+            let keys = [SortKey::parse(spec).expect("Failed to parse key")];
+            for config in [
+                SortConfig::default(),
+                SortConfig::default().with_stable(true),
+                SortConfig::default().with_reverse(true),
+            ] {
+                assert_eq!(
+                    a.compare_with_keys(&b, &keys, None, &config),
+                    Ordering::Equal
+                );
+            }
+        }
+
+        // Lines that only agree on the key still reach the key comparison
+        let keys = [SortKey::parse("2,2n").expect("Failed to parse key")];
+        let other = Line::new(b"alpha 10");
+        let stable = SortConfig::default().with_stable(true);
+        assert_eq!(
+            a.compare_with_keys(&other, &keys, None, &stable),
+            Ordering::Equal
+        );
+        assert_eq!(
+            a.compare_with_keys(&other, &keys, None, &SortConfig::default()),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_debug_underlines_leave_out_ignored_characters() {
+        use crate::config::{SortConfig, SortKey};
+
+        let underlines =
+            |text: &[u8], config: &SortConfig| Line::new(text).debug_underlines(config);
+        let key = |spec: &str| SortKey::parse(spec).expect("Failed to parse key");
+
+        // -d: the punctuation is not compared, so it is not underlined
+        let dictionary = SortConfig {
+            dictionary_order: true,
+            ..SortConfig::default()
+        };
+        assert_eq!(underlines(b"a,b.c d", &dictionary), ["_ _ ___", "_______"]);
+        let keyed = SortConfig::default().add_key(key("2,2d"));
+        assert_eq!(underlines(b"x (a-b)", &keyed), [" _ _ _", "_______"]);
+
+        // b: skipped blanks stay unmarked; i: so do control characters
+        let blanks = SortConfig::default().add_key(key("2b,2"));
+        assert_eq!(underlines(b"x   ab", &blanks), ["    __", "______"]);
+        let nonprinting = SortConfig::default().add_key(key("2,2i")).with_stable(true);
+        assert_eq!(underlines(b"x a\x01b", &nonprinting), [" __ _"]);
+
+        // A key past the end of the line is reported, as GNU sort does
+        let missing = SortConfig::default().add_key(key("3,3"));
+        assert_eq!(
+            underlines(b"x y", &missing),
+            ["   ^ no match for key", "___"]
+        );
+        // Plain whole-line comparisons have the single, whole-line key
+        assert_eq!(underlines(b"x y", &SortConfig::default()), ["___"]);
+    }
+
+    #[test]
+    fn test_ignore_chars() {
+        use crate::config::{SortConfig, SortKey};
+
+        let config = SortConfig {
+            stable: true,
+            ..Default::default()
+        }
+        .with_ignore_chars(Some("\"'".to_string()));
+        let quoted = Line::new(b"'abc'");
+        let plain = Line::new(b"abc");
+        assert_eq!(
+            quoted.compare_with_keys(&plain, &[], None, &config),
+            Ordering::Equal
+        );
+        let key = SortKey::parse("2d").expect("Failed to parse key");
+        assert_eq!(
+            Line::new(b"x \"a-b\"").compare_with_keys(&Line::new(b"y ab"), &[key], None, &config),
+            Ordering::Equal
+        );
+
+        // Output keeps the characters
+        let sorted = crate::sort_bytes(&config, b"\"b\"\n'abc'\nabc\n").unwrap();
+        assert_eq!(sorted, b"'abc'\nabc\n\"b\"\n");
+    }
+
+    #[test]
+    fn test_key_length_option() {
+        use crate::config::{SortConfig, SortKey};
+
+        let key = SortKey::parse("2,2L").expect("Failed to parse key");
+        assert!(key.options.length);
+        assert_eq!(key.to_string(), "2,2L");
+
+        let config = SortConfig::default()
+            .with_field_separator(Some(':'))
+            .add_key(key);
+        let sorted = crate::sort_bytes(&config, b"a:xyz\nb:\nc:long one\nd:pq\ne:zz\n").unwrap();
+        assert_eq!(sorted, b"b:\nd:pq\ne:zz\na:xyz\nc:long one\n");
+
+        // Under a UTF-8 locale the length is counted in characters
+        let utf8 = SortConfig {
+            utf8_positions: true,
+            ..config.clone()
+        };
+        let sorted = crate::sort_bytes(&utf8, "a:\u{e9}\u{e9}\nb:abc\n".as_bytes()).unwrap();
+        assert_eq!(sorted, "a:\u{e9}\u{e9}\nb:abc\n".as_bytes());
+        let sorted = crate::sort_bytes(&config, "a:\u{e9}\u{e9}\nb:abc\n".as_bytes()).unwrap();
+        assert_eq!(sorted, "b:abc\na:\u{e9}\u{e9}\n".as_bytes());
+    }
+
+    #[test]
+    fn test_per_key_ignore_nonprinting() {
+        use crate::config::{SortConfig, SortKey};
+
+        let config = SortConfig::default();
+        let key = |spec: &str| SortKey::parse(spec).expect("Failed to parse key");
+        let noisy = Line::new(b"z b\x01\x7fd 1");
+        let clean = Line::new(b"a bc 2");
+
+        // Plain, the control byte sorts field 2 first; under `i` it is dropped
+        assert_eq!(
+            noisy.compare_with_keys(&clean, &[key("2,2")], None, &config),
+            Ordering::Less
+        );
+        assert_eq!(
+            noisy.compare_with_keys(&clean, &[key("2,2i")], None, &config),
+            Ordering::Greater
+        );
+        // Only field 2 is filtered: equal there, the next key decides
+        let equal = Line::new(b"z b\x01d\x02 0");
+        assert_eq!(
+            noisy.compare_with_keys(&equal, &[key("2,2i"), key("3,3")], None, &config),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_utf8_character_positions() {
+        use crate::config::{SortConfig, SortKey};
+
+        let key = SortKey::parse("1.3,1.4").unwrap();
+        let bytes = SortConfig::default();
+        let chars = SortConfig::default().with_utf8_positions(true);
+        let key_of = |text: &str, config: &SortConfig| {
+            Line::new(text.as_bytes())
+                .extract_key_with_config(&key, None, config)
+                .map(<[u8]>::to_vec)
+        };
+
+        // The C locale addresses bytes, splitting the two-byte "é"
+        assert_eq!(key_of("café x", &bytes), Some(b"f\xc3".to_vec()));
+        assert_eq!(key_of("café x", &chars), Some("fé".as_bytes().to_vec()));
+        assert_eq!(key_of("ñuñez", &chars), Some("ñe".as_bytes().to_vec()));
+        assert_eq!(
+            key_of("日本語です", &chars),
+            Some("語で".as_bytes().to_vec())
+        );
+        // Short fields and ASCII behave as before
+        assert_eq!(key_of("né", &chars), Some(Vec::new()));
+        assert_eq!(key_of("abcdef", &chars), key_of("abcdef", &bytes));
+
+        // Sorting on the 3rd and 4th characters
+        let keys = [key.clone()];
+        let mut lines = [
+            Line::new("ñuñez".as_bytes()),
+            Line::new("caféa".as_bytes()),
+            Line::new("zzab".as_bytes()),
+        ];
+        lines.sort_by(|a, b| a.compare_with_keys(b, &keys, None, &chars));
+        let sorted: Vec<&[u8]> = lines.iter().map(|l| unsafe { l.as_bytes() }).collect();
+        assert_eq!(
+            sorted,
+            ["zzab".as_bytes(), "caféa".as_bytes(), "ñuñez".as_bytes()]
+        );
+    }
+
+    #[test]
+    fn test_out_of_range_keys_fall_back_to_whole_line() {
+        use crate::config::{SortConfig, SortKey};
+
+        let keys = [
+            SortKey::parse("3,3").expect("Failed to parse key"),
+            SortKey::parse("2.5,2.5").expect("Failed to parse key"),
+        ];
+        let short = Line::new(b"zeta");
+        let clamped = Line::new(b"alpha x");
+        let other_short = Line::new(b"beta");
+
+        // Missing and clamped-empty keys compare as equal empty keys
+        let config = SortConfig::default();
+        let cmp =
+            |a: &Line, b: &Line, config: &SortConfig| a.compare_with_keys(b, &keys, None, config);
+        assert_eq!(cmp(&short, &clamped, &config), Ordering::Greater);
+        assert_eq!(cmp(&other_short, &short, &config), Ordering::Less);
+        assert_eq!(cmp(&short, &short, &config), Ordering::Equal);
+
+        // A line with a real key still sorts after the empty ones
+        let keyed = Line::new(b"a b c");
+        assert_eq!(cmp(&keyed, &short, &config), Ordering::Greater);
+
+        // -r reverses the fallback; -s leaves the lines tied
+        let reversed = SortConfig::default().with_reverse(true);
+        assert_eq!(cmp(&short, &clamped, &reversed), Ordering::Less);
+        let stable = SortConfig::default().with_stable(true);
+        assert_eq!(cmp(&short, &clamped, &stable), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_per_key_random_groups_and_is_seeded() {
+        use crate::config::{SortConfig, SortKey};
+
+        let data: Vec<String> = (0..40).map(|i| format!("{i} k{}", i % 5)).collect();
+        let keys = [SortKey::parse("2,2R").expect("Failed to parse key")];
+
+        let sort_with_seed = |seed: u64| -> Vec<String> {
+            let config = SortConfig::default().with_random_seed(Some(seed));
+            let mut lines: Vec<Line> = data.iter().map(|s| Line::new(s.as_bytes())).collect();
+            lines.sort_by(|a, b| a.compare_with_keys(b, &keys, None, &config));
+            lines
+                .iter()
+                .map(|line| String::from_utf8_lossy(unsafe { line.as_bytes() }).into_owned())
+                .collect()
+        };
+        let group_order = |sorted: &[String]| -> Vec<String> {
+            let mut order: Vec<String> = Vec::new();
+            for line in sorted {
+                let field = line.split(' ').nth(1).unwrap().to_string();
+                if order.last() != Some(&field) {
+                    // Each field-2 value forms a single contiguous group
+                    assert!(!order.contains(&field), "group {field} split in {sorted:?}");
+                    order.push(field);
+                }
+            }
+            order
+        };
+
+        let first = sort_with_seed(7);
+        assert_eq!(group_order(&first).len(), 5);
+        // Same seed, same order
+        assert_eq!(first, sort_with_seed(7));
+        // Different seeds shuffle the groups differently
+        let orders: std::collections::HashSet<Vec<String>> = (0..8)
+            .map(|seed| group_order(&sort_with_seed(seed)))
+            .collect();
+        assert!(orders.len() > 1);
+    }
 }
@@ -0,0 +1,64 @@
+//! Collation under `LC_ALL`, checked through the `sort` binary so that each
+//! locale lives in its own process instead of the shared unit test binary.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const INPUT: &str = "rose\nrésumé\nÉclair\neden\n";
+const BYTE_ORDER: &str = "eden\nrose\nrésumé\nÉclair\n";
+
+/// Output of `sort ARGS` over [`INPUT`] with `LC_ALL` set to `locale`
+fn sort_under(locale: &str, args: &[&str]) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_sort"))
+        .args(args)
+        .env("LC_ALL", locale)
+        .env_remove("LC_COLLATE")
+        .env_remove("LANG")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("run sort");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(INPUT.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{locale} {args:?}");
+    String::from_utf8(output.stdout).unwrap()
+}
+
+/// A collating UTF-8 locale that `locale -a` lists, if any
+fn installed_collating_locale() -> Option<&'static str> {
+    let listed = Command::new("locale").arg("-a").output().ok()?;
+    let normalize = |name: &str| name.to_ascii_lowercase().replace("utf-8", "utf8");
+    let listed: Vec<String> = String::from_utf8_lossy(&listed.stdout)
+        .lines()
+        .map(normalize)
+        .collect();
+    ["en_US.UTF-8", "de_DE.UTF-8", "fr_FR.UTF-8"]
+        .into_iter()
+        .find(|name| listed.contains(&normalize(name)))
+}
+
+#[test]
+fn test_collation_differs_from_byte_order() {
+    assert_eq!(sort_under("C", &[]), BYTE_ORDER);
+
+    // Skipped where no collating locale is installed
+    let Some(locale) = installed_collating_locale() else {
+        return;
+    };
+
+    // Accents are secondary to the base letters: "résumé" goes before
+    // "rose", though 'é' is encoded above 'o'
+    assert_eq!(
+        sort_under(locale, &[]),
+        "Éclair\neden\nrésumé\nrose\n",
+        "{locale}"
+    );
+    for flag in ["--byte-order", "--locale=C"] {
+        assert_eq!(sort_under(locale, &[flag]), BYTE_ORDER, "{locale} {flag}");
+    }
+}